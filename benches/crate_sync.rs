@@ -0,0 +1,58 @@
+// Compares writing the whole crate pack into the scene buffer with
+// writing only the crates hit since the last sync:
+//
+// cargo bench --bench crate_sync
+
+use breakout_zero::{
+    crates::{CrateKind, CratePack},
+    rendering::{InstanceAllocator, SceneBuffer},
+};
+use criterion::{criterion_group, criterion_main, Criterion};
+use zero::prelude::*;
+
+const ROWS: u32 = 20;
+const COLS: u32 = 30;
+
+// Crates can not be destroyed by the benchmark, so every hit changes a crate
+fn crate_pack(instance_allocator: &mut InstanceAllocator) -> CratePack {
+    CratePack::new(
+        Vector3::new(0.0, 0.0, 0.0),
+        ROWS,
+        COLS,
+        0.5,
+        0.25,
+        0.05,
+        0.05,
+        |_, _| [1.0; 4],
+        |_| 10,
+        |_, _| u32::MAX,
+        |_, _| CrateKind::Normal,
+        instance_allocator,
+    )
+}
+
+fn crate_sync(c: &mut Criterion) {
+    let mut instance_allocator = InstanceAllocator::default();
+    let mut crate_pack = crate_pack(&mut instance_allocator);
+    let mut scene = SceneBuffer::new(instance_allocator.allocated());
+
+    let mut group = c.benchmark_group("crate_sync");
+    group.bench_function("full", |b| {
+        b.iter(|| {
+            crate_pack.need_sync = true;
+            crate_pack.render_sync(&mut scene);
+        })
+    });
+    let mut next = 0;
+    group.bench_function("incremental", |b| {
+        b.iter(|| {
+            crate_pack.hit(next);
+            next = (next + 1) % (ROWS * COLS) as usize;
+            crate_pack.render_sync(&mut scene);
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, crate_sync);
+criterion_main!(benches);
@@ -0,0 +1,78 @@
+// Rings the terminal bell whenever the ball hits the platform or destroys
+// a crate, reacting to events through `Game::on_event` instead of the
+// events returned by `Game::update`:
+//
+// cargo run --example beep
+
+use std::io::Write;
+
+use breakout_zero::prelude::*;
+use zero::prelude::winit::{
+    event::{Event, KeyEvent, WindowEvent},
+    event_loop::{ControlFlow, EventLoop},
+    window::WindowBuilder,
+};
+
+fn main() {
+    let event_loop = EventLoop::new().unwrap();
+    let window = WindowBuilder::new().build(&event_loop).unwrap();
+    let mut game = match Game::try_with_config(&window, GameConfig::default()) {
+        Ok(game) => game,
+        Err(e) => {
+            eprintln!("Could not start the game: {e:?}");
+            std::process::exit(1);
+        }
+    };
+    game.on_event(Box::new(|event| {
+        if matches!(
+            event,
+            GameEvent::PaddleHit | GameEvent::CrateDestroyed { .. }
+        ) {
+            print!("\x07");
+            _ = std::io::stdout().flush();
+        }
+    }));
+
+    let mut last_render_time = std::time::Instant::now();
+    _ = event_loop.run(|event, target| {
+        target.set_control_flow(ControlFlow::Poll);
+        match event {
+            Event::WindowEvent {
+                ref event,
+                window_id,
+            } if window_id == window.id() => match event {
+                WindowEvent::CloseRequested => {
+                    game.on_exit();
+                    target.exit();
+                }
+                WindowEvent::KeyboardInput {
+                    event:
+                        KeyEvent {
+                            logical_key: key,
+                            state,
+                            repeat: false,
+                            ..
+                        },
+                    ..
+                } => game.handle_input(key, state),
+                WindowEvent::Resized(physical_size) => game.resize(*physical_size),
+                WindowEvent::RedrawRequested => {
+                    let now = std::time::Instant::now();
+                    let dt = (now - last_render_time).as_secs_f32();
+                    last_render_time = now;
+
+                    // Events were already handled by the callback
+                    game.update(dt);
+                    game.render_sync();
+                    if game.should_quit() || !game.render() {
+                        game.on_exit();
+                        target.exit();
+                    }
+                }
+                _ => {}
+            },
+            Event::AboutToWait => window.request_redraw(),
+            _ => {}
+        }
+    });
+}
@@ -0,0 +1,63 @@
+// Plays the default level without a window with a bot which keeps
+// the platform under the ball, feeding the simulation the same
+// `Input` the keyboard would, and prints what happens:
+//
+// cargo run --example bot [seed]
+
+use breakout_zero::{
+    config::GameConfig,
+    rendering::InstanceAllocator,
+    simulation::{GameEvent, Input, Simulation},
+};
+
+const DT: f32 = 1.0 / 120.0;
+// Game is stopped after this many seconds if it is not over by then
+const MAX_TIME: f32 = 300.0;
+
+fn follow_ball(simulation: &Simulation) -> Input {
+    let Some(ball) = simulation.balls.first() else {
+        return Input::default();
+    };
+    let platform = &simulation.platform;
+    let offset = ball.position().x - platform.position().x;
+    Input {
+        movement: (offset / (platform.width() / 4.0)).clamp(-1.0, 1.0),
+        launch: !ball.is_launched(),
+        super_shot: simulation.charge_meter.is_full(),
+    }
+}
+
+fn main() {
+    let seed = std::env::args()
+        .nth(1)
+        .and_then(|seed| seed.parse().ok())
+        .unwrap_or(0);
+    let config = GameConfig::default();
+    let mut simulation = Simulation::new(&config, None, seed, &mut InstanceAllocator::default());
+
+    let mut time = 0.0;
+    while time < MAX_TIME && !simulation.is_won() && !simulation.is_lost() {
+        let input = follow_ball(&simulation);
+        simulation.step(DT, &input);
+        time += DT;
+
+        for event in simulation.events.iter() {
+            match event {
+                GameEvent::CrateDestroyed { points, .. } => {
+                    println!("{time:7.2}s: crate destroyed, +{points}")
+                }
+                GameEvent::BallLost => println!("{time:7.2}s: ball lost"),
+                GameEvent::ExtraLife => println!("{time:7.2}s: extra life"),
+                GameEvent::LevelCleared => println!("{time:7.2}s: level cleared"),
+                _ => {}
+            }
+        }
+    }
+
+    println!(
+        "Seed {seed}: score {}, lives {}, {} crates left after {time:.2}s",
+        simulation.score(),
+        simulation.lives(),
+        simulation.crate_pack.remaining(),
+    );
+}
@@ -0,0 +1,41 @@
+// Vertex shader
+
+struct BackgroundColors {
+  top: vec4<f32>,
+  bottom: vec4<f32>,
+};
+@group(0) @binding(0)
+var<uniform> colors: BackgroundColors;
+
+struct VertexOutput {
+  @builtin(position) clip_position: vec4<f32>,
+  @location(0) tex_coords: vec2<f32>,
+};
+
+// Single triangle covering the whole screen, the parts
+// outside of it are clipped
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+  let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+
+  var out: VertexOutput;
+  // Furthest depth, behind everything else
+  out.clip_position = vec4<f32>(uv * 2.0 - 1.0, 1.0, 1.0);
+  // Texture rows go from top to bottom
+  out.tex_coords = vec2<f32>(uv.x, 1.0 - uv.y);
+  return out;
+}
+
+// Fragment shader
+
+@group(1) @binding(0)
+var t_background: texture_2d<f32>;
+@group(1) @binding(1)
+var s_background: sampler;
+
+@fragment
+fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
+  let texel = textureSample(t_background, s_background, vertex.tex_coords);
+  let color = mix(colors.top, colors.bottom, vertex.tex_coords.y);
+  return color * texel;
+}
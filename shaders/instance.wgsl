@@ -26,12 +26,16 @@ struct InstanceInput {
     @location(8) transform_3: vec4<f32>,
     @location(9) color: vec4<f32>,
     @location(10) disabled: i32,
+    // Sprite in the atlas as x, y, width and height
+    @location(11) uv_rect: vec4<f32>,
 };
 
 struct VertexOutput {
   @builtin(position) clip_position: vec4<f32>,
   @location(0) color: vec4<f32>,
   @location(1) disabled: i32,
+  @location(2) tex_coords: vec2<f32>,
+  @location(3) sprite_width: f32,
 };
 
 @vertex
@@ -51,6 +55,8 @@ fn vs_main(
   out.clip_position = camera.view_projection * world_position;
   out.color = instance.color;
   out.disabled = instance.disabled;
+  out.tex_coords = instance.uv_rect.xy + vertex.tex_coords * instance.uv_rect.zw;
+  out.sprite_width = instance.uv_rect.z;
   return out;
 }
 
@@ -63,3 +69,24 @@ fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
   }
   return vertex.color; 
 }
+
+// Textured fragment shader
+
+@group(1) @binding(0)
+var t_atlas: texture_2d<f32>;
+@group(1) @binding(1)
+var s_atlas: sampler;
+
+// Instances without a sprite keep the flat color
+@fragment
+fn fs_textured(vertex: VertexOutput) -> @location(0) vec4<f32> {
+  // Sampled before any branching, which sampling requires
+  let texel = textureSample(t_atlas, s_atlas, vertex.tex_coords);
+  if vertex.disabled != 0 {
+    discard;
+  }
+  if vertex.sprite_width == 0.0 {
+    return vertex.color;
+  }
+  return vertex.color * texel;
+}
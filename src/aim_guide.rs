@@ -0,0 +1,117 @@
+use zero::prelude::*;
+
+use crate::{
+    rendering::{InstanceUniform, Instances, InstancesRenderCommand},
+    simulation::Simulation,
+};
+
+// Bounces shown ahead of the ball
+const BOUNCES: u32 = 3;
+// Seconds of the ball flight shown ahead of it
+const MAX_TIME: f32 = 3.0;
+// Distance between the dots in world units
+const DOT_SPACING: f32 = 0.4;
+const DOT_SIZE: f32 = 0.08;
+const MAX_DOTS: u32 = 128;
+const ALPHA: f32 = 0.35;
+
+// Dotted line along the predicted path of each ball waiting
+// on the platform, helping beginners to aim the launch
+pub struct AimGuide {
+    enabled: bool,
+    instances: Instances,
+}
+
+impl AimGuide {
+    pub fn new(
+        renderer: &Renderer,
+        storage: &mut RenderStorage,
+        mesh_id: ResourceId,
+        enabled: bool,
+    ) -> Self {
+        let mut instances = Instances::new(renderer, storage, mesh_id, MAX_DOTS);
+        instances.instance_num = 0;
+        Self { enabled, instances }
+    }
+
+    pub fn toggle(&mut self) {
+        self.enabled = !self.enabled;
+    }
+
+    pub fn render_sync(
+        &mut self,
+        renderer: &Renderer,
+        storage: &RenderStorage,
+        simulation: &Simulation,
+        color: [f32; 4],
+    ) {
+        if !self.enabled {
+            self.instances.instance_num = 0;
+            return;
+        }
+
+        let color = [color[0], color[1], color[2], color[3] * ALPHA];
+        let dots = simulation
+            .balls
+            .iter()
+            .filter(|ball| !ball.is_launched())
+            .flat_map(|ball| {
+                let path = ball.predict_path(
+                    &simulation.border,
+                    &simulation.crate_pack,
+                    BOUNCES,
+                    MAX_TIME,
+                );
+                dots(&path)
+            })
+            .take(MAX_DOTS as usize)
+            .map(|position| dot(position, color))
+            .collect::<Vec<_>>();
+
+        self.instances.instance_num = dots.len() as u32;
+        self.instances
+            .instance_buffer_handle
+            .update(renderer, storage, 0, &dots);
+    }
+
+    pub fn render_command(
+        &self,
+        pipeline_id: ResourceId,
+        camera_bind_group: ResourceId,
+    ) -> InstancesRenderCommand {
+        self.instances
+            .render_command(pipeline_id, camera_bind_group)
+    }
+}
+
+// Points along the `path` polyline `DOT_SPACING` apart,
+// the spacing carries over the corners of the path
+fn dots(path: &[Vector2<f32>]) -> Vec<Vector2<f32>> {
+    let mut dots = vec![];
+    // Distance from the start of the segment to its first dot
+    let mut offset = DOT_SPACING;
+    for segment in path.windows(2) {
+        let direction = segment[1] - segment[0];
+        let length = direction.x.hypot(direction.y);
+        while offset <= length {
+            dots.push(segment[0] + direction * (offset / length));
+            offset += DOT_SPACING;
+        }
+        offset -= length;
+    }
+    dots
+}
+
+fn dot(position: Vector2<f32>, color: [f32; 4]) -> InstanceUniform {
+    InstanceUniform {
+        transform: Matrix4::from(&Transform {
+            translation: position.extend(0.0),
+            scale: Vector3::new(DOT_SIZE, DOT_SIZE, 1.0),
+            ..Default::default()
+        })
+        .into(),
+        color,
+        disabled: 0,
+        uv_rect: InstanceUniform::NO_SPRITE,
+    }
+}
@@ -0,0 +1,125 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SoundId {
+    Paddle,
+    Wall,
+    Break,
+}
+
+impl SoundId {
+    // All sounds in the order of their discriminants
+    #[cfg(feature = "audio")]
+    const ALL: [SoundId; 3] = [SoundId::Paddle, SoundId::Wall, SoundId::Break];
+
+    #[cfg(feature = "audio")]
+    fn path(&self) -> &'static str {
+        match self {
+            SoundId::Paddle => "./sounds/paddle.wav",
+            SoundId::Wall => "./sounds/wall.wav",
+            SoundId::Break => "./sounds/break.wav",
+        }
+    }
+}
+
+#[cfg(feature = "audio")]
+type Sound = rodio::source::Buffered<rodio::Decoder<std::io::BufReader<std::fs::File>>>;
+
+#[cfg(feature = "audio")]
+struct AudioOutput {
+    // Sounds only play while the stream is alive
+    _stream: rodio::OutputStream,
+    handle: rodio::OutputStreamHandle,
+    sounds: Vec<Sound>,
+}
+
+// Plays short sound effects. If there is no audio device or the samples
+// can not be loaded the player stays silent instead of failing the game.
+#[cfg(feature = "audio")]
+pub struct AudioPlayer {
+    output: Option<AudioOutput>,
+    // Multiplier of all sounds in [0, 1]
+    volume: f32,
+}
+
+#[cfg(feature = "audio")]
+impl AudioPlayer {
+    pub fn new() -> Self {
+        let output = match Self::open() {
+            Ok(output) => Some(output),
+            Err(e) => {
+                eprintln!("Audio is disabled: {e}");
+                None
+            }
+        };
+        Self {
+            output,
+            volume: 1.0,
+        }
+    }
+
+    fn open() -> Result<AudioOutput, Box<dyn std::error::Error>> {
+        use rodio::Source;
+
+        let (stream, handle) = rodio::OutputStream::try_default()?;
+        let sounds = SoundId::ALL
+            .iter()
+            .map(|sound| {
+                let file = std::fs::File::open(sound.path())?;
+                let decoder = rodio::Decoder::new(std::io::BufReader::new(file))?;
+                Ok(decoder.buffered())
+            })
+            .collect::<Result<Vec<_>, Box<dyn std::error::Error>>>()?;
+        Ok(AudioOutput {
+            _stream: stream,
+            handle,
+            sounds,
+        })
+    }
+
+    pub fn play(&self, sound: SoundId) {
+        use rodio::Source;
+
+        if let Some(output) = &self.output {
+            let source = output.sounds[sound as usize]
+                .clone()
+                .amplify(self.volume)
+                .convert_samples();
+            if let Err(e) = output.handle.play_raw(source) {
+                eprintln!("Failed to play {sound:?}: {e}");
+            }
+        }
+    }
+
+    // Sounds already playing keep their volume
+    pub fn set_volume(&mut self, volume: f32) {
+        self.volume = volume.clamp(0.0, 1.0);
+    }
+
+    // Closes the output stream, cutting off sounds still playing.
+    // The player stays silent afterwards.
+    pub fn stop(&mut self) {
+        self.output = None;
+    }
+}
+
+// Silent player used when the game is built without the `audio` feature
+#[cfg(not(feature = "audio"))]
+pub struct AudioPlayer;
+
+#[cfg(not(feature = "audio"))]
+impl AudioPlayer {
+    pub fn new() -> Self {
+        Self
+    }
+
+    pub fn play(&self, _sound: SoundId) {}
+
+    pub fn set_volume(&mut self, _volume: f32) {}
+
+    pub fn stop(&mut self) {}
+}
+
+impl Default for AudioPlayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,270 @@
+use zero::prelude::*;
+
+use crate::{
+    rendering::RenderTargets,
+    texture::{TextureError, TextureMaterial},
+};
+
+const BACKGROUND_SHADER: &str = "./shaders/background.wgsl";
+
+// Drawn over the whole viewport before everything else. The inside of the
+// border is drawn over it too, unless the theme `inner` color is transparent.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Background {
+    Solid([f32; 4]),
+    // Top and bottom colors blended vertically
+    Gradient([f32; 4], [f32; 4]),
+    // Image at the path stretched over the viewport
+    Texture(String),
+}
+
+impl Default for Background {
+    fn default() -> Self {
+        Self::Solid([0.0, 0.0, 0.0, 1.0])
+    }
+}
+
+impl Background {
+    // Colors multiplied by the texture, which is white
+    // for backgrounds without one
+    fn colors(&self) -> BackgroundColors {
+        match self {
+            Self::Solid(color) => BackgroundColors {
+                top: *color,
+                bottom: *color,
+            },
+            Self::Gradient(top, bottom) => BackgroundColors {
+                top: *top,
+                bottom: *bottom,
+            },
+            Self::Texture(_) => BackgroundColors {
+                top: [1.0; 4],
+                bottom: [1.0; 4],
+            },
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+struct BackgroundColors {
+    top: [f32; 4],
+    bottom: [f32; 4],
+}
+
+#[derive(Debug)]
+pub enum BackgroundError {
+    Texture(TextureError),
+    Shader { path: &'static str, error: String },
+}
+
+impl From<TextureError> for BackgroundError {
+    fn from(value: TextureError) -> Self {
+        Self::Texture(value)
+    }
+}
+
+// Pipeline drawing a single triangle covering the screen,
+// with the colors in group 0 and the texture in group 1
+pub struct BackgroundPass {
+    pipeline_id: ResourceId,
+    colors_bind_group: ResourceId,
+    texture_bind_group: ResourceId,
+}
+
+impl BackgroundPass {
+    pub fn new(
+        renderer: &Renderer,
+        storage: &mut RenderStorage,
+        texture_material: &TextureMaterial,
+        background: &Background,
+        sample_count: u32,
+    ) -> Result<Self, BackgroundError> {
+        let colors_layout =
+            renderer
+                .device()
+                .create_bind_group_layout(&BindGroupLayoutDescriptor {
+                    label: Some("background_colors_bind_group_layout"),
+                    entries: &[BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: BindingType::Buffer {
+                            ty: BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    }],
+                });
+
+        // The buffer is kept alive by the bind group
+        let colors = background.colors();
+        let colors_buffer = renderer.device().create_buffer(&BufferDescriptor {
+            label: Some("background_colors_buffer"),
+            size: std::mem::size_of::<BackgroundColors>() as BufferAddress,
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        renderer
+            .queue()
+            .write_buffer(&colors_buffer, 0, bytemuck::bytes_of(&colors));
+        let colors_bind_group = renderer.device().create_bind_group(&BindGroupDescriptor {
+            label: Some("background_colors_bind_group"),
+            layout: &colors_layout,
+            entries: &[BindGroupEntry {
+                binding: 0,
+                resource: colors_buffer.as_entire_binding(),
+            }],
+        });
+        let colors_bind_group = storage.insert_bind_group(colors_bind_group);
+
+        let texture_bind_group = match background {
+            Background::Texture(path) => texture_material.load(renderer, storage, path)?,
+            _ => texture_material.upload(
+                renderer,
+                storage,
+                "background_white_texture",
+                &image::RgbaImage::from_pixel(1, 1, image::Rgba([255; 4])),
+            ),
+        };
+
+        let pipeline_id = Self::build_pipeline(
+            renderer,
+            storage,
+            &colors_layout,
+            texture_material,
+            sample_count,
+        )?;
+        Ok(Self {
+            pipeline_id,
+            colors_bind_group,
+            texture_bind_group,
+        })
+    }
+
+    fn build_pipeline(
+        renderer: &Renderer,
+        storage: &mut RenderStorage,
+        colors_layout: &BindGroupLayout,
+        texture_material: &TextureMaterial,
+        sample_count: u32,
+    ) -> Result<ResourceId, BackgroundError> {
+        let shader_path = BACKGROUND_SHADER;
+        // The pipeline builder reads the shader itself and panics if it can not
+        std::fs::metadata(shader_path).map_err(|e| BackgroundError::Shader {
+            path: shader_path,
+            error: e.to_string(),
+        })?;
+        renderer.device().push_error_scope(ErrorFilter::Validation);
+        let pipeline = PipelineBuilder {
+            shader_path,
+            label: Some("background_pipeline"),
+            layout_descriptor: Some(&PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[colors_layout, texture_material.layout()],
+                push_constant_ranges: &[],
+            }),
+            // Vertices are generated from their indices in the shader
+            vertex_layouts: &[],
+            vertex_entry_point: "vs_main",
+            color_targets: Some(&[Some(ColorTargetState {
+                format: renderer.surface_format(),
+                blend: Some(BlendState::REPLACE),
+                write_mask: ColorWrites::ALL,
+            })]),
+            fragment_entry_point: "fs_main",
+            primitive: PrimitiveState {
+                topology: PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: FrontFace::Ccw,
+                cull_mode: None,
+                polygon_mode: PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            // Drawn first and without touching the depth buffer,
+            // so the scene is drawn over it as if it was not there
+            depth_stencil: Some(DepthStencilState {
+                format: RenderTargets::DEPTH_FORMAT,
+                depth_write_enabled: false,
+                depth_compare: CompareFunction::Always,
+                stencil: StencilState::default(),
+                bias: DepthBiasState::default(),
+            }),
+            multisample: MultisampleState {
+                count: sample_count,
+                ..Default::default()
+            },
+            multiview: None,
+        }
+        .build(renderer);
+        if let Some(error) = pollster::block_on(renderer.device().pop_error_scope()) {
+            return Err(BackgroundError::Shader {
+                path: shader_path,
+                error: error.to_string(),
+            });
+        }
+        Ok(storage.insert_pipeline(pipeline))
+    }
+
+    pub fn render_command(&self) -> BackgroundRenderCommand {
+        BackgroundRenderCommand {
+            pipeline_id: self.pipeline_id,
+            colors_bind_group: self.colors_bind_group,
+            texture_bind_group: self.texture_bind_group,
+        }
+    }
+}
+
+pub struct BackgroundRenderCommand {
+    pub pipeline_id: ResourceId,
+    pub colors_bind_group: ResourceId,
+    pub texture_bind_group: ResourceId,
+}
+
+impl RenderCommand for BackgroundRenderCommand {
+    fn execute<'a>(&self, render_pass: &mut RenderPass<'a>, storage: &'a CurrentFrameStorage) {
+        render_pass.set_pipeline(storage.get_pipeline(self.pipeline_id));
+        render_pass.set_bind_group(0, storage.get_bind_group(self.colors_bind_group), &[]);
+        render_pass.set_bind_group(1, storage.get_bind_group(self.texture_bind_group), &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn solid_background_has_the_same_colors() {
+        let color = [0.1, 0.2, 0.3, 1.0];
+        assert_eq!(
+            Background::Solid(color).colors(),
+            BackgroundColors {
+                top: color,
+                bottom: color,
+            }
+        );
+    }
+
+    #[test]
+    fn gradient_goes_from_top_to_bottom() {
+        let top = [1.0, 0.0, 0.0, 1.0];
+        let bottom = [0.0, 0.0, 1.0, 1.0];
+        assert_eq!(
+            Background::Gradient(top, bottom).colors(),
+            BackgroundColors { top, bottom }
+        );
+    }
+
+    #[test]
+    fn texture_background_is_not_tinted() {
+        assert_eq!(
+            Background::Texture("background.png".to_string()).colors(),
+            BackgroundColors {
+                top: [1.0; 4],
+                bottom: [1.0; 4],
+            }
+        );
+    }
+}
@@ -1,58 +1,351 @@
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
 use zero::{
     cgmath_imports::{Matrix4, Vector2, Vector3},
-    render::{
-        renderer::Renderer,
-        storage::{RenderStorage, ResourceId},
-    },
-    shapes::Circle,
     transform::Transform,
 };
 
 use crate::{
-    border::Border,
-    crates::CratePack,
-    physics::{Collider, Collision, Rectangle},
+    border::{Border, Wall},
+    crates::{CrateKind, CratePack},
+    physics::{Collider, Collision, CollisionResponse, GravityWell, Rectangle, Reflect},
     platform::Platform,
-    rendering::{InstanceUniform, Instances, InstancesRenderCommand},
+    rendering::{InstanceUniform, MeshKey},
 };
 
-pub struct Ball {
-    // game_object: GameObject,
-    instance: Instances,
+// Object the ball bounced off
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hit {
+    Border(Wall),
+    Platform,
+    Crate(usize),
+}
+
+// How the ball picks its direction besides bouncing
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum BallBehavior {
+    #[default]
+    Normal,
+    // Turns towards the densest cluster of crates left by at most
+    // `strength` radians per second, so it still bounces off walls
+    Homing {
+        strength: f32,
+    },
+}
+
+// Everything that changes while the ball moves,
+// the rest of the ball comes from the config
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct BallState {
+    pub position: [f32; 3],
+    pub velocity: [f32; 2],
+    pub spawn_velocity: [f32; 2],
+    pub velocity_magnitude: f32,
+    pub speed: f32,
+    pub plow_through: f32,
+    pub launched: bool,
+    pub angular_velocity: f32,
+    pub hue: f32,
+}
 
+pub struct Ball {
     transform: Transform,
     radius: f32,
     color: [f32; 4],
     velocity: Vector2<f32>,
     speed: f32,
+    plow_through: f32,
+    // Used for everything but walls and the top of the platform,
+    // unless the collider supplies its own response
+    pub response: Box<dyn CollisionResponse>,
+
+    spawn_velocity: Vector2<f32>,
+    // Magnitude of the velocity, kept constant over bounces,
+    // so only `speed` changes how fast the ball moves
+    velocity_magnitude: f32,
+    // Unlaunched balls wait on the platform
+    launched: bool,
+    // Radians from straight up the ball is launched at,
+    // positive angles lean right
+    launch_angle: f32,
+    // Smallest part of the speed that goes up or down after a
+    // wall bounce, so the ball can not bounce between walls forever
+    min_vertical_ratio: f32,
+    // Blends the platform bounce from the offset based one
+    // at 0 to the plain reflection at 1
+    bounce_assist: f32,
+
+    // Moving platform spins the ball and the spin
+    // curves its path, only if `spin_enabled` is set
+    spin_enabled: bool,
+    // Radians per second the velocity is rotated by,
+    // positive rotates it counterclockwise
+    angular_velocity: f32,
+
+    behavior: BallBehavior,
+
+    rainbow: bool,
+    rainbow_speed: f32,
+    hue: f32,
+
+    // Objects hit since the last drain
+    pub hits: Vec<Hit>,
+    #[cfg(feature = "debug")]
+    pub last_collision: Option<Collision>,
+
+    // Previous positions of the ball, newest first
+    trail: VecDeque<Vector3<f32>>,
+    trail_length: usize,
+
+    // Sprite in the texture atlas, see `InstanceUniform::uv_rect`
+    uv_rect: [f32; 4],
 }
 
 impl Ball {
+    // Upper bound on the number of bounces resolved in a single update
+    const MAX_COLLISIONS_PER_UPDATE: u32 = 4;
+    // Passes over the border and the platform to push the ball out of them
+    const MAX_OVERLAP_PASSES: u32 = 4;
+    const MAX_TRAIL_LENGTH: usize = 16;
+    // Upper bound on the bounces of a predicted path
+    const MAX_PREDICTED_BOUNCES: u32 = 16;
+    // Angular velocity gained per unit of the platform velocity
+    const SPIN_PER_PLATFORM_SPEED: f32 = 0.1;
+    // Rate at which the spin fades out
+    const SPIN_DECAY: f32 = 1.5;
+    // Maximum number of instances a single ball draws
+    pub const MAX_INSTANCES: u32 = Self::MAX_TRAIL_LENGTH as u32 + 1;
+    // Fewer segments do not make a closed shape
+    const MIN_SEGMENTS: u32 = 3;
+    const MIN_RADIUS: f32 = 0.01;
+    // Launches lean at most this many degrees away from straight
+    // up, so the ball never leaves sideways or into the platform
+    const MAX_LAUNCH_ANGLE: f32 = 75.0;
+
+    // Circle mesh of radius 1 shared by all balls and their trails.
+    // Every segment is a triangle drawn for each instance, so high
+    // counts cost more with many balls and long trails, while low
+    // counts give a retro look. Counts below 3 are raised to 3.
+    pub fn mesh(segments: u32) -> MeshKey {
+        if segments < Self::MIN_SEGMENTS {
+            eprintln!(
+                "Ball mesh needs at least {} segments, got {segments}",
+                Self::MIN_SEGMENTS
+            );
+        }
+        MeshKey::circle(1.0, segments.max(Self::MIN_SEGMENTS))
+    }
+
+    // Balls are drawn with a circle mesh of radius 1
+    // scaled by the ball radius. Non positive radii are
+    // raised to a small minimum.
     pub fn new(
-        renderer: &Renderer,
-        storage: &mut RenderStorage,
         translation: Vector3<f32>,
         radius: f32,
         color: [f32; 4],
         velocity: Vector2<f32>,
         speed: f32,
     ) -> Self {
-        let mesh = Circle::new(radius, 50);
-        let instance = Instances::new(renderer, storage, mesh, 1);
+        if radius <= 0.0 {
+            eprintln!("Ball radius must be positive, got {radius}");
+        }
+        let radius = radius.max(Self::MIN_RADIUS);
         let transform = Transform {
             translation,
+            scale: Vector3::new(radius, radius, 1.0),
             ..Default::default()
         };
         Self {
-            instance,
             transform,
             radius,
             color,
             velocity,
             speed,
+            plow_through: 0.0,
+            response: Box::new(Reflect),
+            spawn_velocity: velocity,
+            velocity_magnitude: velocity.x.hypot(velocity.y),
+            min_vertical_ratio: 0.0,
+            bounce_assist: 0.0,
+            launched: true,
+            launch_angle: 0.0,
+            spin_enabled: false,
+            angular_velocity: 0.0,
+            behavior: BallBehavior::Normal,
+            rainbow: false,
+            rainbow_speed: 0.0,
+            hue: 0.0,
+            hits: vec![],
+            #[cfg(feature = "debug")]
+            last_collision: None,
+            trail: VecDeque::with_capacity(Self::MAX_TRAIL_LENGTH),
+            trail_length: 0,
+            uv_rect: InstanceUniform::NO_SPRITE,
+        }
+    }
+
+    // Number of previous positions drawn behind the ball,
+    // clamped to `MAX_TRAIL_LENGTH`
+    pub fn set_trail_length(&mut self, n: usize) {
+        self.trail_length = n.min(Self::MAX_TRAIL_LENGTH);
+        self.trail.truncate(self.trail_length);
+    }
+
+    pub fn set_spin_enabled(&mut self, enabled: bool) {
+        self.spin_enabled = enabled;
+        if !enabled {
+            self.angular_velocity = 0.0;
+        }
+    }
+
+    pub fn state(&self) -> BallState {
+        BallState {
+            position: self.transform.translation.into(),
+            velocity: self.velocity.into(),
+            spawn_velocity: self.spawn_velocity.into(),
+            velocity_magnitude: self.velocity_magnitude,
+            speed: self.speed,
+            plow_through: self.plow_through,
+            launched: self.launched,
+            angular_velocity: self.angular_velocity,
+            hue: self.hue,
+        }
+    }
+
+    // The trail starts over from the restored position
+    pub fn set_state(&mut self, state: &BallState) {
+        self.transform.translation = state.position.into();
+        self.velocity = state.velocity.into();
+        self.spawn_velocity = state.spawn_velocity.into();
+        self.velocity_magnitude = state.velocity_magnitude;
+        self.speed = state.speed;
+        self.plow_through = state.plow_through;
+        self.launched = state.launched;
+        self.angular_velocity = state.angular_velocity;
+        self.hue = state.hue;
+        self.trail.clear();
+    }
+
+    pub fn set_behavior(&mut self, behavior: BallBehavior) {
+        self.behavior = behavior;
+    }
+
+    #[inline]
+    pub fn is_homing(&self) -> bool {
+        matches!(self.behavior, BallBehavior::Homing { .. })
+    }
+
+    // Sprite of the texture atlas, see `InstanceUniform::uv_rect`
+    pub fn set_sprite(&mut self, uv_rect: [f32; 4]) {
+        self.uv_rect = uv_rect;
+    }
+
+    pub fn set_color(&mut self, color: [f32; 4]) {
+        self.color = color;
+    }
+
+    // Collisions use the same radius the ball is drawn with.
+    // Non positive radii are raised to a small minimum.
+    pub fn set_radius(&mut self, radius: f32) {
+        self.radius = radius.max(Self::MIN_RADIUS);
+        self.transform.scale = Vector3::new(self.radius, self.radius, 1.0);
+    }
+
+    #[inline]
+    pub fn radius(&self) -> f32 {
+        self.radius
+    }
+
+    // Color the ball is drawn with, which cycles
+    // through the rainbow if it is enabled
+    pub fn color(&self) -> [f32; 4] {
+        if self.rainbow {
+            let [r, g, b] = hue_to_rgb(self.hue);
+            [r, g, b, self.color[3]]
+        } else {
+            self.color
         }
     }
 
+    pub fn set_min_vertical_ratio(&mut self, ratio: f32) {
+        self.min_vertical_ratio = ratio;
+    }
+
+    // Clamped to [0, 1]
+    pub fn set_bounce_assist(&mut self, assist: f32) {
+        self.bounce_assist = assist.clamp(0.0, 1.0);
+    }
+
+    pub fn set_rainbow(&mut self, rainbow: bool) {
+        self.rainbow = rainbow;
+    }
+
+    // Speed of the rainbow color cycle in degrees per second
+    pub fn set_rainbow_speed(&mut self, speed: f32) {
+        self.rainbow_speed = speed;
+    }
+
+    #[inline]
+    pub fn position(&self) -> Vector3<f32> {
+        self.transform.translation
+    }
+
+    #[inline]
+    pub fn velocity(&self) -> Vector2<f32> {
+        self.velocity
+    }
+
+    #[inline]
+    pub fn is_launched(&self) -> bool {
+        self.launched
+    }
+
+    #[inline]
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    pub fn set_speed(&mut self, speed: f32) {
+        self.speed = speed;
+    }
+
+    // Makes the ball rest on the platform until launched
+    pub fn set_launch_from_platform(&mut self, launch_from_platform: bool) {
+        self.launched = !launch_from_platform;
+    }
+
+    pub fn set_launch_angle(&mut self, angle: f32) {
+        self.launch_angle = angle;
+    }
+
+    // Leaves the platform at the launch angle turned by the `jitter`
+    // in radians, keeping the magnitude of the spawn velocity
+    pub fn launch(&mut self, jitter: f32) {
+        if !self.launched {
+            self.launched = true;
+            self.velocity = self.launch_velocity(self.launch_angle + jitter);
+        }
+    }
+
+    // Velocity of a launch at the `angle` from straight up,
+    // clamped to `MAX_LAUNCH_ANGLE`
+    fn launch_velocity(&self, angle: f32) -> Vector2<f32> {
+        let max_angle = Self::MAX_LAUNCH_ANGLE.to_radians();
+        let angle = angle.clamp(-max_angle, max_angle);
+        Vector2::new(angle.sin(), angle.cos()) * self.velocity_magnitude
+    }
+
+    #[inline]
+    pub fn is_plowing_through(&self) -> bool {
+        0.0 < self.plow_through
+    }
+
+    // While active the ball does not bounce off crates
+    pub fn set_plow_through(&mut self, duration: f32) {
+        self.plow_through = duration;
+    }
+
     #[inline]
     pub fn border(&self) -> Rectangle {
         Rectangle::from_center(
@@ -67,52 +360,412 @@ impl Ball {
         border: &Border,
         platform: &Platform,
         crate_pack: &mut CratePack,
+        gravity_wells: &[GravityWell],
         dt: f32,
     ) {
-        self.transform.translation.x += self.velocity.x * self.speed * dt;
-        self.transform.translation.y += self.velocity.y * self.speed * dt;
+        if self.rainbow {
+            self.hue = (self.hue + self.rainbow_speed * dt).rem_euclid(360.0);
+        }
+
+        if !self.launched {
+            let rest = platform.top_center() + platform.up() * self.radius;
+            self.transform.translation.x = rest.x;
+            self.transform.translation.y = rest.y;
+            return;
+        }
+
+        if 0 < self.trail_length {
+            self.trail.truncate(self.trail_length - 1);
+            self.trail.push_front(self.transform.translation);
+        }
 
-        self.check_collision(border);
-        self.check_collision(platform);
-        self.check_collision_mut(crate_pack);
+        self.apply_gravity_wells(gravity_wells, dt);
+        self.apply_spin(dt);
+
+        self.handle_platform_push(platform);
+
+        self.plow_through = (self.plow_through - dt).max(0.0);
+
+        self.move_and_collide(border, platform, crate_pack, dt);
+        self.resolve_overlaps(border, platform);
     }
 
-    fn check_collision(&mut self, collider: &impl Collider) {
-        if let Some(collision) = collider.collides(self) {
-            self.handle_collision(collision);
+    // A ball wedged between a wall and the platform can be pushed out of
+    // one into the other, so both are checked again until neither overlaps
+    // the ball, up to `MAX_OVERLAP_PASSES` times. Only the position changes,
+    // the bounces were already handled while moving.
+    fn resolve_overlaps(&mut self, border: &Border, platform: &Platform) {
+        for _ in 0..Self::MAX_OVERLAP_PASSES {
+            let mut overlapping = false;
+            if let Some(collision) = border.collides(self) {
+                self.push_out(&collision);
+                overlapping = true;
+            }
+            let center = self.transform.translation.truncate();
+            if let Some(collision) = platform.obb().collides_circle(center, self.radius) {
+                self.push_out(&collision);
+                overlapping = true;
+            }
+            if !overlapping {
+                return;
+            }
         }
     }
-    fn check_collision_mut(&mut self, collider: &mut impl Collider) {
-        if let Some(collision) = collider.collides_mut(self) {
-            self.handle_collision(collision);
+
+    // Moves the ball along its velocity stopping at each contact point
+    // on the way, so it can not tunnel through thin objects
+    fn move_and_collide(
+        &mut self,
+        border: &Border,
+        platform: &Platform,
+        crate_pack: &mut CratePack,
+        dt: f32,
+    ) {
+        let mut remaining = 1.0;
+        for _ in 0..Self::MAX_COLLISIONS_PER_UPDATE {
+            let displacement = self.velocity * self.speed * dt * remaining;
+            let center = self.transform.translation.truncate();
+            let Some((toi, collision, hits)) =
+                self.next_hit(center, displacement, border, Some(platform), crate_pack)
+            else {
+                self.transform.translation += displacement.extend(0.0);
+                return;
+            };
+
+            self.transform.translation += (displacement * toi).extend(0.0);
+            remaining *= 1.0 - toi;
+            self.hits.extend_from_slice(&hits);
+            #[cfg(feature = "debug")]
+            {
+                self.last_collision = Some(collision);
+            }
+            match hits[0] {
+                Hit::Border(_) => {
+                    self.handle_wall_collision(collision);
+                    self.enforce_min_vertical(self.min_vertical_ratio);
+                }
+                Hit::Platform => {
+                    // Hits on the top of the platform bounce depending on
+                    // where the ball landed, side hits simply reflect
+                    let up = platform.up();
+                    if 0.0 < collision.normal.x * up.x + collision.normal.y * up.y {
+                        self.push_out(&collision);
+                        let deflected = platform.bounce_velocity(
+                            self.transform.translation.truncate(),
+                            self.velocity_magnitude,
+                        );
+                        let reflected = Reflect.respond(self.velocity, &collision);
+                        self.velocity = deflected + (reflected - deflected) * self.bounce_assist;
+                        // Ball curves towards the direction the platform moves in
+                        if self.spin_enabled {
+                            self.angular_velocity =
+                                -platform.velocity_x() * Self::SPIN_PER_PLATFORM_SPEED;
+                        }
+                    } else {
+                        self.handle_collision(collision, platform.response());
+                    }
+                }
+                // All crates touched at once are hit, but the ball bounces
+                // only once off their combined collision
+                Hit::Crate(_) => {
+                    let mut bounce = !self.is_plowing_through();
+                    for &hit in hits.iter() {
+                        if let Hit::Crate(i) = hit {
+                            crate_pack.hit(i);
+                            // Even plowing through balls bounce off steel
+                            bounce |= crate_pack.crates[i].kind() == CrateKind::Steel;
+                        }
+                    }
+                    if bounce {
+                        self.handle_collision(collision, crate_pack.response());
+                    }
+                }
+            }
+            self.renormalize_velocity();
         }
     }
-    fn handle_collision(&mut self, collision: Collision) {
-        if collision.normal.x != 0.0 {
-            self.velocity.x *= -1.0;
+
+    // First object the ball touches while moving from the `center` along
+    // the `displacement`, without hitting it. Returns the fraction of the
+    // move at which it happens together with the collision. Crates touched
+    // at the same time are all returned with a single combined collision.
+    fn next_hit(
+        &self,
+        center: Vector2<f32>,
+        displacement: Vector2<f32>,
+        border: &Border,
+        platform: Option<&Platform>,
+        crate_pack: &CratePack,
+    ) -> Option<(f32, Collision, Vec<Hit>)> {
+        // Walls are flat, so the bounding rectangle
+        // of the ball is exact for them
+        let hits = [
+            border
+                .sweep(
+                    &Rectangle::from_center(center, self.radius * 2.0, self.radius * 2.0),
+                    displacement,
+                )
+                .map(|(toi, collision, walls)| {
+                    (toi, collision, walls.into_iter().map(Hit::Border).collect())
+                }),
+            platform.and_then(|platform| {
+                platform
+                    .obb()
+                    .sweep_circle(center, self.radius, displacement)
+                    .map(|(toi, collision)| (toi, collision, vec![Hit::Platform]))
+            }),
+            crate_pack
+                .sweep_circle(center, self.radius, displacement)
+                .map(|contact| {
+                    (
+                        contact.toi,
+                        contact.collision,
+                        contact.crates.into_iter().map(Hit::Crate).collect(),
+                    )
+                }),
+        ];
+        hits.into_iter()
+            .flatten()
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+    }
+
+    // Positions of the ball at each predicted bounce off the walls and
+    // crates, from the current position to the point where the prediction
+    // stops. Unlaunched balls are predicted as if they were launched now.
+    // Nothing is hit, so every crate is treated as one the ball bounces off.
+    // Bounces are capped by `MAX_PREDICTED_BOUNCES`, so degenerate geometry
+    // can not keep the prediction going.
+    pub fn predict_path(
+        &self,
+        border: &Border,
+        crate_pack: &CratePack,
+        max_bounces: u32,
+        max_time: f32,
+    ) -> Vec<Vector2<f32>> {
+        let mut center = self.transform.translation.truncate();
+        // Launches are predicted without the jitter
+        let mut velocity = if self.launched {
+            self.velocity
+        } else {
+            self.launch_velocity(self.launch_angle)
+        };
+        let mut time_left = max_time;
+        let max_bounces = max_bounces.min(Self::MAX_PREDICTED_BOUNCES);
+
+        let mut path = vec![center];
+        for bounce in 0..=max_bounces {
+            let displacement = velocity * self.speed * time_left;
+            let Some((toi, collision, hits)) =
+                self.next_hit(center, displacement, border, None, crate_pack)
+            else {
+                path.push(center + displacement);
+                break;
+            };
+            center += displacement * toi + collision.normal * collision.penetration;
+            time_left *= 1.0 - toi;
+            path.push(center);
+            if bounce == max_bounces {
+                break;
+            }
+
+            velocity = match hits[0] {
+                Hit::Border(_) => with_min_vertical(
+                    wall_bounce(velocity, collision.normal),
+                    self.min_vertical_ratio,
+                ),
+                _ => crate_pack
+                    .response()
+                    .unwrap_or(self.response.as_ref())
+                    .respond(velocity, &collision),
+            };
+            velocity = with_magnitude(velocity, self.velocity_magnitude);
+        }
+        path
+    }
+
+    // The platform can move sideways into the ball faster than the ball
+    // moves, so its own move is swept against the ball as well. A hit
+    // carries the ball along with the platform and sends it away from it.
+    fn handle_platform_push(&mut self, platform: &Platform) {
+        let center = self.transform.translation.truncate();
+        let Some((toi, collision)) = platform.sweep_circle(center, self.radius) else {
+            return;
+        };
+
+        let displacement = platform.displacement();
+        self.transform.translation += (displacement * (1.0 - toi)).extend(0.0);
+        self.push_out(&collision);
+        self.hits.push(Hit::Platform);
+
+        if self.velocity.x * displacement.x < 0.0 {
+            self.velocity.x = -self.velocity.x;
         }
-        if collision.normal.y != 0.0 {
-            self.velocity.y *= -1.0;
+        // Ball is pushed from below, so it should not keep falling into the platform
+        if 0.0 < collision.normal.y && self.velocity.y < 0.0 {
+            self.velocity.y = -self.velocity.y;
         }
+        self.renormalize_velocity();
     }
 
-    pub fn render_sync(&self, renderer: &Renderer, storage: &RenderStorage) {
-        let data = InstanceUniform {
-            transform: Matrix4::from(&self.transform).into(),
-            color: self.color,
-            disabled: 0,
+    // Separates overlapping balls along the line between their centers
+    // and exchanges their velocity components along it, like an elastic
+    // collision of equal masses. Balls moving apart are only separated.
+    fn collide_with(&mut self, other: &mut Ball) {
+        let delta = (self.transform.translation - other.transform.translation).truncate();
+        let distance = delta.x.hypot(delta.y);
+        let penetration = self.radius + other.radius - distance;
+        if penetration <= 0.0 || distance == 0.0 {
+            return;
+        }
+
+        let normal = delta / distance;
+        self.transform.translation += (normal * penetration / 2.0).extend(0.0);
+        other.transform.translation -= (normal * penetration / 2.0).extend(0.0);
+
+        let self_normal = self.velocity.x * normal.x + self.velocity.y * normal.y;
+        let other_normal = other.velocity.x * normal.x + other.velocity.y * normal.y;
+        if self_normal - other_normal < 0.0 {
+            self.velocity += normal * (other_normal - self_normal);
+            other.velocity += normal * (self_normal - other_normal);
+            self.renormalize_velocity();
+            other.renormalize_velocity();
+        }
+    }
+
+    // Rescales the velocity back to `velocity_magnitude`,
+    // so rounding errors of bounces do not add up over time
+    fn renormalize_velocity(&mut self) {
+        self.velocity = with_magnitude(self.velocity, self.velocity_magnitude);
+    }
+
+    // Bends the velocity towards the wells while
+    // keeping its magnitude unchanged
+    fn apply_gravity_wells(&mut self, gravity_wells: &[GravityWell], dt: f32) {
+        if gravity_wells.is_empty() {
+            return;
+        }
+
+        for well in gravity_wells {
+            self.velocity += well.acceleration(self.transform.translation.truncate()) * dt;
+        }
+        self.renormalize_velocity();
+    }
+
+    // Rotates the velocity by the spin, which keeps its magnitude,
+    // and lets the spin fade out
+    fn apply_spin(&mut self, dt: f32) {
+        if self.angular_velocity == 0.0 {
+            return;
+        }
+
+        self.velocity = rotated(self.velocity, self.angular_velocity * dt);
+        self.angular_velocity *= (-Self::SPIN_DECAY * dt).exp();
+        if self.angular_velocity.abs() < 1e-3 {
+            self.angular_velocity = 0.0;
+        }
+    }
+
+    // Turns homing balls towards the `target` before they move.
+    // The turn is capped by the strength, so a target behind
+    // the ball takes a while to face.
+    pub fn steer(&mut self, target: Vector2<f32>, dt: f32) {
+        let BallBehavior::Homing { strength } = self.behavior else {
+            return;
         };
-        self.instance
-            .instance_buffer_handle
-            .update(renderer, storage, 0, &[data]);
+        if !self.launched {
+            return;
+        }
+        let to_target = target - self.transform.translation.truncate();
+        let angle = (self.velocity.x * to_target.y - self.velocity.y * to_target.x)
+            .atan2(self.velocity.x * to_target.x + self.velocity.y * to_target.y);
+        let max_turn = strength * dt;
+        self.velocity = rotated(self.velocity, angle.clamp(-max_turn, max_turn));
     }
 
-    pub fn render_command(
-        &self,
-        pipeline_id: ResourceId,
-        camera_bind_group: ResourceId,
-    ) -> InstancesRenderCommand {
-        self.instance.render_command(pipeline_id, camera_bind_group)
+    // Rotates the velocity by the `angle` in radians counterclockwise
+    pub fn nudge(&mut self, angle: f32) {
+        self.velocity = rotated(self.velocity, angle);
+    }
+
+    // Moves the ball along the collision normal, so it
+    // does not overlap with the collider anymore
+    fn push_out(&mut self, collision: &Collision) {
+        self.transform.translation += (collision.normal * collision.penetration).extend(0.0);
+    }
+
+    // Pushes the ball out of the collider and applies the collider
+    // response, falling back to the one of the ball
+    fn handle_collision(&mut self, collision: Collision, response: Option<&dyn CollisionResponse>) {
+        self.push_out(&collision);
+
+        let response = response.unwrap_or(self.response.as_ref());
+        self.velocity = response.respond(self.velocity, &collision);
+    }
+
+    // Walls meet at right angles, so instead of reflecting about the
+    // normal every axis the ball moves into is flipped separately.
+    // This sends the ball straight back out of corners.
+    fn handle_wall_collision(&mut self, collision: Collision) {
+        self.push_out(&collision);
+        self.velocity = wall_bounce(self.velocity, collision.normal);
+    }
+
+    // Raises the vertical part of the velocity to at least `min_ratio`
+    // of the speed keeping the speed itself. Balls moving straight
+    // sideways are sent down. Ratios are clamped to [0, 1].
+    pub fn enforce_min_vertical(&mut self, min_ratio: f32) {
+        self.velocity = with_min_vertical(self.velocity, min_ratio);
+    }
+
+    // Instances of the trail followed by the ball itself, so the ball
+    // is drawn on top. Trail instances fade out and shrink the older they are.
+    pub fn instances(&self) -> impl Iterator<Item = InstanceUniform> + '_ {
+        let color = self.color();
+
+        let trail_len = self.trail.len();
+        self.trail
+            .iter()
+            .enumerate()
+            .rev()
+            .map(move |(i, translation)| {
+                let fade = 1.0 - (i + 1) as f32 / (trail_len + 1) as f32;
+                let scale = self.radius * fade;
+                InstanceUniform {
+                    transform: Matrix4::from(&Transform {
+                        translation: *translation,
+                        scale: Vector3::new(scale, scale, 1.0),
+                        ..Default::default()
+                    })
+                    .into(),
+                    color: [color[0], color[1], color[2], color[3] * fade * 0.5],
+                    disabled: 0,
+                    uv_rect: self.uv_rect,
+                }
+            })
+            .chain(std::iter::once(InstanceUniform {
+                transform: Matrix4::from(&self.transform).into(),
+                color,
+                disabled: 0,
+                uv_rect: self.uv_rect,
+            }))
+    }
+}
+
+// Pairs of balls which may touch. All pairs are checked, which is
+// fine for the few balls of multi ball, but it could be replaced
+// with a grid lookup without touching the resolution.
+fn ball_pairs(n: usize) -> impl Iterator<Item = (usize, usize)> {
+    (0..n).flat_map(move |i| (i + 1..n).map(move |j| (i, j)))
+}
+
+// Resolves collisions between launched balls
+pub fn resolve_ball_collisions(balls: &mut [Ball]) {
+    for (i, j) in ball_pairs(balls.len()) {
+        let (left, right) = balls.split_at_mut(j);
+        let (a, b) = (&mut left[i], &mut right[0]);
+        if a.launched && b.launched {
+            a.collide_with(b);
+        }
     }
 }
 
@@ -122,3 +775,574 @@ impl Collider for Ball {
         Some(self.border())
     }
 }
+
+// Flips every component of the `velocity` going into the wall with the `normal`
+fn wall_bounce(velocity: Vector2<f32>, normal: Vector2<f32>) -> Vector2<f32> {
+    let mut velocity = velocity;
+    if velocity.x * normal.x < 0.0 {
+        velocity.x = -velocity.x;
+    }
+    if velocity.y * normal.y < 0.0 {
+        velocity.y = -velocity.y;
+    }
+    velocity
+}
+
+// See `Ball::enforce_min_vertical`
+fn with_min_vertical(velocity: Vector2<f32>, min_ratio: f32) -> Vector2<f32> {
+    let magnitude = velocity.x.hypot(velocity.y);
+    let min_y = magnitude * min_ratio.clamp(0.0, 1.0);
+    if min_y <= velocity.y.abs() {
+        return velocity;
+    }
+    let direction_y = if 0.0 < velocity.y { 1.0 } else { -1.0 };
+    Vector2::new(
+        (magnitude * magnitude - min_y * min_y)
+            .max(0.0)
+            .sqrt()
+            .copysign(velocity.x),
+        min_y * direction_y,
+    )
+}
+
+// Rotates the `velocity` by the `angle` in radians counterclockwise
+fn rotated(velocity: Vector2<f32>, angle: f32) -> Vector2<f32> {
+    let (sin, cos) = angle.sin_cos();
+    Vector2 {
+        x: velocity.x * cos - velocity.y * sin,
+        y: velocity.x * sin + velocity.y * cos,
+    }
+}
+
+// Zero velocities are returned as is
+fn with_magnitude(velocity: Vector2<f32>, magnitude: f32) -> Vector2<f32> {
+    let current = velocity.x.hypot(velocity.y);
+    if current != 0.0 {
+        velocity * (magnitude / current)
+    } else {
+        velocity
+    }
+}
+
+// Converts hue in degrees to a fully saturated and bright rgb color
+fn hue_to_rgb(hue: f32) -> [f32; 3] {
+    let sector = hue / 60.0;
+    let x = 1.0 - (sector % 2.0 - 1.0).abs();
+    match sector as u32 {
+        0 => [1.0, x, 0.0],
+        1 => [x, 1.0, 0.0],
+        2 => [0.0, 1.0, x],
+        3 => [0.0, x, 1.0],
+        4 => [x, 0.0, 1.0],
+        _ => [1.0, 0.0, x],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        border::Walls,
+        level::{Level, LevelCrate},
+        platform::PlatformState,
+        rendering::InstanceAllocator,
+    };
+
+    // Ball of radius 0.5 and speed 1
+    fn ball(position: [f32; 2], velocity: [f32; 2]) -> Ball {
+        Ball::new(
+            Vector3::new(position[0], position[1], 0.0),
+            0.5,
+            [1.0; 4],
+            velocity.into(),
+            1.0,
+        )
+    }
+
+    // Closed border of the default size with a resting platform
+    // at the bottom and no crates
+    fn world() -> (Border, Platform, CratePack) {
+        let mut allocator = InstanceAllocator::default();
+        let border = Border::new(
+            15.0,
+            20.0,
+            0.2,
+            [1.0; 4],
+            [0.0; 4],
+            Walls::ALL,
+            false,
+            &mut allocator,
+        );
+        let platform = Platform::new(
+            Vector3::new(0.0, -8.0, 0.0),
+            2.0,
+            0.5,
+            [1.0; 4],
+            5.0,
+            40.0,
+            30.0,
+            &mut allocator,
+        );
+        (border, platform, crates(&[], [1.0, 1.0], &mut allocator))
+    }
+
+    // Crates of the `size` centered at the `positions`, each with 1 health
+    fn crates(
+        positions: &[[f32; 2]],
+        size: [f32; 2],
+        allocator: &mut InstanceAllocator,
+    ) -> CratePack {
+        CratePack::from_level(&level(positions, size), allocator)
+    }
+
+    // Level of the default size with crates of the `size` at the `positions`
+    fn level(positions: &[[f32; 2]], size: [f32; 2]) -> Level {
+        let crates = positions
+            .iter()
+            .map(|&position| LevelCrate::test(position, 10))
+            .collect();
+        Level::test(size, crates)
+    }
+
+    #[test]
+    fn gravity_well_bends_the_path() {
+        let well = GravityWell {
+            pos: Vector2::new(1.0, 1.0),
+            radius: 5.0,
+            strength: 2.0,
+        };
+        let mut ball = ball([0.0, 0.0], [1.0, 0.0]);
+
+        for _ in 0..10 {
+            ball.apply_gravity_wells(&[well], 0.01);
+        }
+
+        let velocity = ball.velocity();
+        assert!(0.0 < velocity.y);
+        assert!((velocity.x.hypot(velocity.y) - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn no_gravity_wells_keep_the_path() {
+        let mut ball = ball([0.0, 0.0], [1.0, 0.0]);
+
+        ball.apply_gravity_wells(&[], 0.01);
+
+        assert_eq!(ball.velocity(), Vector2::new(1.0, 0.0));
+    }
+
+    #[test]
+    fn rainbow_hue_advances_with_time_and_wraps() {
+        let (border, platform, mut crate_pack) = world();
+        let mut ball = ball([0.0, 0.0], [0.0, 1.0]);
+        ball.set_rainbow(true);
+        ball.set_rainbow_speed(90.0);
+
+        let mut colors: Vec<[f32; 4]> = vec![];
+        for i in 0..8 {
+            assert_eq!(ball.hue, i as f32 * 45.0);
+            let color = ball.instances().last().unwrap().color;
+            assert!(colors.iter().all(|c| *c != color));
+            colors.push(color);
+            ball.update(&border, &platform, &mut crate_pack, &[], 0.5);
+        }
+        assert_eq!(ball.hue, 0.0);
+        assert_eq!(ball.instances().last().unwrap().color, colors[0]);
+    }
+
+    #[test]
+    fn rainbow_does_not_change_the_movement() {
+        let (border, platform, mut crate_pack) = world();
+        let mut plain = ball([0.0, 0.0], [0.6, 0.8]);
+        let mut rainbow = ball([0.0, 0.0], [0.6, 0.8]);
+        rainbow.set_rainbow(true);
+        rainbow.set_rainbow_speed(90.0);
+
+        for _ in 0..1000 {
+            plain.update(&border, &platform, &mut crate_pack, &[], 0.01);
+            rainbow.update(&border, &platform, &mut crate_pack, &[], 0.01);
+        }
+
+        assert_eq!(plain.position(), rainbow.position());
+        assert_eq!(plain.velocity(), rainbow.velocity());
+    }
+
+    #[test]
+    fn fast_ball_does_not_tunnel_through_thin_crates() {
+        let (border, platform, _) = world();
+        let mut crate_pack = crates(&[[0.0, 0.0]], [0.2, 2.0], &mut InstanceAllocator::default());
+        // Moves further than the ball and the crate are wide in a single update
+        let mut ball = ball([-3.0, 0.0], [1.0, 0.0]);
+        ball.set_speed(100.0);
+
+        for _ in 0..2 {
+            ball.update(&border, &platform, &mut crate_pack, &[], 1.0 / 60.0);
+        }
+
+        assert!(ball.hits.contains(&Hit::Crate(0)));
+        assert!(crate_pack.crates[0].is_disabled());
+        assert!(ball.velocity().x < 0.0);
+        assert!(ball.position().x < 0.0);
+    }
+
+    #[test]
+    fn crates_of_different_sizes_collide_with_their_own_extents() {
+        let (border, platform, _) = world();
+        let mut level = level(&[[-3.0, 0.0], [3.0, 0.0]], [4.0, 2.0]);
+        level.crates[1].size = Some([0.5, 0.5]);
+        let mut crate_pack = CratePack::from_level(&level, &mut InstanceAllocator::default());
+        // Only within the extents of the large crate
+        let mut large = ball([-1.5, -3.0], [0.0, 1.0]);
+        // Only within the extents the small crate would have if it was large
+        let mut past_small = ball([3.9, -3.0], [0.0, 1.0]);
+        let mut small = ball([3.0, -3.0], [0.0, 1.0]);
+
+        for _ in 0..30 {
+            large.update(&border, &platform, &mut crate_pack, &[], 0.1);
+            past_small.update(&border, &platform, &mut crate_pack, &[], 0.1);
+        }
+        assert!(large.hits.contains(&Hit::Crate(0)));
+        assert!(crate_pack.crates[0].is_disabled());
+        assert!(past_small.hits.is_empty());
+        assert!(!crate_pack.crates[1].is_disabled());
+
+        for _ in 0..30 {
+            small.update(&border, &platform, &mut crate_pack, &[], 0.1);
+        }
+        assert!(small.hits.contains(&Hit::Crate(1)));
+        assert!(crate_pack.crates[1].is_disabled());
+    }
+
+    #[test]
+    fn ball_fired_into_the_top_right_corner_leaves_down_left() {
+        let (border, platform, mut crate_pack) = world();
+        // Touches both walls at the same time halfway through the update
+        let mut ball = ball([6.5, 9.0], [1.0, 1.0]);
+
+        ball.update(&border, &platform, &mut crate_pack, &[], 1.0);
+
+        assert!(ball.hits.contains(&Hit::Border(Wall::Right)));
+        assert!(ball.hits.contains(&Hit::Border(Wall::Top)));
+        assert!(ball.velocity().x < 0.0);
+        assert!(ball.velocity().y < 0.0);
+        assert!(border.border().contains(ball.position().truncate()));
+    }
+
+    #[test]
+    fn bounces_keep_the_speed() {
+        use rand::{rngs::SmallRng, Rng, SeedableRng};
+
+        let (border, platform, _) = world();
+        let positions = [-4.5, -1.5, 1.5, 4.5]
+            .into_iter()
+            .flat_map(|x| [0.0, 3.0, 6.0].map(|y| [x, y]))
+            .collect::<Vec<_>>();
+        let mut crate_pack = crates(&positions, [1.0, 0.5], &mut InstanceAllocator::default());
+        // Steel crates are never destroyed, so the ball keeps bouncing off them
+        for c in crate_pack.crates.iter_mut() {
+            c.set_kind(CrateKind::Steel);
+        }
+
+        let mut rng = SmallRng::seed_from_u64(0);
+        let mut bounces = 0;
+        while bounces < 10_000 {
+            let angle = rng.gen_range(0.0..std::f32::consts::TAU);
+            let mut ball = ball([0.0, -3.0], [angle.cos(), angle.sin()]);
+            ball.set_speed(25.0);
+            for _ in 0..1000 {
+                ball.update(&border, &platform, &mut crate_pack, &[], 0.02);
+                bounces += ball.hits.drain(..).count();
+                let velocity = ball.velocity();
+                assert!((velocity.x.hypot(velocity.y) - 1.0).abs() < 1e-4);
+            }
+        }
+    }
+
+    #[test]
+    fn moving_platform_spins_the_ball_towards_its_movement() {
+        let (border, mut platform, mut crate_pack) = world();
+        // Platform moving right without changing its position this update
+        platform.set_state(&PlatformState {
+            velocity_x: 5.0,
+            ..platform.state()
+        });
+        let mut spinning = ball([0.0, -7.0], [0.0, -1.0]);
+        spinning.set_spin_enabled(true);
+        let mut plain = ball([0.0, -7.0], [0.0, -1.0]);
+
+        for _ in 0..20 {
+            spinning.update(&border, &platform, &mut crate_pack, &[], 0.1);
+            plain.update(&border, &platform, &mut crate_pack, &[], 0.1);
+        }
+
+        assert!(spinning.hits.contains(&Hit::Platform));
+        assert!(0.0 < spinning.velocity().x);
+        assert!(0.0 < spinning.position().x);
+        assert_eq!(plain.velocity().x, 0.0);
+        assert_eq!(plain.position().x, 0.0);
+    }
+
+    #[test]
+    fn spin_fades_out() {
+        let (border, platform, mut crate_pack) = world();
+        let mut ball = ball([0.0, 0.0], [0.0, 1.0]);
+        ball.angular_velocity = 1.0;
+
+        for _ in 0..1000 {
+            ball.update(&border, &platform, &mut crate_pack, &[], 0.01);
+        }
+
+        assert_eq!(ball.angular_velocity, 0.0);
+    }
+
+    #[test]
+    fn fast_platform_pushes_a_ball_out_of_its_way() {
+        let (border, mut platform, mut crate_pack) = world();
+        // Platform jumped further than it is wide during its last update
+        let dt = 1.0 / 60.0;
+        platform.set_state(&PlatformState {
+            x: 2.5,
+            previous_x: 0.0,
+            velocity_x: 2.5 / dt,
+            ..platform.state()
+        });
+        // Almost still ball right of where the platform was
+        let mut ball = ball([1.6, -8.0], [-1.0, 0.0]);
+        ball.set_speed(0.01);
+
+        ball.update(&border, &platform, &mut crate_pack, &[], dt);
+
+        let center = ball.position().truncate();
+        assert!(ball.hits.contains(&Hit::Platform));
+        assert!(platform.obb().collides_circle(center, 0.5).is_none());
+        assert!(platform.border().right() < center.x);
+        assert!(0.0 < ball.velocity().x);
+    }
+
+    #[test]
+    fn ball_bounces_off_steel_crates() {
+        let (border, platform, _) = world();
+        let mut crate_pack = crates(&[[0.0, 2.0]], [1.0, 1.0], &mut InstanceAllocator::default());
+        crate_pack.crates[0].set_kind(CrateKind::Steel);
+        let mut ball = ball([0.0, 0.0], [0.0, 1.0]);
+
+        for _ in 0..30 {
+            ball.update(&border, &platform, &mut crate_pack, &[], 0.1);
+        }
+
+        assert!(ball.hits.contains(&Hit::Crate(0)));
+        assert!(ball.velocity().y < 0.0);
+        assert!(!crate_pack.crates[0].is_disabled());
+    }
+
+    #[test]
+    fn balls_on_a_collision_course_swap_velocities() {
+        // Already overlapping and moving towards each other
+        let mut balls = [ball([-0.4, 0.0], [0.6, 0.8]), ball([0.4, 0.0], [-0.6, 0.8])];
+
+        resolve_ball_collisions(&mut balls);
+
+        let [a, b] = &balls;
+        let close =
+            |v: Vector2<f32>, [x, y]: [f32; 2]| (v.x - x).abs() < 1e-5 && (v.y - y).abs() < 1e-5;
+        assert!(close(a.velocity(), [-0.6, 0.8]));
+        assert!(close(b.velocity(), [0.6, 0.8]));
+        // Pushed apart until they only touch
+        assert!(1.0 - 1e-5 <= b.position().x - a.position().x);
+    }
+
+    #[test]
+    fn meshes_with_less_than_three_segments_are_clamped() {
+        assert_eq!(Ball::mesh(2), MeshKey::circle(1.0, 3));
+        assert_eq!(Ball::mesh(8), MeshKey::circle(1.0, 8));
+    }
+
+    #[test]
+    fn non_positive_radius_is_raised() {
+        let ball = Ball::new(
+            Vector3::new(0.0, 0.0, 0.0),
+            0.0,
+            [1.0; 4],
+            Vector2::new(0.0, 1.0),
+            1.0,
+        );
+        assert!(0.0 < ball.radius);
+    }
+
+    #[test]
+    fn near_horizontal_ball_leaves_a_side_wall_at_the_min_vertical_ratio() {
+        let (border, platform, mut crate_pack) = world();
+        let mut ball = ball([5.0, 0.0], [1.0, 0.01]);
+        ball.set_speed(10.0);
+        ball.set_min_vertical_ratio(0.2);
+
+        ball.update(&border, &platform, &mut crate_pack, &[], 0.5);
+
+        assert!(ball.hits.contains(&Hit::Border(Wall::Right)));
+        let velocity = ball.velocity();
+        let magnitude = velocity.x.hypot(velocity.y);
+        assert!(velocity.x < 0.0);
+        assert!(0.2 - 1e-5 <= velocity.y / magnitude);
+        assert!((magnitude - 1.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn ball_straddling_two_crates_hits_both_and_bounces_once() {
+        let (border, platform, _) = world();
+        let mut crate_pack = crates(
+            &[[-0.5, 2.0], [0.5, 2.0]],
+            [1.0, 0.5],
+            &mut InstanceAllocator::default(),
+        );
+        // Centered on the seam between the crates
+        let mut ball = ball([0.0, 0.0], [0.0, 1.0]);
+
+        ball.update(&border, &platform, &mut crate_pack, &[], 2.0);
+
+        assert!(ball.hits.contains(&Hit::Crate(0)));
+        assert!(ball.hits.contains(&Hit::Crate(1)));
+        assert!(crate_pack.crates.iter().all(|c| c.is_disabled()));
+        // Bouncing off each crate separately would send it back up
+        assert!(ball.velocity().x.abs() < 1e-5);
+        assert!(ball.velocity().y < 0.0);
+        assert!(ball.position().y < 1.25);
+    }
+
+    #[test]
+    fn ball_falling_onto_a_45_degree_crate_leaves_along_its_face() {
+        let (border, platform, _) = world();
+        let mut crate_pack = crates(&[[0.0, 0.0]], [2.0, 0.5], &mut InstanceAllocator::default());
+        crate_pack.crates[0].set_rotation(std::f32::consts::FRAC_PI_4);
+        // Above the middle of the upper left face, which faces up and left
+        let face = crate_pack.crates[0].obb().axes()[1] * 0.25;
+        let mut ball = ball([face.x, 3.0], [0.0, -1.0]);
+
+        ball.update(&border, &platform, &mut crate_pack, &[], 2.5);
+
+        assert!(ball.hits.contains(&Hit::Crate(0)));
+        assert!((ball.velocity().x + 1.0).abs() < 1e-4);
+        assert!(ball.velocity().y.abs() < 1e-4);
+    }
+
+    #[test]
+    fn ball_wedged_between_a_wall_and_the_platform_is_pushed_out_of_both() {
+        let mut allocator = InstanceAllocator::default();
+        let (border, _, _) = world();
+        // Right edge of the platform touches the right wall
+        let platform = Platform::new(
+            Vector3::new(6.5, -8.0, 0.0),
+            2.0,
+            0.5,
+            [1.0; 4],
+            5.0,
+            40.0,
+            30.0,
+            &mut allocator,
+        );
+        let mut ball = ball([7.2, -7.5], [0.0, 1.0]);
+
+        ball.resolve_overlaps(&border, &platform);
+
+        let center = ball.position().truncate();
+        let overlaps = |collision: Option<Collision>| {
+            collision.is_some_and(|collision| 1e-4 < collision.penetration)
+        };
+        assert!(!overlaps(border.collides(&ball)));
+        assert!(!overlaps(platform.obb().collides_circle(center, 0.5)));
+    }
+
+    #[test]
+    fn homing_ball_curves_towards_the_last_crate() {
+        let (border, platform, _) = world();
+        let mut allocator = InstanceAllocator::default();
+        let mut normal_crates = crates(&[[3.0, 5.0]], [1.0, 0.5], &mut allocator);
+        let mut homing_crates = crates(&[[3.0, 5.0]], [1.0, 0.5], &mut allocator);
+        let mut normal = ball([0.0, 0.0], [0.0, 1.0]);
+        let mut homing = ball([0.0, 0.0], [0.0, 1.0]);
+        normal.set_speed(2.0);
+        homing.set_speed(2.0);
+        homing.set_behavior(BallBehavior::Homing { strength: 2.0 });
+
+        let target = homing_crates.densest_cluster(2.0).unwrap();
+        homing.steer(target, 0.01);
+        // Turns by at most the strength
+        assert!(0.0 < homing.velocity().x && homing.velocity().x <= (0.02_f32).sin() + 1e-5);
+
+        for _ in 0..400 {
+            normal.update(&border, &platform, &mut normal_crates, &[], 0.01);
+            if let Some(target) = homing_crates.densest_cluster(2.0) {
+                homing.steer(target, 0.01);
+            }
+            homing.update(&border, &platform, &mut homing_crates, &[], 0.01);
+        }
+
+        // Straight up and down misses the crate
+        assert!(!normal_crates.crates[0].is_disabled());
+        assert!(homing_crates.crates[0].is_disabled());
+    }
+
+    #[test]
+    fn launch_angle_is_clamped_away_from_the_sides() {
+        let mut ball = ball([0.0, 0.0], [0.0, 1.0]);
+        ball.set_launch_from_platform(true);
+        ball.set_launch_angle(85.0_f32.to_radians());
+
+        ball.launch(0.0);
+
+        let velocity = ball.velocity();
+        let angle = velocity.x.atan2(velocity.y).to_degrees();
+        assert!((angle - Ball::MAX_LAUNCH_ANGLE).abs() < 1e-3);
+    }
+
+    #[test]
+    fn bounce_assist_blends_the_deflection_into_the_reflection() {
+        let (border, platform, mut crate_pack) = world();
+        // Falls straight down onto the right half of the platform
+        let mut bounced = |assist| {
+            let mut ball = ball([0.5, -7.2], [0.0, -1.0]);
+            ball.set_bounce_assist(assist);
+            ball.update(&border, &platform, &mut crate_pack, &[], 0.1);
+            assert_eq!(ball.hits, vec![Hit::Platform]);
+            ball.velocity()
+        };
+
+        let reflected = bounced(1.0);
+        assert!(reflected.x.abs() < 1e-5);
+        assert!((reflected.y - 1.0).abs() < 1e-5);
+
+        let deflected = bounced(0.0);
+        let expected = platform.bounce_velocity(Vector2::new(0.5, -7.25), 1.0);
+        assert!(0.0 < expected.x);
+        assert!((deflected - expected).x.abs() < 1e-5);
+        assert!((deflected - expected).y.abs() < 1e-5);
+    }
+
+    #[test]
+    fn ball_dropped_onto_a_crate_bounces_once() {
+        let (border, platform, _) = world();
+        let mut allocator = InstanceAllocator::default();
+        let mut crate_pack = crates(&[[0.0, 0.0]], [1.5, 1.0], &mut allocator);
+        // Steel crates stay, so a second flip would have something to hit
+        crate_pack.crates[0].set_kind(CrateKind::Steel);
+
+        let mut dropped = ball([0.0, 1.2], [0.0, -1.0]);
+        dropped.update(&border, &platform, &mut crate_pack, &[], 0.5);
+        assert_eq!(dropped.hits, vec![Hit::Crate(0)]);
+        assert_eq!(dropped.velocity(), Vector2::new(0.0, 1.0));
+        // Touches the top of the crate after 0.2 and goes back up for the rest
+        assert!((dropped.position().y - 1.3).abs() < 1e-5);
+
+        // Ball already sunk into the crate is pushed out by the
+        // penetration before moving the rest of the way
+        let mut sunk = ball([0.0, 0.9], [0.0, -1.0]);
+        sunk.update(&border, &platform, &mut crate_pack, &[], 0.1);
+        assert_eq!(sunk.hits, vec![Hit::Crate(0)]);
+        assert_eq!(sunk.velocity(), Vector2::new(0.0, 1.0));
+        assert!((sunk.position().y - 1.1).abs() < 1e-5);
+
+        sunk.hits.clear();
+        sunk.update(&border, &platform, &mut crate_pack, &[], 0.1);
+        assert!(sunk.hits.is_empty());
+        assert!((sunk.position().y - 1.2).abs() < 1e-5);
+    }
+}
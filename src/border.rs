@@ -1,21 +1,64 @@
 use zero::{
     cgmath_imports::{Matrix4, Vector2, Vector3},
-    render::{renderer::Renderer, storage::RenderStorage},
     transform::Transform,
 };
 
 use crate::{
     physics::{Collider, Collision, Rectangle},
-    rendering::{InstanceUniform, Instances},
+    rendering::{InstanceAllocator, InstanceRange, InstanceUniform, SceneBuffer},
 };
 
+// Side of the border
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Wall {
+    Left,
+    Right,
+    Bottom,
+    Top,
+}
+
+// Walls objects bounce off, the missing ones let objects leave the border
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Walls {
+    pub left: bool,
+    pub right: bool,
+    pub bottom: bool,
+    pub top: bool,
+}
+
+impl Walls {
+    pub const ALL: Self = Self {
+        left: true,
+        right: true,
+        bottom: true,
+        top: true,
+    };
+    pub const OPEN_BOTTOM: Self = Self {
+        bottom: false,
+        ..Self::ALL
+    };
+
+    #[inline]
+    pub fn has(&self, wall: Wall) -> bool {
+        match wall {
+            Wall::Left => self.left,
+            Wall::Right => self.right,
+            Wall::Bottom => self.bottom,
+            Wall::Top => self.top,
+        }
+    }
+}
+
 pub struct Border {
     width: f32,
     height: f32,
     thickness: f32,
     border_color: [f32; 4],
     inner_color: [f32; 4],
-    instance_buffer_offset: u64,
+    walls: Walls,
+    // Objects falling below the platform are out of play
+    deadly_bottom: bool,
+    instance_range: InstanceRange,
 }
 
 impl Border {
@@ -25,7 +68,9 @@ impl Border {
         thickness: f32,
         border_color: [f32; 4],
         inner_color: [f32; 4],
-        instance_buffer_offset: u64,
+        walls: Walls,
+        deadly_bottom: bool,
+        instance_allocator: &mut InstanceAllocator,
     ) -> Self {
         Self {
             width,
@@ -33,45 +78,180 @@ impl Border {
             thickness,
             border_color,
             inner_color,
-            instance_buffer_offset,
+            walls,
+            deadly_bottom,
+            // Inside of the border and each of the walls
+            instance_range: instance_allocator.allocate(5),
         }
     }
 
+    pub fn set_colors(&mut self, border_color: [f32; 4], inner_color: [f32; 4]) {
+        self.border_color = border_color;
+        self.inner_color = inner_color;
+    }
+
     #[inline]
     pub fn border(&self) -> Rectangle {
         Rectangle::from_center(Vector2::new(0.0, 0.0), self.width, self.height)
     }
 
-    pub fn render_sync(&self, renderer: &Renderer, storage: &RenderStorage, boxes: &Instances) {
-        let data = [
-            InstanceUniform {
-                transform: Matrix4::from(&Transform {
-                    translation: Vector3::new(0.0, 0.0, -0.1),
-                    scale: Vector3::new(self.width, self.height, 1.0),
-                    ..Default::default()
-                })
-                .into(),
-                color: self.border_color,
-                disabled: 0,
-            },
-            InstanceUniform {
-                transform: Matrix4::from(&Transform {
-                    translation: Vector3::new(0.0, 0.0, -0.01),
-                    scale: Vector3::new(
-                        self.width - self.thickness,
-                        self.height - self.thickness,
-                        1.0,
-                    ),
-                    ..Default::default()
+    // Objects below the `floor` are out of play if the bottom is deadly.
+    // Objects entirely outside of the border left it through a missing wall.
+    pub fn is_out_of_play(&self, rect: &Rectangle, floor: f32) -> bool {
+        let this_rect = self.border();
+        (self.deadly_bottom && rect.pos().y < floor)
+            || rect.right() < this_rect.left()
+            || this_rect.right() < rect.left()
+            || rect.top() < this_rect.bot()
+            || this_rect.top() < rect.bot()
+    }
+
+    // Hits on both walls closer in time than this are treated as a corner hit
+    const CORNER_TOI_EPSILON: f32 = 1e-5;
+
+    // Moves `rect` along the `displacement` and returns the fraction of it
+    // after which `rect` touches one of the walls from the inside together
+    // with the walls it touches. Corner hits touch two walls and return
+    // a diagonal normal pointing out of the corner.
+    pub fn sweep(
+        &self,
+        rect: &Rectangle,
+        displacement: Vector2<f32>,
+    ) -> Option<(f32, Collision, Vec<Wall>)> {
+        let this_rect = self.border();
+
+        let x_hit = if displacement.x < 0.0 && self.walls.left {
+            Some((
+                this_rect.left() - rect.left(),
+                this_rect.left(),
+                1.0,
+                Wall::Left,
+            ))
+        } else if 0.0 < displacement.x && self.walls.right {
+            Some((
+                this_rect.right() - rect.right(),
+                this_rect.right(),
+                -1.0,
+                Wall::Right,
+            ))
+        } else {
+            None
+        }
+        .map(|(distance, x, normal, wall)| {
+            let toi = (distance / displacement.x).max(0.0);
+            (
+                toi,
+                Collision {
+                    pos: Vector2 {
+                        x,
+                        y: rect.pos().y + displacement.y * toi,
+                    },
+                    normal: Vector2 { x: normal, y: 0.0 },
+                    penetration: 0.0,
+                },
+                wall,
+            )
+        });
+
+        let y_hit = if displacement.y < 0.0 && self.walls.bottom {
+            Some((
+                this_rect.bot() - rect.bot(),
+                this_rect.bot(),
+                1.0,
+                Wall::Bottom,
+            ))
+        } else if 0.0 < displacement.y && self.walls.top {
+            Some((
+                this_rect.top() - rect.top(),
+                this_rect.top(),
+                -1.0,
+                Wall::Top,
+            ))
+        } else {
+            None
+        }
+        .map(|(distance, y, normal, wall)| {
+            let toi = (distance / displacement.y).max(0.0);
+            (
+                toi,
+                Collision {
+                    pos: Vector2 {
+                        x: rect.pos().x + displacement.x * toi,
+                        y,
+                    },
+                    normal: Vector2 { x: 0.0, y: normal },
+                    penetration: 0.0,
+                },
+                wall,
+            )
+        });
+
+        match (x_hit, y_hit) {
+            (Some((x_toi, x_collision, x_wall)), Some((y_toi, y_collision, y_wall)))
+                if (x_toi - y_toi).abs() <= Self::CORNER_TOI_EPSILON =>
+            {
+                let toi = x_toi.min(y_toi);
+                (toi <= 1.0).then(|| {
+                    (
+                        toi,
+                        Collision {
+                            pos: Vector2 {
+                                x: x_collision.pos.x,
+                                y: y_collision.pos.y,
+                            },
+                            normal: (x_collision.normal + y_collision.normal)
+                                / std::f32::consts::SQRT_2,
+                            penetration: 0.0,
+                        },
+                        vec![x_wall, y_wall],
+                    )
                 })
-                .into(),
-                color: self.inner_color,
-                disabled: 0,
-            },
-        ];
-        boxes
-            .instance_buffer_handle
-            .update(renderer, storage, self.instance_buffer_offset, &data);
+            }
+            (x_hit, y_hit) => [x_hit, y_hit]
+                .into_iter()
+                .flatten()
+                .filter(|(toi, _, _)| *toi <= 1.0)
+                .min_by(|a, b| a.0.total_cmp(&b.0))
+                .map(|(toi, collision, wall)| (toi, collision, vec![wall])),
+        }
+    }
+
+    // Missing walls are not drawn
+    pub fn render_sync(&self, scene: &mut SceneBuffer) {
+        let inner = InstanceUniform {
+            transform: Matrix4::from(&Transform {
+                translation: Vector3::new(0.0, 0.0, -0.1),
+                scale: Vector3::new(self.width, self.height, 1.0),
+                ..Default::default()
+            })
+            .into(),
+            color: self.inner_color,
+            disabled: 0,
+            uv_rect: InstanceUniform::NO_SPRITE,
+        };
+        // Walls are drawn inside of the border with half of its thickness
+        let wall_thickness = self.thickness / 2.0;
+        let x = (self.width - wall_thickness) / 2.0;
+        let y = (self.height - wall_thickness) / 2.0;
+        let walls = [
+            (Wall::Left, -x, 0.0, wall_thickness, self.height),
+            (Wall::Right, x, 0.0, wall_thickness, self.height),
+            (Wall::Bottom, 0.0, -y, self.width, wall_thickness),
+            (Wall::Top, 0.0, y, self.width, wall_thickness),
+        ]
+        .map(|(wall, x, y, width, height)| InstanceUniform {
+            transform: Matrix4::from(&Transform {
+                translation: Vector3::new(x, y, -0.01),
+                scale: Vector3::new(width, height, 1.0),
+                ..Default::default()
+            })
+            .into(),
+            color: self.border_color,
+            disabled: u32::from(!self.walls.has(wall)),
+            uv_rect: InstanceUniform::NO_SPRITE,
+        });
+        scene.write(self.instance_range, 0, &[inner]);
+        scene.write(self.instance_range, 1, &walls);
     }
 }
 
@@ -92,40 +272,113 @@ impl Collider for Border {
         let this_rect = this_rect.unwrap();
         let other_rect = other_rect.unwrap();
 
-        if other_rect.left() < this_rect.left() {
-            Some(Collision {
-                pos: Vector2 {
-                    x: this_rect.left(),
-                    y: other_rect.pos().y,
-                },
-                normal: Vector2 { x: 1.0, y: 0.0 },
-            })
-        } else if this_rect.right() < other_rect.right() {
-            Some(Collision {
+        // Objects stuck in a corner overlap with two walls at once,
+        // so both overlaps are combined into a single collision
+        let x_overlap = if other_rect.left() < this_rect.left() && self.walls.left {
+            Some((this_rect.left(), 1.0, this_rect.left() - other_rect.left()))
+        } else if this_rect.right() < other_rect.right() && self.walls.right {
+            Some((
+                this_rect.right(),
+                -1.0,
+                other_rect.right() - this_rect.right(),
+            ))
+        } else {
+            None
+        };
+        let y_overlap = if other_rect.bot() < this_rect.bot() && self.walls.bottom {
+            Some((this_rect.bot(), 1.0, this_rect.bot() - other_rect.bot()))
+        } else if this_rect.top() < other_rect.top() && self.walls.top {
+            Some((this_rect.top(), -1.0, other_rect.top() - this_rect.top()))
+        } else {
+            None
+        };
+
+        match (x_overlap, y_overlap) {
+            (None, None) => None,
+            (Some((x, normal, penetration)), None) => Some(Collision {
                 pos: Vector2 {
-                    x: this_rect.right(),
+                    x,
                     y: other_rect.pos().y,
                 },
-                normal: Vector2 { x: -1.0, y: 0.0 },
-            })
-        } else if other_rect.top() < this_rect.top() {
-            Some(Collision {
-                pos: Vector2 {
-                    x: other_rect.pos().x,
-                    y: this_rect.top(),
-                },
-                normal: Vector2 { x: 0.0, y: 1.0 },
-            })
-        } else if this_rect.bot() < other_rect.bot() {
-            Some(Collision {
+                normal: Vector2 { x: normal, y: 0.0 },
+                penetration,
+            }),
+            (None, Some((y, normal, penetration))) => Some(Collision {
                 pos: Vector2 {
                     x: other_rect.pos().x,
-                    y: this_rect.bot(),
+                    y,
                 },
-                normal: Vector2 { x: 0.0, y: -1.0 },
-            })
-        } else {
-            None
+                normal: Vector2 { x: 0.0, y: normal },
+                penetration,
+            }),
+            // Normal is scaled, so moving along it by the penetration
+            // resolves the overlap on both axes
+            (Some((x, normal_x, penetration_x)), Some((y, normal_y, penetration_y))) => {
+                let penetration = penetration_x.hypot(penetration_y);
+                Some(Collision {
+                    pos: Vector2 { x, y },
+                    normal: Vector2 {
+                        x: normal_x * penetration_x / penetration,
+                        y: normal_y * penetration_y / penetration,
+                    },
+                    penetration,
+                })
+            }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn border(walls: Walls) -> Border {
+        Border::new(
+            15.0,
+            20.0,
+            0.2,
+            [1.0; 4],
+            [0.0; 4],
+            walls,
+            false,
+            &mut InstanceAllocator::default(),
+        )
+    }
+
+    #[test]
+    fn corner_sweep_hits_both_walls() {
+        let border = border(Walls::ALL);
+        let rect = Rectangle::from_center(Vector2::new(6.5, 9.0), 1.0, 1.0);
+
+        let (toi, collision, walls) = border.sweep(&rect, Vector2::new(1.0, 1.0)).unwrap();
+
+        assert!((toi - 0.5).abs() < 1e-5);
+        assert_eq!(walls, vec![Wall::Right, Wall::Top]);
+        assert!(collision.normal.x < 0.0 && collision.normal.y < 0.0);
+        assert!((collision.normal.x - collision.normal.y).abs() < 1e-5);
+    }
+
+    #[test]
+    fn open_bottom_lets_objects_through() {
+        let rect = Rectangle::from_center(Vector2::new(0.0, -9.0), 1.0, 1.0);
+        let down = Vector2::new(0.0, -3.0);
+
+        let (_, _, walls) = border(Walls::ALL).sweep(&rect, down).unwrap();
+        assert_eq!(walls, vec![Wall::Bottom]);
+
+        let border = border(Walls::OPEN_BOTTOM);
+        assert!(border.sweep(&rect, down).is_none());
+        let below = Rectangle::from_center(Vector2::new(0.0, -10.2), 1.0, 1.0);
+        assert!(border.collides(&below).is_none());
+    }
+
+    #[test]
+    fn objects_leaving_through_a_missing_wall_are_out_of_play() {
+        let border = border(Walls::OPEN_BOTTOM);
+        let inside = Rectangle::from_center(Vector2::new(0.0, -9.0), 1.0, 1.0);
+        let below = Rectangle::from_center(Vector2::new(0.0, -12.0), 1.0, 1.0);
+
+        assert!(!border.is_out_of_play(&inside, -20.0));
+        assert!(border.is_out_of_play(&below, -20.0));
+    }
+}
@@ -0,0 +1,30 @@
+use crate::simulation::{Input, Simulation};
+
+// Platform offset from the ball, relative to the platform
+// half width, at which the bot moves at full speed
+const FULL_SPEED_OFFSET: f32 = 0.5;
+
+// Trivial controller which keeps the platform under the lowest ball
+// and launches balls waiting on the platform. It only reads the
+// simulation, so it works the same with and without a window.
+pub fn follow_ball(simulation: &Simulation) -> Input {
+    let platform = &simulation.platform;
+    let Some(ball) = simulation
+        .balls
+        .iter()
+        .min_by(|a, b| a.position().y.total_cmp(&b.position().y))
+    else {
+        return Input::default();
+    };
+
+    let offset = (ball.position().x - platform.position().x) / (platform.width() / 2.0);
+    let mut movement = (offset / FULL_SPEED_OFFSET).clamp(-1.0, 1.0);
+    if simulation.controls_inverted() {
+        movement = -movement;
+    }
+    Input {
+        movement,
+        launch: simulation.balls.iter().any(|ball| !ball.is_launched()),
+        super_shot: false,
+    }
+}
@@ -0,0 +1,81 @@
+pub struct ChargeMeter {
+    charge: u32,
+    capacity: u32,
+    plow_through_duration: f32,
+}
+
+impl ChargeMeter {
+    pub fn new(capacity: u32, plow_through_duration: f32) -> Self {
+        Self {
+            charge: 0,
+            capacity,
+            plow_through_duration,
+        }
+    }
+
+    #[inline]
+    pub fn charge(&self) -> u32 {
+        self.charge
+    }
+
+    #[inline]
+    pub fn capacity(&self) -> u32 {
+        self.capacity
+    }
+
+    #[inline]
+    pub fn is_full(&self) -> bool {
+        self.capacity <= self.charge
+    }
+
+    // Charge above the capacity is dropped
+    pub fn set_charge(&mut self, charge: u32) {
+        self.charge = charge.min(self.capacity);
+    }
+
+    pub fn fill(&mut self, destroyed_crates: u32) {
+        self.charge = (self.charge + destroyed_crates).min(self.capacity);
+    }
+
+    // Drains the meter if it is full and returns
+    // the duration of the super shot
+    pub fn activate(&mut self) -> Option<f32> {
+        if self.is_full() {
+            self.charge = 0;
+            Some(self.plow_through_duration)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn destroyed_crates_fill_the_meter_up_to_the_capacity() {
+        let mut meter = ChargeMeter::new(5, 2.0);
+
+        meter.fill(3);
+        assert_eq!(meter.charge(), 3);
+        assert!(!meter.is_full());
+
+        meter.fill(4);
+        assert_eq!(meter.charge(), 5);
+        assert!(meter.is_full());
+    }
+
+    #[test]
+    fn only_a_full_meter_can_be_activated() {
+        let mut meter = ChargeMeter::new(5, 2.0);
+
+        meter.fill(4);
+        assert_eq!(meter.activate(), None);
+        assert_eq!(meter.charge(), 4);
+
+        meter.fill(1);
+        assert_eq!(meter.activate(), Some(2.0));
+        assert_eq!(meter.charge(), 0);
+    }
+}
@@ -0,0 +1,301 @@
+use crate::{
+    background::Background, ball::BallBehavior, border::Walls, crates::CrateKind,
+    physics::GravityWell, theme::Theme,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BorderConfig {
+    pub width: f32,
+    pub height: f32,
+    pub thickness: f32,
+    // Walls the ball bounces off, it leaves through the missing ones
+    pub walls: Walls,
+    // Balls falling below the platform are lost. Otherwise they are
+    // only lost once they leave the border through a missing wall.
+    pub deadly_bottom: bool,
+}
+
+impl Default for BorderConfig {
+    fn default() -> Self {
+        Self {
+            width: 15.0,
+            height: 20.0,
+            thickness: 0.2,
+            walls: Walls::OPEN_BOTTOM,
+            deadly_bottom: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlatformConfig {
+    pub position: [f32; 3],
+    pub width: f32,
+    pub height: f32,
+    pub speed: f32,
+    // Units per second squared the platform speeds up with while
+    // moved and slows down with once released. 0 disables momentum.
+    pub accel: f32,
+    pub friction: f32,
+    // Tilt in degrees counterclockwise
+    pub rotation: f32,
+}
+
+impl Default for PlatformConfig {
+    fn default() -> Self {
+        Self {
+            position: [0.0, -8.0, 0.0],
+            width: 2.0,
+            height: 0.5,
+            speed: 5.0,
+            accel: 40.0,
+            friction: 30.0,
+            rotation: 0.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BallConfig {
+    pub position: [f32; 3],
+    // Only its length is used when the ball is launched
+    // from the platform, the direction comes from the angle
+    pub velocity: [f32; 2],
+    // Degrees from straight up, positive angles lean right.
+    // Each launch is turned by a random angle of up to
+    // `launch_jitter` degrees either way.
+    pub launch_angle: f32,
+    pub launch_jitter: f32,
+    pub radius: f32,
+    // Segments of the ball circle mesh, see `Ball::mesh`
+    pub segments: u32,
+    pub speed: f32,
+    pub trail_length: usize,
+    // Cycles the ball color through the rainbow
+    pub rainbow: bool,
+    // Moving platform spins the ball, which curves its path
+    pub spin: bool,
+    // Smallest part of the ball speed that goes up or down
+    // after a wall bounce, in [0, 1]. 0 disables it.
+    pub min_vertical_ratio: f32,
+    // Makes platform bounces more predictable by blending them from
+    // the offset based angle at 0 to the plain reflection at 1
+    pub bounce_assist: f32,
+    // Degrees per second
+    pub rainbow_speed: f32,
+    // Homing balls seek the crates left in a novelty mode
+    pub behavior: BallBehavior,
+    // Balls faster than this speed leave faint particles behind
+    // them, on top of the trail. Disabled if not set.
+    pub speed_trail: Option<f32>,
+    // Part of the radius the ball grows by with every step of the combo,
+    // up to `max_combo_growth`. Shrinks back once the combo resets.
+    // 0 disables it.
+    pub combo_growth: f32,
+    pub max_combo_growth: f32,
+}
+
+impl Default for BallConfig {
+    fn default() -> Self {
+        Self {
+            position: [0.0, -7.0, 0.0],
+            velocity: [2.5, 2.5],
+            launch_angle: 45.0,
+            launch_jitter: 5.0,
+            radius: 0.5,
+            segments: 50,
+            speed: 1.0,
+            trail_length: 8,
+            rainbow: false,
+            spin: false,
+            min_vertical_ratio: 0.2,
+            bounce_assist: 0.0,
+            rainbow_speed: 90.0,
+            behavior: BallBehavior::Normal,
+            speed_trail: None,
+            combo_growth: 0.0,
+            max_combo_growth: 0.5,
+        }
+    }
+}
+
+// Crate grid used when the game is started without a level
+#[derive(Debug, Clone, PartialEq)]
+pub struct CratesConfig {
+    pub center: [f32; 3],
+    pub rows: u32,
+    pub cols: u32,
+    pub width: f32,
+    pub height: f32,
+    pub gap_x: f32,
+    pub gap_y: f32,
+    // Crates in the row `n` counting from the bottom
+    // give `points_per_row * (n + 1)` points
+    pub points_per_row: u32,
+    // Health of crates in each row counting from the bottom.
+    // Rows past the end of the list have 1 health.
+    pub row_health: Vec<u32>,
+    // Kinds of crates in each row counting from the bottom.
+    // Rows past the end of the list are normal.
+    pub row_kinds: Vec<CrateKind>,
+    // Optional image to build the crate grid from. Each non transparent
+    // pixel becomes a crate, translucent pixels give crates with more
+    // health. Must not have more than `rows * cols` crates.
+    pub image: Option<String>,
+    // Optional shape of the crate grid, only set cells have crates.
+    // Rows go from top to bottom. Ignored if the image is set.
+    pub mask: Option<Vec<Vec<bool>>>,
+}
+
+impl Default for CratesConfig {
+    fn default() -> Self {
+        Self {
+            center: [0.0, 4.0, 0.0],
+            rows: 5,
+            cols: 7,
+            width: 1.5,
+            height: 1.0,
+            gap_x: 0.2,
+            gap_y: 0.2,
+            points_per_row: 10,
+            row_health: vec![1, 1, 1, 1, 2],
+            row_kinds: vec![],
+            image: None,
+            mask: None,
+        }
+    }
+}
+
+// Sprites of the crates and the ball taken from a single image.
+// Sprites are x, y, width and height in UV coordinates of the
+// atlas and are tinted by the colors of the objects.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TexturesConfig {
+    pub atlas: String,
+    pub crate_sprite: [f32; 4],
+    pub ball_sprite: [f32; 4],
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameConfig {
+    // RON level to play instead of the default crate grid.
+    // Values from the level override the ones below.
+    pub level_path: Option<String>,
+    // Random seed, picked at random if not set
+    pub seed: Option<u64>,
+    // Number of samples per pixel for anti-aliasing: 1, 2, 4 or 8
+    pub msaa_samples: u32,
+    // Starts in borderless fullscreen on the current monitor.
+    // Can be toggled at runtime with F11.
+    pub fullscreen: bool,
+    // Width to height ratio of the drawn area. Windows of other shapes
+    // get black bars around it. Fills the whole window if not set.
+    pub logical_aspect: Option<f32>,
+    // Maximum frames per second, uncapped if not set.
+    // Can be toggled at runtime with L.
+    pub frame_limit: Option<u32>,
+    // Shows the frame rate and simulation stats in the bottom
+    // left corner. Can be toggled at runtime with F3.
+    pub show_stats: bool,
+    // Prints the frame time to stdout once a second,
+    // for runs where nobody watches the window
+    pub log_frame_stats: bool,
+
+    pub starting_lives: u32,
+    // Lives never go above it
+    pub max_lives: u32,
+    // Score step for a bonus life, e.g. with 1000 lives are
+    // granted at 1000, 2000 and so on. Disabled if not set.
+    pub extra_life_every: Option<u32>,
+    // Chance of a destroyed crate to drop a power-up
+    pub power_up_chance: f32,
+    // Units per second falling power-ups drift sideways
+    // towards the platform with. 0 disables it.
+    pub power_up_magnet: f32,
+    // Platform gets this much narrower every time it returns the ball,
+    // down to `min_paddle_width`. Losing a life restores its width.
+    // 0 disables it.
+    pub paddle_shrink_per_hit: f32,
+    pub min_paddle_width: f32,
+    // Number of destroyed crates needed for a super shot
+    pub charge_capacity: u32,
+    pub plow_through_duration: f32,
+    // Seconds the inverted controls debuff swaps left and right for
+    pub inverted_controls_duration: f32,
+    // Seconds the platform can not move for after a freeze crate is destroyed
+    pub paddle_freeze_duration: f32,
+    // Seconds without destroyed crates or platform hits after which
+    // launched balls are turned by a small random angle, so they can
+    // not bounce around in the same loop forever. Disabled if not set.
+    pub stall_timeout: Option<f32>,
+    // Seconds between the laser destroying a random column of crates,
+    // each column is warned about before. Disabled if not set.
+    pub column_laser_interval: Option<f32>,
+    // Seconds before each round starts, disabled if not positive
+    pub countdown: f32,
+    // Launches the ball once the countdown runs out
+    // instead of waiting for the player
+    pub auto_launch: bool,
+    // Shows the predicted path of the ball waiting on the platform.
+    // Can be toggled at runtime with G.
+    pub aim_guide: bool,
+    // Alpha of the black overlay dimming the game while paused
+    pub pause_dim: f32,
+    // Unpauses the game once the window is focused again after losing
+    // focus paused it. Otherwise the player has to unpause manually.
+    pub resume_on_focus: bool,
+
+    // Drawn behind the border and everything inside of it
+    pub background: Background,
+    // Colors of the border, the platform, the ball, the crate grid and the HUD
+    pub theme: Theme,
+    // Crates and the ball are flat colored if not set
+    pub textures: Option<TexturesConfig>,
+    pub border: BorderConfig,
+    pub platform: PlatformConfig,
+    pub ball: BallConfig,
+    pub crates: CratesConfig,
+    // Wells bending the path of the ball, levels use their own ones
+    pub gravity_wells: Vec<GravityWell>,
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        Self {
+            level_path: None,
+            seed: None,
+            msaa_samples: 4,
+            fullscreen: false,
+            logical_aspect: None,
+            frame_limit: Some(60),
+            show_stats: false,
+            log_frame_stats: false,
+            starting_lives: 3,
+            max_lives: 9,
+            extra_life_every: Some(1000),
+            power_up_chance: 0.15,
+            power_up_magnet: 0.0,
+            paddle_shrink_per_hit: 0.0,
+            min_paddle_width: 1.0,
+            charge_capacity: 5,
+            plow_through_duration: 2.0,
+            inverted_controls_duration: 5.0,
+            paddle_freeze_duration: 1.0,
+            stall_timeout: None,
+            column_laser_interval: None,
+            countdown: 3.0,
+            auto_launch: false,
+            aim_guide: false,
+            pause_dim: 0.6,
+            resume_on_focus: false,
+            background: Background::default(),
+            theme: Theme::default(),
+            textures: None,
+            border: BorderConfig::default(),
+            platform: PlatformConfig::default(),
+            ball: BallConfig::default(),
+            crates: CratesConfig::default(),
+            gravity_wells: vec![],
+        }
+    }
+}
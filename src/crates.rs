@@ -1,49 +1,276 @@
-use zero::prelude::*;
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use zero::{
+    cgmath_imports::{Quaternion, Rad, Rotation3},
+    prelude::*,
+};
 
 use crate::{
-    physics::{Collider, Collision, Rectangle},
-    rendering::{InstanceUniform, Instances},
+    level::Level,
+    physics::{Collider, Collision, CollisionResponse, Obb, Rectangle},
+    rendering::{InstanceAllocator, InstanceRange, InstanceUniform, SceneBuffer},
 };
 
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CrateKind {
+    #[default]
+    Normal,
+    // Bounces balls off, but can not be destroyed
+    Steel,
+    // Destroys orthogonal neighbors together with itself
+    Explosive,
+    // Freezes the platform for a while once destroyed
+    Freeze,
+}
+
+// Damage of a single crate
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CrateState {
+    pub health: u32,
+    pub disabled: bool,
+}
+
+// Damage of all crates in the order of the pack together with
+// the destroyed counters, the layout comes from the config or the level
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CratePackState {
+    pub crates: Vec<CrateState>,
+    pub destroyed: u32,
+    pub points: u32,
+}
+
+// Crates a moving circle touches first. Crates touched at the same
+// time, like two neighbors hit right on the gap between them, are
+// all included and their collisions are combined into one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CrateContact {
+    pub toi: f32,
+    pub collision: Collision,
+    pub crates: Vec<usize>,
+}
+
 pub struct Crate {
     transform: Transform,
+    kind: CrateKind,
+    // Column and row in the crate grid counting rows from the bottom,
+    // used to find neighbors of exploding crates
+    cell: Option<[i32; 2]>,
+    // Radians counterclockwise, also kept in the `transform`
+    rotation: f32,
+    // Color of the undamaged crate
+    base_color: [f32; 4],
     color: [f32; 4],
+    points: u32,
+    health: u32,
+    max_health: u32,
+    // Hit the crate drops a power-up on, counting from 1
+    power_up_hit: Option<u32>,
     disabled: bool,
+    // Sprite in the texture atlas, see `InstanceUniform::uv_rect`
+    uv_rect: [f32; 4],
 }
 
 impl Crate {
-    pub fn new(translation: Vector3<f32>, scale: Vector3<f32>, color: [f32; 4]) -> Self {
+    // Each non fatal hit multiplies the base color by this value
+    const DAMAGE_SHADE: f32 = 0.7;
+
+    pub fn new(
+        translation: Vector3<f32>,
+        scale: Vector3<f32>,
+        color: [f32; 4],
+        points: u32,
+        health: u32,
+    ) -> Self {
         Self {
             transform: Transform {
                 translation,
                 scale,
                 ..Default::default()
             },
+            kind: CrateKind::Normal,
+            cell: None,
+            rotation: 0.0,
+            base_color: color,
             color,
+            points,
+            health,
+            max_health: health,
+            power_up_hit: None,
             disabled: false,
+            uv_rect: InstanceUniform::NO_SPRITE,
         }
     }
 
+    pub fn set_kind(&mut self, kind: CrateKind) {
+        self.kind = kind;
+    }
+
+    pub fn set_cell(&mut self, col: i32, row: i32) {
+        self.cell = Some([col, row]);
+    }
+
+    // Rotates the crate around its center by the
+    // `rotation` in radians counterclockwise
+    pub fn set_rotation(&mut self, rotation: f32) {
+        self.rotation = rotation;
+        self.transform.rotation = Quaternion::from_angle_z(Rad(rotation));
+    }
+
+    // Drops a power-up on the `hit`-th hit, hits past the
+    // health of the crate drop it on the destroying one
+    pub fn set_power_up_hit(&mut self, hit: u32) {
+        self.power_up_hit = Some(hit.clamp(1, self.max_health.max(1)));
+    }
+
     #[inline]
-    pub fn rect(&self, rect_width: f32, rect_height: f32) -> Rectangle {
+    pub fn kind(&self) -> CrateKind {
+        self.kind
+    }
+
+    #[inline]
+    pub fn power_up_hit(&self) -> Option<u32> {
+        self.power_up_hit
+    }
+
+    // Checked right after a hit, steel crates are never damaged
+    fn drops_power_up(&self) -> bool {
+        self.kind != CrateKind::Steel
+            && self.power_up_hit == Some(self.max_health.saturating_sub(self.health))
+    }
+
+    // Damages the crate and returns true if it was destroyed.
+    // Steel crates are not damaged.
+    pub fn hit(&mut self) -> bool {
+        if self.kind == CrateKind::Steel {
+            return false;
+        }
+        self.health = self.health.saturating_sub(1);
+        if self.health == 0 {
+            self.disabled = true;
+        } else {
+            self.shade();
+        }
+        self.disabled
+    }
+
+    // Damage shade is kept
+    pub fn set_base_color(&mut self, color: [f32; 4]) {
+        self.base_color = color;
+        self.color[3] = color[3];
+        self.shade();
+    }
+
+    // Darkens the base color by the missing health
+    fn shade(&mut self) {
+        let shade = Self::DAMAGE_SHADE.powi(self.max_health.saturating_sub(self.health) as i32);
+        for (c, base) in self.color[..3].iter_mut().zip(self.base_color) {
+            *c = base * shade;
+        }
+    }
+
+    #[inline]
+    pub fn state(&self) -> CrateState {
+        CrateState {
+            health: self.health,
+            disabled: self.disabled,
+        }
+    }
+
+    pub fn set_state(&mut self, state: &CrateState) {
+        self.health = state.health;
+        self.disabled = state.disabled;
+        self.shade();
+    }
+
+    #[inline]
+    pub fn position(&self) -> Vector2<f32> {
+        self.transform.translation.truncate()
+    }
+
+    #[inline]
+    pub fn color(&self) -> [f32; 4] {
+        self.color
+    }
+
+    #[inline]
+    pub fn points(&self) -> u32 {
+        self.points
+    }
+
+    #[inline]
+    pub fn is_disabled(&self) -> bool {
+        self.disabled
+    }
+
+    pub fn instance(&self) -> InstanceUniform {
+        InstanceUniform {
+            transform: Matrix4::from(&self.transform).into(),
+            color: self.color,
+            disabled: self.disabled.into(),
+            uv_rect: self.uv_rect,
+        }
+    }
+
+    // Crates are drawn with a unit quad scaled by their size.
+    // Rotation is not included, see `obb`.
+    #[inline]
+    pub fn rect(&self) -> Rectangle {
         Rectangle::from_center(
             self.transform.translation.truncate(),
-            rect_width,
-            rect_height,
+            self.transform.scale.x,
+            self.transform.scale.y,
         )
     }
+
+    #[inline]
+    pub fn obb(&self) -> Obb {
+        self.rect().to_obb(self.rotation)
+    }
+}
+
+#[derive(Debug)]
+pub enum CratePackImageError {
+    Image(image::ImageError),
+    TooManyCrates { crates: u32, max_crates: u32 },
+}
+
+impl From<image::ImageError> for CratePackImageError {
+    fn from(value: image::ImageError) -> Self {
+        Self::Image(value)
+    }
 }
 
 pub struct CratePack {
     pub crates: Vec<Crate>,
-    pub rect_width: f32,
-    pub rect_height: f32,
     pub need_sync: bool,
+    pub destroyed: u32,
+    // Sum of points of all destroyed crates
+    pub points: u32,
+    // Number of enabled crates which can be destroyed
+    remaining: u32,
+    // Index of the crate in each grid cell
+    cells: HashMap<[i32; 2], usize>,
+    // Indices of crates destroyed since the last drain
+    pub just_destroyed: Vec<usize>,
+    // Indices of crates hit on their power-up hit since the last drain
+    pub power_up_drops: Vec<usize>,
+    // Indices of crates changed since the last sync, which may repeat.
+    // Ignored if the whole pack needs to be synced.
+    dirty: Vec<usize>,
+    // Overrides how balls bounce off crates
+    pub response: Option<Box<dyn CollisionResponse>>,
 
-    pub instance_buffer_offset: u64,
+    pub instance_range: InstanceRange,
 }
 
 impl CratePack {
+    // Number of alpha ranges of crate image pixels mapped to
+    // health, alpha below 0.25 gives the most health
+    const IMAGE_ALPHA_HEALTH_STEPS: f32 = 4.0;
+
+    // `row_points` receives the row index, `color`, `health` and `kind`
+    // receive the column and the row indices, all counting rows from the bottom.
     #[allow(clippy::too_many_arguments)]
     pub fn new(
         center: Vector3<f32>,
@@ -53,59 +280,446 @@ impl CratePack {
         height: f32,
         gap_x: f32,
         gap_y: f32,
-        color: [f32; 4],
-        instance_buffer_offset: u64,
+        color: impl Fn(u32, u32) -> [f32; 4],
+        row_points: impl Fn(u32) -> u32,
+        health: impl Fn(u32, u32) -> u32,
+        kind: impl Fn(u32, u32) -> CrateKind,
+        instance_allocator: &mut InstanceAllocator,
     ) -> Self {
-        let bottom_left = center
-            - Vector3::new(
-                (gap_x + width) / 2.0 * (cols - 1) as f32,
-                (gap_y + height) / 2.0 * (rows - 1) as f32,
-                0.0,
-            );
+        let bottom_left = Self::bottom_left(center, rows, cols, width, height, gap_x, gap_y);
         let mut crates = vec![];
         for x in 0..cols {
             for y in 0..rows {
-                let c = Crate::new(
-                    Vector3::new(
-                        bottom_left.x + x as f32 * (width + gap_x),
-                        bottom_left.y + y as f32 * (height + gap_y),
-                        0.0,
-                    ),
+                let mut c = Crate::new(
+                    Self::cell_position(bottom_left, x, y, width, height, gap_x, gap_y),
                     Vector3::new(width, height, 1.0),
-                    color,
+                    color(x, y),
+                    row_points(y),
+                    health(x, y),
                 );
+                c.set_kind(kind(x, y));
+                c.set_cell(x as i32, y as i32);
                 crates.push(c);
             }
         }
 
+        Self::from_crates(crates, instance_allocator)
+    }
+
+    // Builds crates from an image where each non transparent pixel
+    // is a crate with the pixel color. The lower the alpha of the pixel,
+    // the more health the crate has, from 1 for opaque pixels up to 4.
+    // Crates themselves are opaque. Image rows go from top to bottom.
+    // `row_points` receives the row index counting from the bottom.
+    #[allow(clippy::too_many_arguments)]
+    pub fn from_image(
+        path: &str,
+        center: Vector3<f32>,
+        width: f32,
+        height: f32,
+        gap_x: f32,
+        gap_y: f32,
+        row_points: impl Fn(u32) -> u32,
+        max_crates: u32,
+        instance_allocator: &mut InstanceAllocator,
+    ) -> Result<Self, CratePackImageError> {
+        let image = image::open(path)?.into_rgba8();
+        let (cols, rows) = image.dimensions();
+
+        let crates_num = image.pixels().filter(|p| p.0[3] != 0).count() as u32;
+        if max_crates < crates_num {
+            return Err(CratePackImageError::TooManyCrates {
+                crates: crates_num,
+                max_crates,
+            });
+        }
+
+        let bottom_left = Self::bottom_left(center, rows, cols, width, height, gap_x, gap_y);
+        let mut crates = vec![];
+        for x in 0..cols {
+            for y in 0..rows {
+                let pixel = image.get_pixel(x, rows - 1 - y);
+                if pixel.0[3] == 0 {
+                    continue;
+                }
+                let [r, g, b, a] = pixel.0.map(|c| c as f32 / 255.0);
+                let mut c = Crate::new(
+                    Self::cell_position(bottom_left, x, y, width, height, gap_x, gap_y),
+                    Vector3::new(width, height, 1.0),
+                    [r, g, b, 1.0],
+                    row_points(y),
+                    1 + ((1.0 - a) * Self::IMAGE_ALPHA_HEALTH_STEPS) as u32,
+                );
+                c.set_cell(x as i32, y as i32);
+                crates.push(c);
+            }
+        }
+
+        Ok(Self::from_crates(crates, instance_allocator))
+    }
+
+    // Builds crates only in the cells of the `mask` that are set.
+    // Mask rows go from top to bottom, so it reads the same way it
+    // is drawn on screen. Rows may have different lengths, missing
+    // cells are empty. `row_points` receives the row index and `color`
+    // receives the column and the row indices, counting rows from the bottom.
+    #[allow(clippy::too_many_arguments)]
+    pub fn from_mask(
+        center: Vector3<f32>,
+        width: f32,
+        height: f32,
+        gap_x: f32,
+        gap_y: f32,
+        mask: &[impl AsRef<[bool]>],
+        color: impl Fn(u32, u32) -> [f32; 4],
+        row_points: impl Fn(u32) -> u32,
+        instance_allocator: &mut InstanceAllocator,
+    ) -> Self {
+        let rows = mask.len() as u32;
+        let cols = mask.iter().map(|row| row.as_ref().len()).max().unwrap_or(0) as u32;
+
+        let bottom_left = Self::bottom_left(center, rows, cols, width, height, gap_x, gap_y);
+        let mut crates = vec![];
+        for x in 0..cols {
+            for y in 0..rows {
+                let row = mask[(rows - 1 - y) as usize].as_ref();
+                if !row.get(x as usize).copied().unwrap_or(false) {
+                    continue;
+                }
+                let mut c = Crate::new(
+                    Self::cell_position(bottom_left, x, y, width, height, gap_x, gap_y),
+                    Vector3::new(width, height, 1.0),
+                    color(x, y),
+                    row_points(y),
+                    1,
+                );
+                c.set_cell(x as i32, y as i32);
+                crates.push(c);
+            }
+        }
+
+        // Empty cells do not take any instances
+        Self::from_crates(crates, instance_allocator)
+    }
+
+    pub fn from_level(level: &Level, instance_allocator: &mut InstanceAllocator) -> Self {
+        let crates = level
+            .crates
+            .iter()
+            .map(|c| {
+                let [width, height] = c.size.unwrap_or([level.crate_width, level.crate_height]);
+                let mut new_crate = Crate::new(
+                    Vector3::new(c.position[0], c.position[1], 0.0),
+                    Vector3::new(width, height, 1.0),
+                    c.color,
+                    c.points,
+                    c.health,
+                );
+                new_crate.set_kind(c.kind);
+                new_crate.set_rotation(c.rotation.to_radians());
+                if let Some(hit) = c.power_up_hit {
+                    new_crate.set_power_up_hit(hit);
+                }
+                if let Some([col, row]) = c.cell {
+                    new_crate.set_cell(col, row);
+                }
+                new_crate
+            })
+            .collect::<Vec<_>>();
+
+        Self::from_crates(crates, instance_allocator)
+    }
+
+    fn from_crates(crates: Vec<Crate>, instance_allocator: &mut InstanceAllocator) -> Self {
+        let instance_range = instance_allocator.allocate(crates.len() as u32);
+        let cells = crates
+            .iter()
+            .enumerate()
+            .filter_map(|(i, c)| c.cell.map(|cell| (cell, i)))
+            .collect();
         Self {
+            remaining: crates.iter().filter(|c| c.kind != CrateKind::Steel).count() as u32,
+            cells,
             crates,
-            rect_width: width,
-            rect_height: height,
             need_sync: true,
-            instance_buffer_offset,
+            dirty: vec![],
+            response: None,
+            destroyed: 0,
+            points: 0,
+            just_destroyed: vec![],
+            power_up_drops: vec![],
+            instance_range,
         }
     }
 
-    pub fn render_sync(&mut self, renderer: &Renderer, storage: &RenderStorage, boxes: &Instances) {
+    #[inline]
+    fn bottom_left(
+        center: Vector3<f32>,
+        rows: u32,
+        cols: u32,
+        width: f32,
+        height: f32,
+        gap_x: f32,
+        gap_y: f32,
+    ) -> Vector3<f32> {
+        center
+            - Vector3::new(
+                (gap_x + width) / 2.0 * cols.saturating_sub(1) as f32,
+                (gap_y + height) / 2.0 * rows.saturating_sub(1) as f32,
+                0.0,
+            )
+    }
+
+    #[inline]
+    fn cell_position(
+        bottom_left: Vector3<f32>,
+        x: u32,
+        y: u32,
+        width: f32,
+        height: f32,
+        gap_x: f32,
+        gap_y: f32,
+    ) -> Vector3<f32> {
+        Vector3::new(
+            bottom_left.x + x as f32 * (width + gap_x),
+            bottom_left.y + y as f32 * (height + gap_y),
+            0.0,
+        )
+    }
+
+    // Hits closer in time than this to the earliest one happen at once
+    const SIMULTANEOUS_TOI_EPSILON: f32 = 1e-5;
+
+    // Moves a circle along the `displacement` and returns
+    // the earliest contact with the enabled crates
+    pub fn sweep_circle(
+        &self,
+        center: Vector2<f32>,
+        radius: f32,
+        displacement: Vector2<f32>,
+    ) -> Option<CrateContact> {
+        let hits = self
+            .crates
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| !c.disabled)
+            .filter_map(|(i, c)| {
+                c.obb()
+                    .sweep_circle(center, radius, displacement)
+                    .map(|(toi, collision)| (toi, collision, i))
+            })
+            .collect::<Vec<_>>();
+        let toi = hits
+            .iter()
+            .map(|(toi, _, _)| *toi)
+            .min_by(|a, b| a.total_cmp(b))?;
+        let (collisions, crates): (Vec<_>, Vec<_>) = hits
+            .into_iter()
+            .filter(|(t, _, _)| *t - toi <= Self::SIMULTANEOUS_TOI_EPSILON)
+            .map(|(_, collision, i)| (collision, i))
+            .unzip();
+        Some(CrateContact {
+            toi,
+            collision: Collision::combine(&collisions)?,
+            crates,
+        })
+    }
+
+    #[inline]
+    pub fn remaining(&self) -> u32 {
+        self.remaining
+    }
+
+    #[inline]
+    pub fn all_disabled(&self) -> bool {
+        self.remaining == 0
+    }
+
+    // Position of the crate with the most crates within the `radius`
+    // around it, counting only crates which can still be destroyed.
+    // None once all of them are destroyed.
+    pub fn densest_cluster(&self, radius: f32) -> Option<Vector2<f32>> {
+        let targets = self
+            .crates
+            .iter()
+            .filter(|c| !c.disabled && c.kind != CrateKind::Steel)
+            .map(Crate::position)
+            .collect::<Vec<_>>();
+        targets
+            .iter()
+            .max_by_key(|&&a| {
+                targets
+                    .iter()
+                    .filter(|&&b| {
+                        let delta = a - b;
+                        delta.x.hypot(delta.y) <= radius
+                    })
+                    .count()
+            })
+            .copied()
+    }
+
+    // Grid columns with crates which can still be destroyed, in order
+    pub fn columns(&self) -> Vec<i32> {
+        let mut columns = self
+            .crates
+            .iter()
+            .filter(|c| !c.disabled && c.kind != CrateKind::Steel)
+            .filter_map(|c| c.cell.map(|[col, _]| col))
+            .collect::<Vec<_>>();
+        columns.sort_unstable();
+        columns.dedup();
+        columns
+    }
+
+    // Bounds of the enabled crates in the grid `column`,
+    // none if all of them are destroyed
+    pub fn column_rect(&self, column: i32) -> Option<Rectangle> {
+        self.crates
+            .iter()
+            .filter(|c| !c.disabled && c.cell.is_some_and(|[col, _]| col == column))
+            .map(|c| c.obb().aabb())
+            .reduce(|a, b| {
+                let left = a.left().min(b.left());
+                let bot = a.bot().min(b.bot());
+                Rectangle {
+                    x: left,
+                    y: bot,
+                    width: a.right().max(b.right()) - left,
+                    height: a.top().max(b.top()) - bot,
+                }
+            })
+    }
+
+    // Destroys every crate in the grid `column` except steel ones,
+    // as if they were hit for the last time
+    pub fn destroy_column(&mut self, column: i32) {
+        for i in 0..self.crates.len() {
+            let c = &mut self.crates[i];
+            if !c.disabled
+                && c.kind != CrateKind::Steel
+                && c.cell.is_some_and(|[col, _]| col == column)
+            {
+                c.disabled = true;
+                self.on_destroyed(i);
+            }
+        }
+    }
+
+    pub fn state(&self) -> CratePackState {
+        CratePackState {
+            crates: self.crates.iter().map(Crate::state).collect(),
+            destroyed: self.destroyed,
+            points: self.points,
+        }
+    }
+
+    // Draws all crates with the `uv_rect` sprite of the texture atlas.
+    // The whole pack is synced on the next render.
+    pub fn set_sprite(&mut self, uv_rect: [f32; 4]) {
+        for c in self.crates.iter_mut() {
+            c.uv_rect = uv_rect;
+        }
+        self.need_sync = true;
+    }
+
+    // Recolors crates in grid cells by their row counting from the bottom.
+    // The whole pack is synced on the next render.
+    pub fn set_row_colors(&mut self, color: impl Fn(u32) -> [f32; 4]) {
+        for c in self.crates.iter_mut() {
+            if let Some([_, row]) = c.cell {
+                c.set_base_color(color(row as u32));
+            }
+        }
+        self.need_sync = true;
+    }
+
+    // Crates of the `state` are matched with the crates of
+    // the pack by index, extra ones on either side are ignored.
+    // The whole pack is synced on the next render.
+    pub fn set_state(&mut self, state: &CratePackState) {
+        for (c, crate_state) in self.crates.iter_mut().zip(state.crates.iter()) {
+            c.set_state(crate_state);
+        }
+        self.destroyed = state.destroyed;
+        self.points = state.points;
+        self.remaining = self
+            .crates
+            .iter()
+            .filter(|c| !c.disabled && c.kind != CrateKind::Steel)
+            .count() as u32;
+        self.just_destroyed.clear();
+        self.power_up_drops.clear();
+        self.dirty.clear();
+        self.need_sync = true;
+    }
+
+    // Damages the crate and counts it as destroyed once it runs out of
+    // health. Crates destroyed earlier, like by an explosion set off by
+    // another crate hit at the same time, are ignored.
+    pub fn hit(&mut self, index: usize) {
+        if self.crates[index].disabled {
+            return;
+        }
+        if self.crates[index].hit() {
+            self.on_destroyed(index);
+        } else {
+            self.dirty.push(index);
+        }
+        if self.crates[index].drops_power_up() {
+            self.power_up_drops.push(index);
+        }
+    }
+
+    // Exploding crates destroy their orthogonal neighbors,
+    // which can explode in turn. Steel crates survive explosions.
+    fn on_destroyed(&mut self, index: usize) {
+        let mut destroyed = vec![index];
+        while let Some(index) = destroyed.pop() {
+            self.dirty.push(index);
+            let c = &self.crates[index];
+            self.destroyed += 1;
+            self.points += c.points;
+            self.remaining -= 1;
+            self.just_destroyed.push(index);
+
+            let (CrateKind::Explosive, Some([col, row])) = (c.kind, c.cell) else {
+                continue;
+            };
+            for cell in [
+                [col - 1, row],
+                [col + 1, row],
+                [col, row - 1],
+                [col, row + 1],
+            ] {
+                let Some(&neighbor) = self.cells.get(&cell) else {
+                    continue;
+                };
+                let n = &mut self.crates[neighbor];
+                if !n.disabled && n.kind != CrateKind::Steel {
+                    n.disabled = true;
+                    destroyed.push(neighbor);
+                }
+            }
+        }
+    }
+
+    // Uploads all crates if `need_sync` is set,
+    // otherwise only the ones changed by hits
+    pub fn render_sync(&mut self, scene: &mut SceneBuffer) {
         if self.need_sync {
-            let data = self
-                .crates
-                .iter()
-                .map(|c| InstanceUniform {
-                    transform: Matrix4::from(&c.transform).into(),
-                    color: c.color,
-                    disabled: c.disabled.into(),
-                })
-                .collect::<Vec<_>>();
-            boxes.instance_buffer_handle.update(
-                renderer,
-                storage,
-                self.instance_buffer_offset,
-                &data,
-            );
+            let data = self.crates.iter().map(Crate::instance).collect::<Vec<_>>();
+            scene.write(self.instance_range, 0, &data);
             self.need_sync = false;
+        } else {
+            // Crates hit more than once since the last sync are written once
+            self.dirty.sort_unstable();
+            self.dirty.dedup();
+            for &i in self.dirty.iter() {
+                scene.write(self.instance_range, i as u32, &[self.crates[i].instance()]);
+            }
         }
+        self.dirty.clear();
     }
 }
 
@@ -115,18 +729,280 @@ impl Collider for CratePack {
         None
     }
 
-    #[inline]
+    // Hits every enabled crate overlapping the `other`
+    // and combines their collisions into one
     fn collides_mut(&mut self, other: &impl Collider) -> Option<Collision> {
-        for c in self.crates.iter_mut() {
+        let other = other.rect()?.to_obb(0.0);
+        let mut collisions = vec![];
+        for i in 0..self.crates.len() {
+            let c = &self.crates[i];
             if !c.disabled {
-                let crate_rect = c.rect(self.rect_width, self.rect_height);
-                if let Some(collision) = crate_rect.collides(other) {
-                    c.disabled = true;
-                    self.need_sync = true;
-                    return Some(collision);
+                if let Some(collision) = c.obb().collides_obb(&other) {
+                    self.hit(i);
+                    collisions.push(collision);
                 }
             }
         }
-        None
+        Collision::combine(&collisions)
+    }
+
+    fn response(&self) -> Option<&dyn CollisionResponse> {
+        self.response.as_deref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Crates of size 1 in a row with the given points and health
+    fn crates(crates: &[(u32, u32)]) -> Vec<Crate> {
+        crates
+            .iter()
+            .enumerate()
+            .map(|(i, &(points, health))| {
+                Crate::new(
+                    Vector3::new(i as f32 * 2.0, 0.0, 0.0),
+                    Vector3::new(1.0, 1.0, 1.0),
+                    [1.0; 4],
+                    points,
+                    health,
+                )
+            })
+            .collect()
+    }
+
+    fn crate_pack(points_health: &[(u32, u32)]) -> CratePack {
+        CratePack::from_crates(crates(points_health), &mut InstanceAllocator::default())
+    }
+
+    // Saves the `image` to a file unique to the test and returns its path
+    fn save_image(image: &image::RgbaImage, name: &str) -> String {
+        let path = std::env::temp_dir().join(format!("breakout_zero_{name}.png"));
+        image.save(&path).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn crates_from_image_match_the_pixels() {
+        let mut image = image::RgbaImage::new(2, 2);
+        image.put_pixel(0, 0, image::Rgba([255, 0, 0, 255]));
+        image.put_pixel(1, 0, image::Rgba([0, 0, 0, 0]));
+        image.put_pixel(0, 1, image::Rgba([0, 0, 255, 128]));
+        image.put_pixel(1, 1, image::Rgba([255, 0, 0, 32]));
+        let path = save_image(&image, "crates_from_image");
+
+        let crate_pack = CratePack::from_image(
+            &path,
+            Vector3::new(0.0, 0.0, 0.0),
+            1.0,
+            1.0,
+            0.0,
+            0.0,
+            |row| 10 * (row + 1),
+            4,
+            &mut InstanceAllocator::default(),
+        )
+        .unwrap();
+        _ = std::fs::remove_file(path);
+
+        // Image rows go down while crate rows go up,
+        // so the bottom row of the image is row 0.
+        // Translucent pixels give opaque crates with more health.
+        let crates = crate_pack
+            .crates
+            .iter()
+            .map(|c| (c.cell.unwrap(), c.color(), c.points(), c.health))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            crates,
+            vec![
+                ([0, 0], [0.0, 0.0, 1.0, 1.0], 10, 2),
+                ([0, 1], [1.0, 0.0, 0.0, 1.0], 20, 1),
+                ([1, 0], [1.0, 0.0, 0.0, 1.0], 10, 4),
+            ]
+        );
+        assert_eq!(crate_pack.crates[0].position(), Vector2::new(-0.5, -0.5));
+        assert_eq!(crate_pack.crates[2].position(), Vector2::new(0.5, -0.5));
+    }
+
+    #[test]
+    fn crates_from_mask_skip_empty_cells() {
+        let mut instance_allocator = InstanceAllocator::default();
+        let crate_pack = CratePack::from_mask(
+            Vector3::new(0.0, 0.0, 0.0),
+            1.0,
+            1.0,
+            0.0,
+            0.0,
+            &[vec![true, false, true], vec![false, true]],
+            |_, _| [1.0; 4],
+            |row| 10 * (row + 1),
+            &mut instance_allocator,
+        );
+
+        // Empty and missing cells take neither crates nor instances
+        assert_eq!(crate_pack.crates.len(), 3);
+        assert_eq!(instance_allocator.allocated(), 3);
+
+        // The first row of the mask is the top row on the screen
+        let crates = crate_pack
+            .crates
+            .iter()
+            .map(|c| (c.cell.unwrap(), c.position(), c.points()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            crates,
+            vec![
+                ([0, 1], Vector2::new(-1.0, 0.5), 20),
+                ([1, 0], Vector2::new(0.0, -0.5), 10),
+                ([2, 1], Vector2::new(1.0, 0.5), 20),
+            ]
+        );
+    }
+
+    #[test]
+    fn images_with_too_many_crates_are_rejected() {
+        let image = image::RgbaImage::from_pixel(2, 2, image::Rgba([255, 255, 255, 255]));
+        let path = save_image(&image, "too_many_crates");
+
+        let result = CratePack::from_image(
+            &path,
+            Vector3::new(0.0, 0.0, 0.0),
+            1.0,
+            1.0,
+            0.0,
+            0.0,
+            |_| 10,
+            3,
+            &mut InstanceAllocator::default(),
+        );
+        _ = std::fs::remove_file(path);
+
+        assert!(matches!(
+            result,
+            Err(CratePackImageError::TooManyCrates {
+                crates: 4,
+                max_crates: 3
+            })
+        ));
+    }
+
+    #[test]
+    fn diagonal_sweep_into_a_corner_hits_the_earliest_crate() {
+        // Crates touching at the corner (0.5, 0.5), the ball path
+        // reaches the bottom of the top one first
+        let crates = [[0.0, 1.0], [1.0, 0.0]]
+            .into_iter()
+            .map(|[x, y]| {
+                Crate::new(
+                    Vector3::new(x, y, 0.0),
+                    Vector3::new(1.0, 1.0, 1.0),
+                    [1.0; 4],
+                    10,
+                    1,
+                )
+            })
+            .collect();
+        let crate_pack = CratePack::from_crates(crates, &mut InstanceAllocator::default());
+
+        let contact = crate_pack
+            .sweep_circle(Vector2::new(-1.0, -0.9), 0.2, Vector2::new(2.0, 2.0))
+            .unwrap();
+
+        assert_eq!(contact.crates, vec![0]);
+        assert!((contact.toi - 0.6).abs() < 1e-5);
+        assert_eq!(contact.collision.normal, Vector2::new(0.0, -1.0));
+    }
+
+    #[test]
+    fn destroyed_crates_add_up_their_points() {
+        let mut crate_pack = crate_pack(&[(10, 1), (20, 1), (30, 1)]);
+
+        for i in 0..3 {
+            crate_pack.hit(i);
+        }
+
+        assert_eq!(crate_pack.points, 60);
+        assert_eq!(crate_pack.destroyed, 3);
+        assert_eq!(crate_pack.just_destroyed, vec![0, 1, 2]);
+        assert!(crate_pack.all_disabled());
+    }
+
+    #[test]
+    fn crate_with_three_health_dies_on_the_third_hit() {
+        let mut crate_pack = crate_pack(&[(10, 3)]);
+        let color = crate_pack.crates[0].color();
+
+        crate_pack.hit(0);
+        let damaged = crate_pack.crates[0].color();
+        assert_ne!(damaged, color);
+        crate_pack.hit(0);
+        assert_ne!(crate_pack.crates[0].color(), damaged);
+        assert!(!crate_pack.crates[0].is_disabled());
+        assert_eq!(crate_pack.destroyed, 0);
+
+        crate_pack.hit(0);
+        assert!(crate_pack.crates[0].is_disabled());
+        assert_eq!(crate_pack.destroyed, 1);
+        assert_eq!(crate_pack.points, 10);
+    }
+
+    #[test]
+    fn steel_crates_are_never_destroyed() {
+        let mut crates = crates(&[(10, 1), (10, 1)]);
+        crates[0].set_kind(CrateKind::Steel);
+        let mut crate_pack = CratePack::from_crates(crates, &mut InstanceAllocator::default());
+        assert_eq!(crate_pack.remaining(), 1);
+
+        for _ in 0..5 {
+            crate_pack.hit(0);
+        }
+        assert!(!crate_pack.crates[0].is_disabled());
+        assert_eq!(crate_pack.points, 0);
+
+        // Steel crates do not have to be destroyed to clear the pack
+        crate_pack.hit(1);
+        assert!(crate_pack.all_disabled());
+    }
+
+    #[test]
+    fn explosions_destroy_orthogonal_neighbors_in_a_chain() {
+        // Bottom row: explosive, explosive, normal, normal
+        // Top row: steel, normal, normal, normal
+        let kind = |x: u32, y: u32| match (x, y) {
+            (0 | 1, 0) => CrateKind::Explosive,
+            (0, 1) => CrateKind::Steel,
+            _ => CrateKind::Normal,
+        };
+        let mut crate_pack = CratePack::new(
+            Vector3::new(0.0, 0.0, 0.0),
+            2,
+            4,
+            1.0,
+            1.0,
+            0.0,
+            0.0,
+            |_, _| [1.0; 4],
+            |_| 10,
+            |_, _| 1,
+            kind,
+            &mut InstanceAllocator::default(),
+        );
+        let index = |x: usize, y: usize| x * 2 + y;
+
+        crate_pack.hit(index(0, 0));
+
+        let destroyed = [(0, 0), (1, 0), (1, 1), (2, 0)];
+        for x in 0..4 {
+            for y in 0..2 {
+                assert_eq!(
+                    crate_pack.crates[index(x, y)].is_disabled(),
+                    destroyed.contains(&(x, y)),
+                    "crate at ({x}, {y})"
+                );
+            }
+        }
+        assert_eq!(crate_pack.destroyed, 4);
     }
 }
@@ -0,0 +1,122 @@
+use zero::prelude::*;
+
+use crate::{
+    physics::Rectangle,
+    rendering::{InstanceUniform, Instances, InstancesRenderCommand},
+    simulation::Simulation,
+};
+
+// Width of the drawn lines in world units
+const LINE_WIDTH: f32 = 0.04;
+// Length of the drawn collision normal
+const NORMAL_LENGTH: f32 = 1.0;
+// In front of the scene, but behind the HUD
+const DEPTH: f32 = 0.5;
+
+const BORDER_COLOR: [f32; 4] = [0.0, 1.0, 0.0, 1.0];
+const PLATFORM_COLOR: [f32; 4] = [0.0, 0.5, 1.0, 1.0];
+const CRATE_COLOR: [f32; 4] = [1.0, 1.0, 0.0, 1.0];
+const POWER_UP_COLOR: [f32; 4] = [1.0, 0.0, 1.0, 1.0];
+const BALL_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+const COLLISION_COLOR: [f32; 4] = [1.0, 0.0, 0.0, 1.0];
+
+// Draws outlines of all colliders and the last collision of each
+// ball. Lines are thin quads, so they go through the instance pipeline.
+pub struct DebugOverlay {
+    enabled: bool,
+    instances: Instances,
+}
+
+impl DebugOverlay {
+    pub fn new(renderer: &Renderer, storage: &mut RenderStorage, mesh_id: ResourceId) -> Self {
+        let mut instances = Instances::new(renderer, storage, mesh_id, 256);
+        instances.instance_num = 0;
+        Self {
+            enabled: false,
+            instances,
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.enabled = !self.enabled;
+    }
+
+    pub fn render_sync(
+        &mut self,
+        renderer: &Renderer,
+        storage: &mut RenderStorage,
+        simulation: &Simulation,
+    ) {
+        if !self.enabled {
+            self.instances.instance_num = 0;
+            return;
+        }
+
+        let mut lines = vec![];
+        outline(&mut lines, simulation.border.border(), BORDER_COLOR);
+        outline(&mut lines, simulation.platform.border(), PLATFORM_COLOR);
+        for c in simulation
+            .crate_pack
+            .crates
+            .iter()
+            .filter(|c| !c.is_disabled())
+        {
+            outline(&mut lines, c.rect(), CRATE_COLOR);
+        }
+        for power_up in simulation.power_ups.iter() {
+            outline(&mut lines, power_up.border(), POWER_UP_COLOR);
+        }
+        for ball in simulation.balls.iter() {
+            outline(&mut lines, ball.border(), BALL_COLOR);
+            if let Some(collision) = ball.last_collision {
+                let end = collision.pos + collision.normal * NORMAL_LENGTH;
+                lines.push(line(collision.pos, end, COLLISION_COLOR));
+            }
+        }
+
+        self.instances
+            .ensure_capacity(renderer, storage, lines.len() as u32);
+        self.instances
+            .instance_buffer_handle
+            .update(renderer, storage, 0, &lines);
+    }
+
+    pub fn render_command(
+        &self,
+        pipeline_id: ResourceId,
+        camera_bind_group: ResourceId,
+    ) -> InstancesRenderCommand {
+        self.instances
+            .render_command(pipeline_id, camera_bind_group)
+    }
+}
+
+fn outline(lines: &mut Vec<InstanceUniform>, rect: Rectangle, color: [f32; 4]) {
+    let bot_left = Vector2::new(rect.left(), rect.bot());
+    let bot_right = Vector2::new(rect.right(), rect.bot());
+    let top_left = Vector2::new(rect.left(), rect.top());
+    let top_right = Vector2::new(rect.right(), rect.top());
+    lines.push(line(bot_left, bot_right, color));
+    lines.push(line(bot_right, top_right, color));
+    lines.push(line(top_right, top_left, color));
+    lines.push(line(top_left, bot_left, color));
+}
+
+// Unit quad stretched from `start` to `end`
+fn line(start: Vector2<f32>, end: Vector2<f32>, color: [f32; 4]) -> InstanceUniform {
+    let direction = end - start;
+    let length = direction.x.hypot(direction.y).max(f32::EPSILON);
+    let side = Vector2::new(-direction.y, direction.x) / length * LINE_WIDTH;
+    let center = (start + end) / 2.0;
+    InstanceUniform {
+        transform: [
+            [direction.x, direction.y, 0.0, 0.0],
+            [side.x, side.y, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [center.x, center.y, DEPTH, 1.0],
+        ],
+        color,
+        disabled: 0,
+        uv_rect: InstanceUniform::NO_SPRITE,
+    }
+}
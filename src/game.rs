@@ -1,23 +1,155 @@
-use zero::{
-    const_vec,
-    prelude::{
-        winit::{dpi::PhysicalSize, event::ElementState, keyboard::Key, window::Window},
-        *,
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+use zero::prelude::{
+    winit::{
+        dpi::{PhysicalPosition, PhysicalSize},
+        event::{ElementState, MouseButton},
+        keyboard::{Key, NamedKey},
+        window::Window,
     },
+    *,
 };
 
+#[cfg(feature = "debug")]
+use crate::debug::DebugOverlay;
+#[cfg(feature = "hot-reload")]
+use crate::hot_reload::ShaderWatcher;
 use crate::{
+    aim_guide::AimGuide,
+    audio::{AudioPlayer, SoundId},
+    background::{BackgroundError, BackgroundPass},
     ball::Ball,
-    border::Border,
-    crates::CratePack,
-    platform::Platform,
-    rendering::{InstanceUniform, InstanceVertex, Instances},
+    bot,
+    charge::ChargeMeter,
+    config::GameConfig,
+    hud::{Hud, HudOverlay},
+    laser,
+    level::{Level, LevelError, LevelSet},
+    menu::{Menu, MenuItem},
+    options::{OptionsItem, OptionsMenu},
+    particles::ParticleSystem,
+    powerup::PowerUp,
+    rendering::{
+        InstanceAllocator, InstanceVertex, Instances, MeshCache, MeshKey, RenderTargets,
+        SceneBuffer, Viewport,
+    },
+    scores::{FinishedGame, HighScores, ScoreEntry, ScoreReporter, ScoreSink},
+    settings::{Action, Difficulty, Settings},
+    simulation::{GameEvent, Input, Simulation},
+    snapshot::{GameSnapshot, SnapshotError},
+    texture::{TextureError, TextureMaterial},
+    theme::Theme,
 };
 
+// Duration of a single physics step
+const PHYSICS_DT: f32 = 1.0 / 120.0;
+// Frame time is clamped to this value, so after a long stall
+// the game does not try to catch up with too many physics steps
+const MAX_FRAME_TIME: f32 = 0.25;
+
+#[cfg(feature = "hot-reload")]
+const SHADER_DIR: &str = "./shaders";
+const INSTANCE_SHADER: &str = "./shaders/instance.wgsl";
+
+// Half of the vertical extent of the world visible by the camera
+const CAMERA_HALF_HEIGHT: f32 = 10.0;
+// Camera shake amplitude per point of a destroyed crate
+const SHAKE_PER_POINT: f32 = 0.004;
+// Number of high scores shown once the game ends
+const SHOWN_HIGH_SCORES: usize = 5;
+// Radians per second of the pulsing pause text
+const PAUSE_PULSE_SPEED: f32 = 4.0;
+// Seconds the number of the next level is shown before its countdown
+const LEVEL_TRANSITION: f32 = 1.5;
+
+// Decaying random offset of the camera
+#[derive(Debug, Default)]
+pub struct CameraShake {
+    amplitude: f32,
+    time: f32,
+}
+
+impl CameraShake {
+    // Amplitude is multiplied by exp(-DECAY) every second
+    const DECAY: f32 = 10.0;
+    const MAX_AMPLITUDE: f32 = 0.5;
+    // Shakes below this amplitude are stopped
+    const MIN_AMPLITUDE: f32 = 0.005;
+
+    #[inline]
+    pub fn is_active(&self) -> bool {
+        self.amplitude != 0.0
+    }
+
+    pub fn add(&mut self, amplitude: f32) {
+        self.amplitude = (self.amplitude + amplitude).min(Self::MAX_AMPLITUDE);
+    }
+
+    // Advances the shake and returns the offset of the camera.
+    // The offset is exactly zero once the shake is over.
+    pub fn update(&mut self, dt: f32) -> Vector2<f32> {
+        self.amplitude *= (-Self::DECAY * dt).exp();
+        if self.amplitude < Self::MIN_AMPLITUDE {
+            self.amplitude = 0.0;
+            self.time = 0.0;
+            return Vector2::new(0.0, 0.0);
+        }
+
+        // Sum of incommensurate sines looks random enough
+        // and does not need a random number generator
+        self.time += dt;
+        let t = self.time;
+        Vector2::new(
+            (t * 53.0).sin() * 0.6 + (t * 31.0).sin() * 0.4,
+            (t * 47.0).sin() * 0.6 + (t * 23.0).sin() * 0.4,
+        ) * self.amplitude
+    }
+}
+
+// Frame time averaged over `REFRESH_INTERVAL`,
+// so the shown numbers change slow enough to read
+#[derive(Debug, Default)]
+pub struct FrameStats {
+    frames: u32,
+    elapsed: f32,
+    // Average of the last finished interval in seconds
+    frame_time: f32,
+}
+
+impl FrameStats {
+    const REFRESH_INTERVAL: f32 = 0.5;
+
+    pub fn record(&mut self, dt: f32) {
+        self.frames += 1;
+        self.elapsed += dt;
+        if Self::REFRESH_INTERVAL <= self.elapsed {
+            self.frame_time = self.elapsed / self.frames as f32;
+            self.frames = 0;
+            self.elapsed = 0.0;
+        }
+    }
+
+    #[inline]
+    pub fn frame_time(&self) -> f32 {
+        self.frame_time
+    }
+
+    #[inline]
+    pub fn fps(&self) -> f32 {
+        if 0.0 < self.frame_time {
+            1.0 / self.frame_time
+        } else {
+            0.0
+        }
+    }
+}
+
 pub struct GameCamera {
     camera: Camera,
     handle: CameraHandle,
     bind_group: CameraBindGroup,
+    // Position without the shake applied
+    position: [f32; 3],
+    shake: CameraShake,
 }
 
 impl GameCamera {
@@ -25,10 +157,10 @@ impl GameCamera {
         let camera = Camera::Orthogonal(OrthogonalCamera {
             position: position.into(),
             direction: -Vector3::unit_z(),
-            left: -10.0,
-            right: 10.0,
-            bottom: -10.0,
-            top: 10.0,
+            left: -CAMERA_HALF_HEIGHT,
+            right: CAMERA_HALF_HEIGHT,
+            bottom: -CAMERA_HALF_HEIGHT,
+            top: CAMERA_HALF_HEIGHT,
             near: 0.1,
             far: 100.0,
         });
@@ -39,174 +171,1286 @@ impl GameCamera {
             camera,
             handle,
             bind_group,
+            position,
+            shake: CameraShake::default(),
+        }
+    }
+
+    // Keeps the vertical extent fixed and widens
+    // or narrows the horizontal one to match `aspect`
+    pub fn resize(&mut self, renderer: &Renderer, storage: &RenderStorage, aspect: f32) {
+        if !aspect.is_normal() {
+            return;
+        }
+        if let Camera::Orthogonal(camera) = &mut self.camera {
+            camera.left = -CAMERA_HALF_HEIGHT * aspect;
+            camera.right = CAMERA_HALF_HEIGHT * aspect;
+        }
+        self.handle.update(renderer, storage, &self.camera);
+    }
+
+    pub fn add_shake(&mut self, amplitude: f32) {
+        self.shake.add(amplitude);
+    }
+
+    // Moves the camera by the current shake offset
+    pub fn update(&mut self, renderer: &Renderer, storage: &RenderStorage, dt: f32) {
+        if !self.shake.is_active() {
+            return;
+        }
+        let offset = self.shake.update(dt);
+        if let Camera::Orthogonal(camera) = &mut self.camera {
+            camera.position = [
+                self.position[0] + offset.x,
+                self.position[1] + offset.y,
+                self.position[2],
+            ]
+            .into();
         }
+        self.handle.update(renderer, storage, &self.camera);
     }
 }
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GameState {
+    Menu,
+    Options,
+    // Physics is frozen until the countdown runs out
+    Countdown { remaining: f32 },
+    // Next level of the set is shown before its countdown starts
+    LevelTransition { remaining: f32 },
+    Playing,
+    Paused,
+    GameOver,
+    Won,
+}
+
+impl GameState {
+    // State after the window gains or loses focus. Losing focus pauses
+    // the game, regaining it only resumes if `resume_on_focus` is set.
+    pub fn after_focus_change(self, focused: bool, resume_on_focus: bool) -> Self {
+        match self {
+            Self::Playing if !focused => Self::Paused,
+            Self::Paused if focused && resume_on_focus => Self::Playing,
+            state => state,
+        }
+    }
+
+    // Physics only runs while playing, frame time
+    // of all other states is dropped
+    #[inline]
+    pub fn is_simulated(&self) -> bool {
+        *self == Self::Playing
+    }
+}
+
+// Movement keys currently held down. Each direction is tracked
+// separately, so releasing one of two held keys keeps moving
+// towards the other one.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct HeldKeys {
+    left: bool,
+    right: bool,
+}
+
+impl HeldKeys {
+    // Key repeats are filtered out before they get here
+    pub fn set(&mut self, action: Action, pressed: bool) {
+        match action {
+            Action::Left => self.left = pressed,
+            Action::Right => self.right = pressed,
+            Action::SuperShot => {}
+        }
+    }
+
+    // Key releases are not seen while unfocused
+    #[inline]
+    pub fn release_all(&mut self) {
+        *self = Self::default();
+    }
+
+    #[inline]
+    pub fn movement(&self) -> f32 {
+        self.right as i32 as f32 - self.left as i32 as f32
+    }
+}
+
 pub struct Game<'window> {
     renderer: Renderer<'window>,
     storage: RenderStorage,
+    mesh_cache: MeshCache,
 
     instance_pipeline_id: ResourceId,
-    phase: RenderPhase,
+    texture_material: TextureMaterial,
+    // Pipeline sampling the texture atlas and the bind group of the
+    // atlas, used for boxes and balls if the config has textures
+    textured_pipeline: Option<(ResourceId, ResourceId)>,
+    background: BackgroundPass,
+    render_targets: RenderTargets,
+    // Window has a zero size, see `resize`
+    minimized: bool,
+    // Part of the window the scene and the HUD are drawn into
+    viewport: Viewport,
 
     camera: GameCamera,
+    hud: Hud,
 
     box_instances: Instances,
+    // Instances of the border, the platform, crates and particles
+    scene: SceneBuffer,
+
+    // Kept to rebuild the simulation on reset
+    config: GameConfig,
+    levels: Option<LevelSet>,
+    simulation: Simulation,
+    // Input for the next physics step
+    input: Input,
+    held_keys: HeldKeys,
+    // Platform is controlled by `bot::follow_ball`
+    bot: bool,
 
-    border: Border,
-    ball: Ball,
-    platform: Platform,
-    crate_pack: CratePack,
+    // Shared by all balls
+    ball_instances: Instances,
+    power_up_instances: Instances,
+    // Warning stripe of the column laser
+    laser_instances: Instances,
+    particles: ParticleSystem,
+    aim_guide: AimGuide,
+    frame_stats: FrameStats,
+    // Stats are drawn in the HUD, toggled with F3
+    show_stats: bool,
+    #[cfg(feature = "debug")]
+    debug_overlay: DebugOverlay,
+    #[cfg(feature = "hot-reload")]
+    shader_watcher: Option<ShaderWatcher>,
+    // Separate from the simulation one, so effects
+    // do not change the outcome of the game
+    effects_rng: SmallRng,
+    audio: AudioPlayer,
+
+    state: GameState,
+    high_scores: HighScores,
+    // Every finished game is submitted to it, see `set_score_sink`
+    score_reporter: ScoreReporter,
+    menu: Menu,
+    settings: Settings,
+    options: OptionsMenu,
+    // Last cursor position in screen pixels with
+    // the origin in the bottom left corner of the window
+    cursor: Vector2<f32>,
+    quit_requested: bool,
+    // Set by `on_exit`
+    exited: bool,
+    // Registered with `on_event`
+    event_callbacks: Vec<EventCallback>,
+
+    // Time not yet simulated by fixed physics steps
+    accumulated_time: f32,
+    // Frame time right after unpausing includes the time spent
+    // paused, so that frame is not simulated
+    skip_frame_time: bool,
+    // Seconds since the start, advancing in every state.
+    // Drives effects which are animated while paused.
+    overlay_time: f32,
+}
+
+// Called with every event of a physics step, see `Game::on_event`
+pub type EventCallback = Box<dyn FnMut(&GameEvent)>;
+
+#[derive(Debug)]
+pub enum GameError {
+    Level(LevelError),
+    Texture(TextureError),
+    Background(BackgroundError),
+    Shader { path: &'static str, error: String },
+}
+
+impl From<LevelError> for GameError {
+    fn from(value: LevelError) -> Self {
+        Self::Level(value)
+    }
+}
+
+impl From<TextureError> for GameError {
+    fn from(value: TextureError) -> Self {
+        Self::Texture(value)
+    }
+}
+
+impl From<BackgroundError> for GameError {
+    fn from(value: BackgroundError) -> Self {
+        Self::Background(value)
+    }
 }
 
 impl<'window> Game<'window> {
     pub fn new(window: &'window Window) -> Game<'window> {
+        Self::try_new(window).unwrap()
+    }
+
+    pub fn try_new(window: &'window Window) -> Result<Game<'window>, GameError> {
+        Self::try_with_config(window, GameConfig::default())
+    }
+
+    // Difficulty of the saved settings overrides the matching config values
+    pub fn try_with_config(
+        window: &'window Window,
+        mut config: GameConfig,
+    ) -> Result<Game<'window>, GameError> {
+        let settings = Settings::load();
+        settings.difficulty.apply(&mut config);
+
+        let levels = config
+            .level_path
+            .as_ref()
+            .map(|path| LevelSet::load(path))
+            .transpose()?;
+        let seed = config.seed.unwrap_or_else(rand::random);
+
+        // Surface format and present mode are picked by the renderer.
+        // `Renderer::new` only takes the window and keeps the surface and
+        // its configuration private, so neither can be chosen or changed
+        // from here. Non sRGB formats make colors look washed out, so log
+        // the one in use to make such setups easy to spot.
         let renderer = pollster::block_on(Renderer::new(window));
+        let surface_format = renderer.surface_format();
+        println!(
+            "Surface format: {surface_format:?}{}",
+            if surface_format.is_srgb() {
+                ""
+            } else {
+                " (not sRGB)"
+            }
+        );
         let mut storage = RenderStorage::default();
 
-        storage.register_bind_group_layout::<CameraBindGroup>(&renderer);
-        let instance_pipeline = PipelineBuilder {
-            shader_path: "./shaders/instance.wgsl",
-            label: Some("instance_pipeline"),
-            layout_descriptor: Some(&PipelineLayoutDescriptor {
-                label: None,
-                bind_group_layouts: &[storage.get_bind_group_layout::<CameraBindGroup>()],
-                push_constant_ranges: &[],
-            }),
-            vertex_layouts: &[MeshVertex::layout(), InstanceVertex::layout()],
-            vertex_entry_point: "vs_main",
-            color_targets: Some(&[Some(ColorTargetState {
-                format: renderer.surface_format(),
-                blend: None,
-                write_mask: ColorWrites::ALL,
-            })]),
-            fragment_entry_point: "fs_main",
-            primitive: PrimitiveState {
-                topology: PrimitiveTopology::TriangleList,
-                strip_index_format: None,
-                front_face: FrontFace::Ccw,
-                cull_mode: Some(Face::Back),
-                polygon_mode: PolygonMode::Fill,
-                unclipped_depth: false,
-                conservative: false,
-            },
-            depth_stencil: None,
-            multisample: MultisampleState::default(),
-            multiview: None,
-        }
-        .build(&renderer);
-        let instance_pipeline_id = storage.insert_pipeline(instance_pipeline);
-
-        let phase = RenderPhase::new(
-            const_vec![ColorAttachment {
-                view_id: ResourceId::WINDOW_VIEW_ID,
-                ops: Operations {
-                    load: LoadOp::Clear(Color::TRANSPARENT),
-                    store: StoreOp::Store,
-                },
-            },],
-            None,
+        let window_size = window.inner_size();
+        let render_targets = RenderTargets::new(
+            &renderer,
+            window_size.width,
+            window_size.height,
+            config.msaa_samples,
         );
 
-        let camera = GameCamera::new(&renderer, &mut storage, [0.0, 0.0, 5.0]);
-
-        // 2 instances for border
-        // 1 instance for platform
-        // 5 * 7 instances for crates
-        let boxes = Instances::new(&renderer, &mut storage, Quad::new(1.0, 1.0), 2 + 1 + 5 * 7);
+        storage.register_bind_group_layout::<CameraBindGroup>(&renderer);
+        let instance_pipeline_id =
+            build_instance_pipeline(&renderer, &mut storage, render_targets.sample_count(), None)?;
+        let texture_material = TextureMaterial::new(&renderer);
+        let textured_pipeline = match &config.textures {
+            Some(textures) => {
+                let atlas = texture_material.load(&renderer, &mut storage, &textures.atlas)?;
+                let pipeline_id = build_instance_pipeline(
+                    &renderer,
+                    &mut storage,
+                    render_targets.sample_count(),
+                    Some(texture_material.layout()),
+                )?;
+                Some((pipeline_id, atlas))
+            }
+            None => None,
+        };
+        let background = BackgroundPass::new(
+            &renderer,
+            &mut storage,
+            &texture_material,
+            &config.background,
+            render_targets.sample_count(),
+        )?;
 
-        let border = Border::new(
-            15.0,
-            20.0,
-            0.2,
-            [0.7, 0.7, 0.7, 1.0],
-            [0.0, 0.0, 0.0, 0.0],
-            0,
+        let viewport = Viewport::fit(window_size.width, window_size.height, config.logical_aspect);
+        let mut camera = GameCamera::new(&renderer, &mut storage, [0.0, 0.0, 5.0]);
+        camera.resize(&renderer, &storage, viewport.aspect());
+        let mut mesh_cache = MeshCache::default();
+        let unit_quad = mesh_cache.get_or_insert(&renderer, &mut storage, MeshKey::UNIT_QUAD);
+        let hud = Hud::new(
+            &renderer,
+            &mut storage,
+            unit_quad,
+            viewport.width,
+            viewport.height,
+            config.theme.hud,
         );
-        border.render_sync(&renderer, &storage, &boxes);
 
-        let platform = Platform::new(
-            Vector3 {
-                x: 0.0,
-                y: -8.0,
-                z: 0.0,
-            },
-            2.0,
-            0.5,
-            [0.9, 0.16, 0.21, 1.0],
-            5.0,
-            std::mem::size_of::<InstanceUniform>() as u64 * 2,
+        let mut box_allocator = InstanceAllocator::default();
+        let mut simulation = Simulation::new(
+            &config,
+            levels.as_ref().map(LevelSet::current),
+            seed,
+            &mut box_allocator,
         );
-        platform.render_sync(&renderer, &storage, &boxes);
 
-        let ball = Ball::new(
+        let ball_mesh =
+            mesh_cache.get_or_insert(&renderer, &mut storage, Ball::mesh(config.ball.segments));
+        let ball_instances =
+            Instances::new(&renderer, &mut storage, ball_mesh, Ball::MAX_INSTANCES);
+
+        // Particles come after the simulation objects, so resets
+        // give the simulation the same ranges again
+        let particles = new_particles(&mut box_allocator);
+        let boxes = Instances::new(
             &renderer,
             &mut storage,
-            Vector3 {
-                x: 0.0,
-                y: -7.0,
-                z: 0.0,
-            },
-            0.5,
-            [0.0, 0.9, 0.18, 1.0],
-            Vector2 { x: 2.5, y: 2.5 },
-            1.0,
+            unit_quad,
+            box_allocator.allocated(),
         );
+        let mut scene = SceneBuffer::new(box_allocator.allocated());
+        simulation.border.render_sync(&mut scene);
+        simulation.platform.render_sync(&mut scene);
+        simulation.crate_pack.render_sync(&mut scene);
+        particles.render_sync(&mut scene);
+        scene.upload(&renderer, &storage, &boxes);
+        let aim_guide = AimGuide::new(&renderer, &mut storage, unit_quad, config.aim_guide);
+        let show_stats = config.show_stats;
+        #[cfg(feature = "debug")]
+        let debug_overlay = DebugOverlay::new(&renderer, &mut storage, unit_quad);
 
-        let mut crate_pack = CratePack::new(
-            Vector3 {
-                x: 0.0,
-                y: 4.0,
-                z: 0.0,
-            },
-            5,
-            7,
-            1.5,
-            1.0,
-            0.2,
-            0.2,
-            [0.5, 0.5, 0.5, 1.0],
-            std::mem::size_of::<InstanceUniform>() as u64 * 3,
+        let power_up_instances = Instances::new(
+            &renderer,
+            &mut storage,
+            unit_quad,
+            Simulation::MAX_POWER_UPS,
         );
-        crate_pack.render_sync(&renderer, &storage, &boxes);
+        let mut laser_instances = Instances::new(&renderer, &mut storage, unit_quad, 1);
+        laser_instances.instance_num = 0;
+        let mut audio = AudioPlayer::new();
+        audio.set_volume(settings.volume);
 
-        Self {
+        Ok(Self {
             renderer,
             storage,
+            mesh_cache,
             instance_pipeline_id,
+            texture_material,
+            textured_pipeline,
+            background,
+            render_targets,
+            minimized: window_size.width == 0 || window_size.height == 0,
+            viewport,
             box_instances: boxes,
-            phase,
+            scene,
             camera,
-            border,
-            ball,
-            platform,
-            crate_pack,
-        }
+            hud,
+            config,
+            levels,
+            simulation,
+            input: Input::default(),
+            held_keys: HeldKeys::default(),
+            bot: false,
+            ball_instances,
+            power_up_instances,
+            laser_instances,
+            particles,
+            aim_guide,
+            frame_stats: FrameStats::default(),
+            show_stats,
+            #[cfg(feature = "debug")]
+            debug_overlay,
+            #[cfg(feature = "hot-reload")]
+            shader_watcher: ShaderWatcher::new(SHADER_DIR),
+            effects_rng: SmallRng::seed_from_u64(seed),
+            audio,
+            state: GameState::Menu,
+            high_scores: HighScores::load(),
+            score_reporter: ScoreReporter::default(),
+            menu: Menu::default(),
+            settings,
+            options: OptionsMenu::default(),
+            cursor: Vector2::new(0.0, 0.0),
+            quit_requested: false,
+            exited: false,
+            event_callbacks: vec![],
+            accumulated_time: 0.0,
+            skip_frame_time: false,
+            overlay_time: 0.0,
+        })
+    }
+
+    #[inline]
+    pub fn simulation(&self) -> &Simulation {
+        &self.simulation
+    }
+
+    #[inline]
+    pub fn charge_meter(&self) -> &ChargeMeter {
+        &self.simulation.charge_meter
+    }
+
+    // Chance in [0, 1] of a destroyed crate to drop a power-up
+    pub fn set_power_up_chance(&mut self, chance: f32) {
+        self.simulation.set_power_up_chance(chance);
+    }
+
+    // Unit quad shared by all quad shaped objects
+    pub fn unit_quad_mesh_id(&mut self) -> ResourceId {
+        self.mesh_cache
+            .get_or_insert(&self.renderer, &mut self.storage, MeshKey::UNIT_QUAD)
+    }
+
+    // Uploads of the shared scene buffer so far, one per frame at most
+    #[inline]
+    pub fn scene_buffer_writes(&self) -> u64 {
+        self.scene.writes()
+    }
+
+    #[inline]
+    pub fn seed(&self) -> u64 {
+        self.simulation.seed()
+    }
+
+    #[inline]
+    pub fn lives(&self) -> u32 {
+        self.simulation.lives()
+    }
+
+    #[inline]
+    pub fn score(&self) -> u32 {
+        self.simulation.score()
+    }
+
+    // Crates destroyed since a ball last touched the platform
+    #[inline]
+    pub fn combo(&self) -> u32 {
+        self.simulation.combo()
+    }
+
+    // Current width of the platform, which may be tweening
+    // after a power-up or shrinking with returned balls
+    #[inline]
+    pub fn paddle_width(&self) -> f32 {
+        self.simulation.platform.width()
+    }
+
+    // Number of the level being played counting from 1,
+    // always 1 without a level set
+    #[inline]
+    pub fn current_level(&self) -> usize {
+        self.levels.as_ref().map_or(0, LevelSet::index) + 1
+    }
+
+    #[inline]
+    fn level(&self) -> Option<&Level> {
+        self.levels.as_ref().map(LevelSet::current)
+    }
+
+    #[inline]
+    pub fn is_game_over(&self) -> bool {
+        self.state == GameState::GameOver
+    }
+
+    #[inline]
+    pub fn state(&self) -> GameState {
+        self.state
+    }
+
+    // Sorted from the highest
+    #[inline]
+    pub fn high_scores(&self) -> &[ScoreEntry] {
+        self.high_scores.entries()
+    }
+
+    // Registers the `callback` to be called during `update` with every
+    // event right after the physics step which produced it, after sounds,
+    // particles and camera shake are applied. Callbacks run in the order
+    // they were registered. They do not get access to the game, and must
+    // not call back into `update` through shared ownership either.
+    pub fn on_event(&mut self, callback: EventCallback) {
+        self.event_callbacks.push(callback);
+    }
+
+    // Replaces the sink which receives the score of every game once it is
+    // won or lost, for example to post it to a leaderboard. Scores are not
+    // submitted anywhere by default, see `ScoreLog` for a file based one.
+    pub fn set_score_sink(&mut self, sink: Box<dyn ScoreSink>) {
+        self.score_reporter = ScoreReporter::new(sink);
+    }
+
+    // Set once Quit is chosen in the menu
+    #[inline]
+    pub fn should_quit(&self) -> bool {
+        self.quit_requested
     }
 
     pub fn handle_input(&mut self, key: &Key, state: &ElementState) {
-        self.platform.handle_input(key, state);
+        match self.state {
+            GameState::Menu if *state == ElementState::Pressed => self.handle_menu_input(key),
+            GameState::Options if *state == ElementState::Pressed => self.handle_options_input(key),
+            GameState::Menu | GameState::Options => {}
+            _ => self.handle_game_input(key, state),
+        }
+    }
+
+    fn handle_game_input(&mut self, key: &Key, state: &ElementState) {
+        let pressed = *state == ElementState::Pressed;
+        let action = match key {
+            Key::Named(NamedKey::ArrowLeft) => Some(Action::Left),
+            Key::Named(NamedKey::ArrowRight) => Some(Action::Right),
+            Key::Character(c) => self.settings.bindings.action(c),
+            _ => None,
+        };
+        if let Some(action) = action {
+            match action {
+                Action::Left | Action::Right => self.held_keys.set(action, pressed),
+                Action::SuperShot => self.input.super_shot |= pressed,
+            }
+            self.input.movement = self.held_keys.movement();
+            // Bound keys do nothing else, even if they
+            // match one of the fixed shortcuts below
+            return;
+        }
+
+        if *state == ElementState::Pressed {
+            match key {
+                #[cfg(feature = "debug")]
+                Key::Named(NamedKey::F1) => self.debug_overlay.toggle(),
+                Key::Named(NamedKey::F3) => self.show_stats = !self.show_stats,
+                Key::Named(NamedKey::Escape) => {
+                    if matches!(self.state, GameState::GameOver | GameState::Won) {
+                        self.state = GameState::Menu;
+                    } else {
+                        self.toggle_pause();
+                    }
+                }
+                Key::Named(NamedKey::Space) => self.launch_ball(),
+                Key::Character(c) => match c.as_str() {
+                    "p" | "P" => self.toggle_pause(),
+                    "r" | "R" => {
+                        if matches!(self.state, GameState::GameOver | GameState::Won) {
+                            self.reset();
+                        }
+                    }
+                    "c" | "C" => self.simulation.toggle_rainbow(),
+                    "g" | "G" => self.aim_guide.toggle(),
+                    "b" | "B" => {
+                        self.bot = !self.bot;
+                        self.input.movement = self.held_keys.movement();
+                    }
+                    _ => {}
+                },
+                _ => {}
+            }
+        }
+    }
+
+    fn handle_menu_input(&mut self, key: &Key) {
+        match key {
+            Key::Named(NamedKey::ArrowUp) => self.menu.select_prev(),
+            Key::Named(NamedKey::ArrowDown) => self.menu.select_next(),
+            Key::Named(NamedKey::Enter | NamedKey::Space) => self.activate_menu_item(),
+            Key::Character(c) => match c.as_str() {
+                "w" | "W" => self.menu.select_prev(),
+                "s" | "S" => self.menu.select_next(),
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+
+    fn activate_menu_item(&mut self) {
+        match self.menu.selected() {
+            MenuItem::Start => self.reset(),
+            MenuItem::Continue => self.continue_game(),
+            MenuItem::Rainbow => self.config.ball.rainbow = !self.config.ball.rainbow,
+            MenuItem::Theme => self.set_theme(self.config.theme.next()),
+            MenuItem::Options => self.state = GameState::Options,
+            MenuItem::Quit => self.quit_requested = true,
+        }
+    }
+
+    // While an action is being rebound the next character key is bound
+    // to it, other keys except Escape, which keeps the old key, are ignored
+    fn handle_options_input(&mut self, key: &Key) {
+        if let Some(action) = self.options.rebinding() {
+            match key {
+                Key::Character(c) => {
+                    if self.settings.bindings.bind(action, c) {
+                        self.options.stop_rebinding();
+                    }
+                }
+                Key::Named(NamedKey::Escape) => self.options.stop_rebinding(),
+                _ => {}
+            }
+            return;
+        }
+        match key {
+            Key::Named(NamedKey::ArrowUp) => self.options.select_prev(),
+            Key::Named(NamedKey::ArrowDown) => self.options.select_next(),
+            Key::Named(NamedKey::ArrowLeft) => self.adjust_option(-1.0),
+            Key::Named(NamedKey::ArrowRight) => self.adjust_option(1.0),
+            Key::Named(NamedKey::Enter | NamedKey::Space) => self.activate_option(),
+            Key::Named(NamedKey::Escape) => self.state = GameState::Menu,
+            _ => {}
+        }
+    }
+
+    // Moves the selected volume or difficulty one step up or down
+    fn adjust_option(&mut self, direction: f32) {
+        match self.options.selected() {
+            OptionsItem::Volume => {
+                let steps = (self.settings.volume / Settings::VOLUME_STEP).round() + direction;
+                self.set_volume(steps * Settings::VOLUME_STEP);
+            }
+            OptionsItem::Difficulty if direction < 0.0 => {
+                self.set_difficulty(self.settings.difficulty.prev())
+            }
+            OptionsItem::Difficulty => self.set_difficulty(self.settings.difficulty.next()),
+            OptionsItem::Binding(_) | OptionsItem::Back => {}
+        }
+    }
+
+    // Volume goes up and wraps around to zero, so
+    // the mouse alone can pick any of the volumes
+    fn activate_option(&mut self) {
+        match self.options.selected() {
+            OptionsItem::Volume => {
+                let steps = (self.settings.volume / Settings::VOLUME_STEP).round() + 1.0;
+                let volume = steps * Settings::VOLUME_STEP;
+                self.set_volume(if 1.0 + f32::EPSILON < volume {
+                    0.0
+                } else {
+                    volume
+                });
+            }
+            OptionsItem::Difficulty => self.set_difficulty(self.settings.difficulty.next()),
+            OptionsItem::Binding(action) => self.options.start_rebinding(action),
+            OptionsItem::Back => self.state = GameState::Menu,
+        }
     }
 
+    fn set_volume(&mut self, volume: f32) {
+        self.settings.set_volume(volume);
+        self.audio.set_volume(self.settings.volume);
+    }
+
+    // Applied to the config right away, the next game is built with it
+    fn set_difficulty(&mut self, difficulty: Difficulty) {
+        self.settings.difficulty = difficulty;
+        difficulty.apply(&mut self.config);
+    }
+
+    // `position` is in window coordinates with the origin in the top left corner
+    pub fn cursor_moved(&mut self, position: PhysicalPosition<f64>) {
+        self.cursor = self.hud.window_to_screen(
+            self.viewport
+                .to_local(Vector2::new(position.x as f32, position.y as f32)),
+        );
+        let Some(i) = self.hud.menu_line_at(&self.menu_lines(), self.cursor) else {
+            return;
+        };
+        match self.state {
+            GameState::Menu => self.menu.select(i),
+            GameState::Options if self.options.rebinding().is_none() => self.options.select(i),
+            _ => {}
+        }
+    }
+
+    pub fn mouse_input(&mut self, button: MouseButton, state: ElementState) {
+        if button != MouseButton::Left || state != ElementState::Pressed {
+            return;
+        }
+        let Some(i) = self.hud.menu_line_at(&self.menu_lines(), self.cursor) else {
+            return;
+        };
+        match self.state {
+            GameState::Menu => {
+                self.menu.select(i);
+                self.activate_menu_item();
+            }
+            GameState::Options if self.options.rebinding().is_none() => {
+                self.options.select(i);
+                self.activate_option();
+            }
+            _ => {}
+        }
+    }
+
+    // Lines of the menu or the options screen, none in other states
+    fn menu_lines(&self) -> Vec<String> {
+        match self.state {
+            GameState::Menu => self
+                .menu
+                .lines(self.config.ball.rainbow, self.config.theme.name),
+            GameState::Options => self.options.lines(&self.settings),
+            _ => vec![],
+        }
+    }
+
+    // The ball is launched on the next physics step.
+    // Ignored during the countdown.
+    #[inline]
+    pub fn launch_ball(&mut self) {
+        if self.state == GameState::Playing {
+            self.input.launch = true;
+        }
+    }
+
+    // Replaces the input for the next physics step, so the game can be
+    // driven by something else than the keyboard. Launches are ignored
+    // during the countdown, same as with `launch_ball`.
+    pub fn set_input(&mut self, input: Input) {
+        self.input = input;
+        self.input.launch &= self.state == GameState::Playing;
+    }
+
+    // Positive `axis` moves the platform right
+    #[inline]
+    pub fn set_analog_movement(&mut self, axis: f32) {
+        self.input.movement = axis;
+    }
+
+    // Starts over from the first level of the set with the same seed and
+    // the current config. The simulation is rebuilt from scratch and synced
+    // to the GPU again on the next `render_sync`.
+    pub fn reset(&mut self) {
+        if let Some(levels) = self.levels.as_mut() {
+            levels.restart();
+        }
+        self.rebuild_simulation(self.simulation.seed());
+        self.score_reporter.start();
+        self.start_round();
+        self.start_countdown();
+    }
+
+    // Builds the simulation of the current level with the `seed`. Levels
+    // of a set have different numbers of crates, so the box instances are
+    // laid out again and everything in them is written anew.
+    // Returns the replaced simulation.
+    fn rebuild_simulation(&mut self, seed: u64) -> Simulation {
+        let mut box_allocator = InstanceAllocator::default();
+        let simulation = Simulation::new(&self.config, self.level(), seed, &mut box_allocator);
+        let previous = std::mem::replace(&mut self.simulation, simulation);
+        self.particles = new_particles(&mut box_allocator);
+
+        let allocated = box_allocator.allocated();
+        self.box_instances
+            .ensure_capacity(&self.renderer, &mut self.storage, allocated);
+        self.scene.resize(allocated);
+        self.simulation.border.render_sync(&mut self.scene);
+        self.simulation.platform.render_sync(&mut self.scene);
+        self.simulation.crate_pack.render_sync(&mut self.scene);
+        self.particles.render_sync(&mut self.scene);
+        previous
+    }
+
+    // Clears the input and the time left over from the previous round.
+    // Keys held through it keep moving the platform.
+    fn start_round(&mut self) {
+        self.input = Input {
+            movement: self.held_keys.movement(),
+            ..Default::default()
+        };
+        self.accumulated_time = 0.0;
+    }
+
+    // Builds the next level of the set, keeping the score and the lives,
+    // and shows its number before the countdown
+    fn start_next_level(&mut self) {
+        let previous = self.rebuild_simulation(self.simulation.seed());
+        self.simulation.carry_over(&previous);
+        self.start_round();
+        self.state = GameState::LevelTransition {
+            remaining: LEVEL_TRANSITION,
+        };
+    }
+
+    // Recolors everything drawn from the config. The border does not
+    // change during the game, so it is only synced here. Later resets
+    // build the simulation with this theme.
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.simulation.set_theme(&theme);
+        self.simulation.border.render_sync(&mut self.scene);
+        self.hud.set_color(theme.hud);
+        self.config.theme = theme;
+    }
+
+    // Continues the saved game, or starts a new one if there is none
+    fn continue_game(&mut self) {
+        match GameSnapshot::load() {
+            Ok(Some(snapshot)) => {
+                if let Err(e) = self.load_state(snapshot) {
+                    eprintln!("Could not continue the saved game: {e:?}");
+                }
+            }
+            Ok(None) => self.reset(),
+            Err(e) => eprintln!("Could not load the saved game: {e:?}"),
+        }
+    }
+
+    // Everything needed to continue the current game later.
+    // See `Simulation::save_state` for how it affects randomness.
+    pub fn save_state(&mut self) -> GameSnapshot {
+        GameSnapshot {
+            level: self
+                .levels
+                .as_ref()
+                .map(|levels| levels.current_path().to_string()),
+            seed: self.simulation.seed(),
+            simulation: self.simulation.save_state(),
+        }
+    }
+
+    // Continues the game from the `snapshot` after a countdown. Snapshots
+    // of levels outside of the set or of other crate layouts are rejected.
+    pub fn load_state(&mut self, snapshot: GameSnapshot) -> Result<(), SnapshotError> {
+        // Levels are switched only once the snapshot is accepted
+        let mut levels = self.levels.clone();
+        let selected = match (levels.as_mut(), snapshot.level.as_deref()) {
+            (Some(levels), Some(path)) => levels.select(path),
+            (None, None) => true,
+            _ => false,
+        };
+        if !selected {
+            return Err(SnapshotError::LevelMismatch {
+                expected: self.config.level_path.clone(),
+                found: snapshot.level,
+            });
+        }
+        let expected = Simulation::new(
+            &self.config,
+            levels.as_ref().map(LevelSet::current),
+            snapshot.seed,
+            &mut InstanceAllocator::default(),
+        )
+        .crate_pack
+        .crates
+        .len();
+        let found = snapshot.simulation.crate_pack.crates.len();
+        if expected != found {
+            return Err(SnapshotError::CrateMismatch { expected, found });
+        }
+        self.levels = levels;
+        self.rebuild_simulation(snapshot.seed);
+        self.simulation.load_state(&snapshot.simulation);
+        self.score_reporter.start();
+
+        self.start_round();
+        self.start_countdown();
+        Ok(())
+    }
+
+    // Persists the game in progress and the high scores and stops the audio
+    // before the window closes. GPU resources are released once the game
+    // is dropped. Calling it again does nothing.
+    pub fn on_exit(&mut self) {
+        if self.exited {
+            return;
+        }
+        self.exited = true;
+        self.save_progress();
+        if let Err(e) = self.high_scores.save() {
+            eprintln!("Could not save high scores: {e:?}");
+        }
+        if let Err(e) = self.settings.save() {
+            eprintln!("Could not save settings: {e:?}");
+        }
+        self.audio.stop();
+    }
+
+    // Writes the game in progress to disk, so it can be continued
+    // from the menu. Called on pause and before the game exits.
+    pub fn save_progress(&mut self) {
+        if !matches!(
+            self.state,
+            GameState::Playing
+                | GameState::Paused
+                | GameState::Countdown { .. }
+                | GameState::LevelTransition { .. }
+        ) {
+            return;
+        }
+        if let Err(e) = self.save_state().save() {
+            eprintln!("Could not save the game: {e:?}");
+        }
+    }
+
+    // Starts a new round, playing right away if the countdown is disabled
+    fn start_countdown(&mut self) {
+        self.state = if 0.0 < self.config.countdown {
+            GameState::Countdown {
+                remaining: self.config.countdown,
+            }
+        } else {
+            GameState::Playing
+        };
+    }
+
+    // Starts playing once the countdown runs out. The ball
+    // is launched right away if `auto_launch` is set,
+    // otherwise it waits on the platform for the player.
+    fn update_countdown(&mut self, remaining: f32, dt: f32) {
+        let remaining = remaining - dt;
+        if 0.0 < remaining {
+            self.state = GameState::Countdown { remaining };
+        } else {
+            self.state = GameState::Playing;
+            if self.config.auto_launch {
+                self.launch_ball();
+            }
+        }
+    }
+
+    // Switches between `Playing` and `Paused`, other states are left as is
+    fn toggle_pause(&mut self) {
+        match self.state {
+            GameState::Playing => self.pause(),
+            GameState::Paused => self.resume(),
+            _ => {}
+        }
+    }
+
+    fn pause(&mut self) {
+        self.state = GameState::Paused;
+        self.save_progress();
+    }
+
+    fn resume(&mut self) {
+        self.state = GameState::Playing;
+        self.skip_frame_time = true;
+    }
+
+    // Losing focus always pauses the game. Regaining it only resumes
+    // if `resume_on_focus` is set in the config, otherwise the player
+    // has to unpause manually.
+    pub fn focus_changed(&mut self, focused: bool) {
+        if !focused {
+            self.held_keys.release_all();
+            self.input.movement = 0.0;
+        }
+        let state = self
+            .state
+            .after_focus_change(focused, self.config.resume_on_focus);
+        match (self.state, state) {
+            (GameState::Playing, GameState::Paused) => self.pause(),
+            (GameState::Paused, GameState::Playing) => self.resume(),
+            _ => {}
+        }
+    }
+
+    // The camera keeps the vertical extent of the world, so the playfield
+    // stays centered in any window. With `logical_aspect` set everything is
+    // drawn into a viewport of that shape instead, so the world and the HUD
+    // are not stretched or widened. Minimized windows report a zero size,
+    // which is not a valid surface size, so the surface keeps its old size
+    // and nothing is rendered until the window is restored.
     pub fn resize(&mut self, physical_size: PhysicalSize<u32>) {
+        self.minimized = physical_size.width == 0 || physical_size.height == 0;
+        if self.minimized {
+            return;
+        }
         self.renderer.resize(Some(physical_size));
+        self.render_targets
+            .resize(&self.renderer, physical_size.width, physical_size.height);
+        self.viewport = Viewport::fit(
+            physical_size.width,
+            physical_size.height,
+            self.config.logical_aspect,
+        );
+        self.camera
+            .resize(&self.renderer, &self.storage, self.viewport.aspect());
+        self.hud.resize(
+            &self.renderer,
+            &self.storage,
+            self.viewport.width,
+            self.viewport.height,
+        );
     }
 
-    pub fn update(&mut self, dt: f32) {
-        self.platform.update(&self.border, dt);
-        self.ball
-            .update(&self.border, &self.platform, &mut self.crate_pack, dt);
+    // Advances the game by `dt` seconds of the frame time. Physics runs in fixed
+    // steps of `PHYSICS_DT`, so a frame can do zero or more steps and the time
+    // left is carried over to the next frame. Physics does not run unless the
+    // game is `Playing`, so time spent paused is simply dropped, including
+    // the frame right after unpausing which may span the paused time.
+    // Returns events of all physics steps taken during this frame.
+    // Sounds, particles and camera shake are already applied for them
+    // and callbacks registered with `on_event` have already seen them.
+    pub fn update(&mut self, dt: f32) -> Vec<GameEvent> {
+        // The shake also settles while the game is not playing,
+        // so the camera does not stay offset after the last crate
+        self.camera.update(&self.renderer, &self.storage, dt);
+        self.overlay_time += dt.min(MAX_FRAME_TIME);
+        self.frame_stats.record(dt);
+
+        let mut events = vec![];
+        match self.state {
+            GameState::Playing if self.skip_frame_time => {
+                self.skip_frame_time = false;
+                return events;
+            }
+            GameState::Countdown { remaining } => {
+                self.update_countdown(remaining, dt);
+                return events;
+            }
+            GameState::LevelTransition { remaining } => {
+                let remaining = remaining - dt;
+                if 0.0 < remaining {
+                    self.state = GameState::LevelTransition { remaining };
+                } else {
+                    self.start_countdown();
+                }
+                return events;
+            }
+            _ if !self.state.is_simulated() => return events,
+            _ => {}
+        }
+
+        self.accumulated_time += dt.min(MAX_FRAME_TIME);
+        while PHYSICS_DT <= self.accumulated_time && self.state.is_simulated() {
+            self.step(PHYSICS_DT, &mut events);
+            self.accumulated_time -= PHYSICS_DT;
+        }
+        events
+    }
+
+    fn step(&mut self, dt: f32, events: &mut Vec<GameEvent>) {
+        if self.bot {
+            self.set_input(bot::follow_ball(&self.simulation));
+        }
+        let lives = self.simulation.lives();
+        self.simulation.step(dt, &self.input);
+        // One shot actions are only applied once
+        self.input.launch = false;
+        self.input.super_shot = false;
+
+        for event in self.simulation.events.iter() {
+            match *event {
+                GameEvent::WallHit { .. } | GameEvent::CrateHit { .. } => {
+                    self.audio.play(SoundId::Wall)
+                }
+                GameEvent::PaddleHit => self.audio.play(SoundId::Paddle),
+                GameEvent::CrateDestroyed { points, pos, color } => {
+                    self.particles
+                        .spawn_burst(pos, color, 12, &mut self.effects_rng);
+                    self.audio.play(SoundId::Break);
+                    self.camera.add_shake(points as f32 * SHAKE_PER_POINT);
+                }
+                GameEvent::BallLost | GameEvent::LevelCleared | GameEvent::ExtraLife => {}
+            }
+        }
+        for event in self.simulation.events.iter() {
+            for callback in self.event_callbacks.iter_mut() {
+                callback(event);
+            }
+        }
+        events.append(&mut self.simulation.events);
+        self.particles.update(dt);
+        if let Some(min_speed) = self.config.ball.speed_trail {
+            spawn_speed_trails(
+                &mut self.particles,
+                &self.simulation.balls,
+                min_speed,
+                &mut self.effects_rng,
+            );
+        }
+
+        if self.simulation.is_won() {
+            // Only the last level of the set wins the game
+            if self.levels.as_mut().is_some_and(LevelSet::advance) {
+                self.start_next_level();
+            } else {
+                self.state = GameState::Won;
+                self.record_score();
+            }
+        } else if self.simulation.is_lost() {
+            self.state = GameState::GameOver;
+            self.record_score();
+        } else if self.simulation.lives() < lives {
+            // New round with the ball back on the platform
+            self.accumulated_time = 0.0;
+            self.start_countdown();
+        }
+    }
+
+    // Records the score of the finished game
+    // and removes its save, if there is one
+    fn record_score(&mut self) {
+        if let Err(e) = GameSnapshot::delete() {
+            eprintln!("Could not delete the saved game: {e:?}");
+        }
+        let entry = ScoreEntry {
+            score: self.simulation.score(),
+            name: None,
+        };
+        if self.high_scores.insert(entry) {
+            if let Err(e) = self.high_scores.save() {
+                eprintln!("Could not save high scores: {e:?}");
+            }
+        }
+        self.score_reporter.finish(&FinishedGame {
+            score: self.simulation.score(),
+            level: self.levels.as_ref().map_or(0, LevelSet::index),
+            duration: self.simulation.play_time(),
+            seed: self.simulation.seed(),
+            won: self.simulation.is_won(),
+        });
+    }
+
+    // Rebuilds the instance pipeline once its shader changes on disk.
+    // Shaders that fail to compile are reported and the old pipeline is kept.
+    // Replaced pipelines stay in the storage, which is fine while editing.
+    #[cfg(feature = "hot-reload")]
+    fn reload_shaders(&mut self) {
+        let Some(watcher) = &self.shader_watcher else {
+            return;
+        };
+        let instance_shader = std::path::Path::new(INSTANCE_SHADER).file_name();
+        if !watcher
+            .changed()
+            .iter()
+            .any(|path| path.file_name() == instance_shader)
+        {
+            return;
+        }
+        match build_instance_pipeline(
+            &self.renderer,
+            &mut self.storage,
+            self.render_targets.sample_count(),
+            None,
+        ) {
+            Ok(id) => {
+                self.instance_pipeline_id = id;
+                println!("Reloaded {INSTANCE_SHADER}");
+            }
+            Err(e) => eprintln!("Could not reload shaders: {e:?}"),
+        }
+        if let Some((pipeline_id, _)) = self.textured_pipeline.as_mut() {
+            match build_instance_pipeline(
+                &self.renderer,
+                &mut self.storage,
+                self.render_targets.sample_count(),
+                Some(self.texture_material.layout()),
+            ) {
+                Ok(id) => *pipeline_id = id,
+                Err(e) => eprintln!("Could not reload the textured pipeline: {e:?}"),
+            }
+        }
     }
 
     pub fn render_sync(&mut self) {
-        self.platform
-            .render_sync(&self.renderer, &self.storage, &self.box_instances);
-        self.ball.render_sync(&self.renderer, &self.storage);
-        self.crate_pack
-            .render_sync(&self.renderer, &self.storage, &self.box_instances);
+        #[cfg(feature = "hot-reload")]
+        self.reload_shaders();
+
+        self.simulation.platform.render_sync(&mut self.scene);
+        self.simulation.crate_pack.render_sync(&mut self.scene);
+        self.particles.render_sync(&mut self.scene);
+        self.scene
+            .upload(&self.renderer, &self.storage, &self.box_instances);
+        let balls = self
+            .simulation
+            .balls
+            .iter()
+            .flat_map(Ball::instances)
+            .collect::<Vec<_>>();
+        self.ball_instances
+            .ensure_capacity(&self.renderer, &mut self.storage, balls.len() as u32);
+        self.ball_instances
+            .instance_buffer_handle
+            .update(&self.renderer, &self.storage, 0, &balls);
+        self.aim_guide.render_sync(
+            &self.renderer,
+            &self.storage,
+            &self.simulation,
+            self.config.theme.ball,
+        );
+        let power_ups = self
+            .simulation
+            .power_ups
+            .iter()
+            .map(PowerUp::instance)
+            .collect::<Vec<_>>();
+        self.power_up_instances.instance_buffer_handle.update(
+            &self.renderer,
+            &self.storage,
+            0,
+            &power_ups,
+        );
+        self.power_up_instances.instance_num = power_ups.len() as u32;
+        let laser_warning = self.simulation.laser_warning().map(laser::warning_instance);
+        if let Some(instance) = laser_warning {
+            self.laser_instances.instance_buffer_handle.update(
+                &self.renderer,
+                &self.storage,
+                0,
+                &[instance],
+            );
+        }
+        self.laser_instances.instance_num = laser_warning.is_some().into();
+        #[cfg(feature = "debug")]
+        self.debug_overlay
+            .render_sync(&self.renderer, &mut self.storage, &self.simulation);
+        if matches!(self.state, GameState::Menu | GameState::Options) {
+            self.hud
+                .render_menu(&self.renderer, &self.storage, &self.menu_lines());
+        } else {
+            // Seconds left are shown as 3, 2, 1
+            let center = match self.state {
+                GameState::Countdown { remaining } => vec![(remaining.ceil() as u32).to_string()],
+                GameState::LevelTransition { .. } => {
+                    vec![format!("LEVEL {}", self.current_level())]
+                }
+                GameState::GameOver | GameState::Won => {
+                    let title = if self.state == GameState::Won {
+                        "YOU WON"
+                    } else {
+                        "GAME OVER"
+                    };
+                    std::iter::once(title.to_string())
+                        .chain(
+                            self.high_scores
+                                .entries()
+                                .iter()
+                                .take(SHOWN_HIGH_SCORES)
+                                .enumerate()
+                                .map(|(i, entry)| format!("{}. {}", i + 1, entry.score)),
+                        )
+                        .collect()
+                }
+                GameState::Paused => vec!["PAUSED".to_string()],
+                _ => vec![],
+            };
+            let overlay = if self.state == GameState::Paused {
+                HudOverlay {
+                    dim: self.config.pause_dim,
+                    center_alpha: 0.6 + 0.4 * (self.overlay_time * PAUSE_PULSE_SPEED).sin(),
+                }
+            } else {
+                HudOverlay::NONE
+            };
+            self.hud.render_sync(
+                &self.renderer,
+                &self.storage,
+                self.simulation.score(),
+                self.simulation.lives(),
+                self.combo(),
+                self.levels.as_ref().map(|_| self.current_level()),
+                &center,
+                &self.stats_lines(),
+                overlay,
+            );
+        }
+    }
+
+    // Lines of the stats overlay, none if it is hidden.
+    // Ball speed is the one of the first ball in world units per second.
+    fn stats_lines(&self) -> Vec<String> {
+        if !self.show_stats {
+            return vec![];
+        }
+        let ball_speed = self.simulation.balls.first().map_or(0.0, |ball| {
+            let velocity = ball.velocity() * ball.speed();
+            velocity.x.hypot(velocity.y)
+        });
+        vec![
+            format!("FPS {:.0}", self.frame_stats.fps()),
+            format!("FRAME {:.2}MS", self.frame_stats.frame_time() * 1000.0),
+            format!("BALL SPEED {ball_speed:.1}"),
+            format!("CRATES {}", self.simulation.crate_pack.remaining()),
+            format!("PADDLE {:.2}", self.paddle_width()),
+        ]
     }
 
     pub fn render(&mut self) -> bool {
+        if self.minimized {
+            return true;
+        }
         let current_frame_context = match self.renderer.current_frame() {
             Ok(cfc) => cfc,
             Err(SurfaceError::Lost) => {
@@ -229,16 +1473,44 @@ impl<'window> Game<'window> {
 
         let mut encoder = self.renderer.create_encoder();
 
-        let ball_command = self
-            .ball
+        // Only crates and balls have sprites
+        let sprites_command = |instances: &Instances| match self.textured_pipeline {
+            Some((pipeline_id, atlas)) => {
+                instances.textured_render_command(pipeline_id, self.camera.bind_group.0, atlas)
+            }
+            None => instances.render_command(self.instance_pipeline_id, self.camera.bind_group.0),
+        };
+        let ball_command = sprites_command(&self.ball_instances);
+        let boxes_command = sprites_command(&self.box_instances);
+        let power_ups_command = self
+            .power_up_instances
             .render_command(self.instance_pipeline_id, self.camera.bind_group.0);
-        let boxes_command = self
-            .box_instances
+        let laser_command = self
+            .laser_instances
             .render_command(self.instance_pipeline_id, self.camera.bind_group.0);
+        let aim_guide_command = self
+            .aim_guide
+            .render_command(self.instance_pipeline_id, self.camera.bind_group.0);
+        #[cfg(feature = "debug")]
+        let debug_command = self
+            .debug_overlay
+            .render_command(self.instance_pipeline_id, self.camera.bind_group.0);
+        let hud_command = self.hud.render_command(self.instance_pipeline_id);
+        let background_command = self.background.render_command();
         {
-            let mut render_pass = self.phase.render_pass(&mut encoder, &current_frame_storage);
+            let mut render_pass = self
+                .render_targets
+                .render_pass(&mut encoder, current_frame_context.view());
+            self.viewport.apply(&mut render_pass);
+            background_command.execute(&mut render_pass, &current_frame_storage);
             boxes_command.execute(&mut render_pass, &current_frame_storage);
+            power_ups_command.execute(&mut render_pass, &current_frame_storage);
+            laser_command.execute(&mut render_pass, &current_frame_storage);
+            aim_guide_command.execute(&mut render_pass, &current_frame_storage);
             ball_command.execute(&mut render_pass, &current_frame_storage);
+            #[cfg(feature = "debug")]
+            debug_command.execute(&mut render_pass, &current_frame_storage);
+            hud_command.execute(&mut render_pass, &current_frame_storage);
         }
 
         let commands = encoder.finish();
@@ -248,3 +1520,189 @@ impl<'window> Game<'window> {
         true
     }
 }
+
+// Builds the pipeline used for everything drawn with instances and returns
+// its id in the `storage`. Instances are flat colored, or sample a texture
+// with the `texture_layout` in group 1 if it is given.
+fn build_instance_pipeline(
+    renderer: &Renderer,
+    storage: &mut RenderStorage,
+    sample_count: u32,
+    texture_layout: Option<&BindGroupLayout>,
+) -> Result<ResourceId, GameError> {
+    let shader_path = INSTANCE_SHADER;
+    // The pipeline builder reads the shader itself and panics if it can not
+    std::fs::metadata(shader_path).map_err(|e| GameError::Shader {
+        path: shader_path,
+        error: e.to_string(),
+    })?;
+    // Invalid shaders are reported through the error scope
+    // instead of the default handler, which panics
+    renderer.device().push_error_scope(ErrorFilter::Validation);
+    let camera_layout = storage.get_bind_group_layout::<CameraBindGroup>();
+    let (label, bind_group_layouts, fragment_entry_point) = match texture_layout {
+        Some(texture_layout) => (
+            "textured_instance_pipeline",
+            vec![camera_layout, texture_layout],
+            "fs_textured",
+        ),
+        None => ("instance_pipeline", vec![camera_layout], "fs_main"),
+    };
+    let instance_pipeline = PipelineBuilder {
+        shader_path,
+        label: Some(label),
+        layout_descriptor: Some(&PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &bind_group_layouts,
+            push_constant_ranges: &[],
+        }),
+        vertex_layouts: &[MeshVertex::layout(), InstanceVertex::layout()],
+        vertex_entry_point: "vs_main",
+        color_targets: Some(&[Some(ColorTargetState {
+            format: renderer.surface_format(),
+            blend: Some(BlendState::ALPHA_BLENDING),
+            write_mask: ColorWrites::ALL,
+        })]),
+        fragment_entry_point,
+        primitive: PrimitiveState {
+            topology: PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: FrontFace::Ccw,
+            cull_mode: Some(Face::Back),
+            polygon_mode: PolygonMode::Fill,
+            unclipped_depth: false,
+            conservative: false,
+        },
+        depth_stencil: Some(DepthStencilState {
+            format: RenderTargets::DEPTH_FORMAT,
+            depth_write_enabled: true,
+            // Objects on the same depth are drawn in the submission order
+            depth_compare: CompareFunction::LessEqual,
+            stencil: StencilState::default(),
+            bias: DepthBiasState::default(),
+        }),
+        multisample: MultisampleState {
+            count: sample_count,
+            ..Default::default()
+        },
+        multiview: None,
+    }
+    .build(renderer);
+    if let Some(error) = pollster::block_on(renderer.device().pop_error_scope()) {
+        return Err(GameError::Shader {
+            path: shader_path,
+            error: error.to_string(),
+        });
+    }
+    Ok(storage.insert_pipeline(instance_pipeline))
+}
+
+// Particles are allocated after the objects of the simulation
+fn new_particles(allocator: &mut InstanceAllocator) -> ParticleSystem {
+    ParticleSystem::new(256, 0.15, 4.0, 0.6, allocator)
+}
+
+// Leaves a trail particle at every launched ball faster than the `min_speed`
+fn spawn_speed_trails(
+    particles: &mut ParticleSystem,
+    balls: &[Ball],
+    min_speed: f32,
+    rng: &mut impl Rng,
+) {
+    for ball in balls {
+        if ball.is_launched() && min_speed < ball.speed() {
+            particles.spawn_trail(ball.position().truncate(), ball.color(), rng);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn losing_focus_pauses_the_game() {
+        assert_eq!(
+            GameState::Playing.after_focus_change(false, false),
+            GameState::Paused
+        );
+        assert_eq!(
+            GameState::Playing.after_focus_change(false, true),
+            GameState::Paused
+        );
+        // The menu has nothing to pause
+        assert_eq!(
+            GameState::Menu.after_focus_change(false, false),
+            GameState::Menu
+        );
+    }
+
+    #[test]
+    fn regaining_focus_resumes_only_if_configured() {
+        assert_eq!(
+            GameState::Paused.after_focus_change(true, false),
+            GameState::Paused
+        );
+        assert_eq!(
+            GameState::Paused.after_focus_change(true, true),
+            GameState::Playing
+        );
+    }
+
+    #[test]
+    fn releasing_one_of_two_held_keys_moves_towards_the_other() {
+        let mut held_keys = HeldKeys::default();
+        held_keys.set(Action::Left, true);
+        assert_eq!(held_keys.movement(), -1.0);
+        held_keys.set(Action::Right, true);
+        assert_eq!(held_keys.movement(), 0.0);
+        held_keys.set(Action::Right, false);
+        assert_eq!(held_keys.movement(), -1.0);
+
+        let config = GameConfig::default();
+        let mut simulation = Simulation::new(&config, None, 0, &mut InstanceAllocator::default());
+        let start = simulation.platform.position().x;
+        let input = Input {
+            movement: held_keys.movement(),
+            ..Default::default()
+        };
+        for _ in 0..30 {
+            simulation.step(1.0 / 120.0, &input);
+        }
+        assert!(simulation.platform.position().x < start);
+    }
+
+    #[test]
+    fn time_is_not_simulated_while_unfocused() {
+        assert!(GameState::Playing.is_simulated());
+        let unfocused = GameState::Playing.after_focus_change(false, true);
+        assert!(!unfocused.is_simulated());
+    }
+
+    #[test]
+    fn only_fast_balls_leave_trail_particles() {
+        let mut allocator = InstanceAllocator::default();
+        let mut particles = new_particles(&mut allocator);
+        let mut rng = SmallRng::seed_from_u64(0);
+        let mut ball = Ball::new(
+            Vector3::new(0.0, 0.0, 0.0),
+            0.5,
+            [1.0; 4],
+            Vector2::new(0.0, 1.0),
+            1.0,
+        );
+
+        for _ in 0..5 {
+            spawn_speed_trails(&mut particles, std::slice::from_ref(&ball), 1.5, &mut rng);
+            particles.update(PHYSICS_DT);
+        }
+        assert!(particles.is_empty());
+
+        ball.set_speed(2.0);
+        for update in 1..=5 {
+            spawn_speed_trails(&mut particles, std::slice::from_ref(&ball), 1.5, &mut rng);
+            assert_eq!(particles.len(), update);
+            particles.update(PHYSICS_DT);
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use gilrs::{Axis, Button, EventType, GamepadId, Gilrs};
+
+use crate::game::Game;
+
+// Forwards input of a single gamepad to the game. The first connected
+// gamepad is used and the next one is picked up if it disconnects.
+pub struct GamepadInput {
+    gilrs: Option<Gilrs>,
+    active: Option<GamepadId>,
+    // Last axis value sent to the game, so the stick
+    // does not override the keyboard while it is idle
+    axis: f32,
+}
+
+impl GamepadInput {
+    // Stick values below this are treated as zero
+    const DEAD_ZONE: f32 = 0.15;
+
+    pub fn new() -> Self {
+        let gilrs = match Gilrs::new() {
+            Ok(gilrs) => Some(gilrs),
+            Err(e) => {
+                eprintln!("Gamepad support is disabled: {e}");
+                None
+            }
+        };
+        let active = gilrs
+            .as_ref()
+            .and_then(|gilrs| gilrs.gamepads().next().map(|(id, _)| id));
+        Self {
+            gilrs,
+            active,
+            axis: 0.0,
+        }
+    }
+
+    pub fn poll(&mut self, game: &mut Game) {
+        let Some(gilrs) = &mut self.gilrs else {
+            return;
+        };
+
+        while let Some(event) = gilrs.next_event() {
+            match event.event {
+                EventType::Connected => {
+                    if self.active.is_none() {
+                        self.active = Some(event.id);
+                    }
+                }
+                EventType::Disconnected => {
+                    if self.active == Some(event.id) {
+                        self.active = gilrs
+                            .gamepads()
+                            .map(|(id, _)| id)
+                            .find(|id| *id != event.id);
+                    }
+                }
+                EventType::ButtonPressed(Button::South, _) if self.active == Some(event.id) => {
+                    game.launch_ball();
+                }
+                _ => {}
+            }
+        }
+
+        let axis = self
+            .active
+            .and_then(|id| gilrs.connected_gamepad(id))
+            .map(|gamepad| {
+                if gamepad.is_pressed(Button::DPadLeft) {
+                    -1.0
+                } else if gamepad.is_pressed(Button::DPadRight) {
+                    1.0
+                } else {
+                    let axis = gamepad.value(Axis::LeftStickX);
+                    if axis.abs() < Self::DEAD_ZONE {
+                        0.0
+                    } else {
+                        axis
+                    }
+                }
+            })
+            .unwrap_or(0.0);
+
+        if axis != self.axis {
+            self.axis = axis;
+            game.set_analog_movement(axis);
+        }
+    }
+}
+
+impl Default for GamepadInput {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,54 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::mpsc::{channel, Receiver},
+};
+
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+// Watches a directory with shaders and reports files changed in it.
+// The watcher thread is stopped once the watcher is dropped.
+pub struct ShaderWatcher {
+    _watcher: RecommendedWatcher,
+    changes: Receiver<PathBuf>,
+}
+
+impl ShaderWatcher {
+    // Returns None if the directory can not be watched
+    pub fn new(dir: &str) -> Option<Self> {
+        let (sender, changes) = channel();
+        let watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+            let Ok(event) = event else {
+                return;
+            };
+            if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                for path in event.paths {
+                    // Receiver is gone only while the game shuts down
+                    _ = sender.send(path);
+                }
+            }
+        })
+        .and_then(|mut watcher| {
+            watcher.watch(Path::new(dir), RecursiveMode::NonRecursive)?;
+            Ok(watcher)
+        });
+        match watcher {
+            Ok(watcher) => Some(Self {
+                _watcher: watcher,
+                changes,
+            }),
+            Err(e) => {
+                eprintln!("Shader hot reload is disabled: {e}");
+                None
+            }
+        }
+    }
+
+    // Files changed since the last call. Editors often write
+    // a file in several steps, so duplicates are removed.
+    pub fn changed(&self) -> Vec<PathBuf> {
+        let mut paths = self.changes.try_iter().collect::<Vec<_>>();
+        paths.sort();
+        paths.dedup();
+        paths
+    }
+}
@@ -0,0 +1,346 @@
+use zero::prelude::*;
+
+use crate::{
+    physics::Rectangle,
+    rendering::{InstanceUniform, Instances, InstancesRenderCommand},
+};
+
+const GLYPH_WIDTH: u32 = 3;
+const GLYPH_HEIGHT: u32 = 5;
+// Size of a single glyph pixel in screen pixels
+const PIXEL_SIZE: f32 = 4.0;
+// Distance from the window edges in screen pixels
+const MARGIN: f32 = 16.0;
+// Maximum number of characters on the screen at once
+const MAX_CHARS: u32 = 160;
+// Empty glyph pixels between lines
+const LINE_GAP: u32 = 3;
+// Behind the text, but in front of the scene
+const DIM_DEPTH: f32 = 0.5;
+
+// Dimming of the screen behind the HUD and the fading of
+// the center lines, used to draw the pause screen
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HudOverlay {
+    // Alpha of the black full screen quad, 0 dims nothing
+    pub dim: f32,
+    // Alpha of the center lines is multiplied by it
+    pub center_alpha: f32,
+}
+
+impl HudOverlay {
+    pub const NONE: Self = Self {
+        dim: 0.0,
+        center_alpha: 1.0,
+    };
+}
+
+// Draws text in screen space using a 3x5 bitmap font where
+// each lit glyph pixel is a separate quad instance
+pub struct Hud {
+    camera: Camera,
+    camera_handle: CameraHandle,
+    camera_bind_group: CameraBindGroup,
+    instances: Instances,
+
+    width: f32,
+    height: f32,
+    color: [f32; 4],
+}
+
+impl Hud {
+    pub fn new(
+        renderer: &Renderer,
+        storage: &mut RenderStorage,
+        mesh_id: ResourceId,
+        width: u32,
+        height: u32,
+        color: [f32; 4],
+    ) -> Self {
+        let camera = Self::screen_camera(width, height);
+        let camera_handle = CameraHandle::new(storage, camera.build(renderer));
+        let camera_bind_group = CameraBindGroup::new(renderer, storage, &camera_handle);
+        let instances = Instances::new(
+            renderer,
+            storage,
+            mesh_id,
+            // One more for the dimming quad
+            MAX_CHARS * GLYPH_WIDTH * GLYPH_HEIGHT + 1,
+        );
+
+        Self {
+            camera,
+            camera_handle,
+            camera_bind_group,
+            instances,
+            width: width as f32,
+            height: height as f32,
+            color,
+        }
+    }
+
+    pub fn set_color(&mut self, color: [f32; 4]) {
+        self.color = color;
+    }
+
+    // Camera with coordinates in pixels and the origin
+    // in the bottom left corner of the window
+    fn screen_camera(width: u32, height: u32) -> Camera {
+        Camera::Orthogonal(OrthogonalCamera {
+            position: Vector3::new(0.0, 0.0, 5.0),
+            direction: -Vector3::unit_z(),
+            left: 0.0,
+            right: width as f32,
+            bottom: 0.0,
+            top: height as f32,
+            near: 0.1,
+            far: 100.0,
+        })
+    }
+
+    pub fn resize(
+        &mut self,
+        renderer: &Renderer,
+        storage: &RenderStorage,
+        width: u32,
+        height: u32,
+    ) {
+        self.camera = Self::screen_camera(width, height);
+        self.camera_handle.update(renderer, storage, &self.camera);
+        self.width = width as f32;
+        self.height = height as f32;
+    }
+
+    // Converts window coordinates with the origin in the top left corner
+    // into the HUD screen coordinates with the origin in the bottom left
+    #[inline]
+    pub fn window_to_screen(&self, position: Vector2<f32>) -> Vector2<f32> {
+        Vector2::new(position.x, self.height - position.y)
+    }
+
+    // Draws the score, the combo and the lives at the top of the
+    // screen with the `level` number under the score, the `center` lines
+    // in the middle of it and the `corner` lines in the bottom left corner,
+    // all over the `overlay`. Combos below 2 are not shown.
+    #[allow(clippy::too_many_arguments)]
+    pub fn render_sync(
+        &self,
+        renderer: &Renderer,
+        storage: &RenderStorage,
+        score: u32,
+        lives: u32,
+        combo: u32,
+        level: Option<usize>,
+        center: &[String],
+        corner: &[String],
+        overlay: HudOverlay,
+    ) {
+        let score = format!("SCORE {score}");
+        let lives = format!("LIVES {lives}");
+        let combo = if 1 < combo {
+            format!("COMBO X{combo}")
+        } else {
+            String::new()
+        };
+        let level = level
+            .map(|level| format!("LEVEL {level}"))
+            .unwrap_or_default();
+
+        let top = self.height - MARGIN;
+        let level_top = top - (GLYPH_HEIGHT + LINE_GAP) as f32 * PIXEL_SIZE;
+        let lives_left = self.width - MARGIN - text_width(&lives);
+        let combo_left = (self.width - text_width(&combo)) / 2.0;
+
+        self.upload(
+            renderer,
+            storage,
+            self.dim_quad(overlay.dim)
+                .into_iter()
+                .chain(self.text(&score, Vector2::new(MARGIN, top)))
+                .chain(self.text(&level, Vector2::new(MARGIN, level_top)))
+                .chain(self.text(&combo, Vector2::new(combo_left, top)))
+                .chain(self.text(&lives, Vector2::new(lives_left, top)))
+                .chain(self.corner_lines(corner))
+                .chain(self.centered_lines(center).map(move |mut instance| {
+                    instance.color[3] *= overlay.center_alpha;
+                    instance
+                })),
+        );
+    }
+
+    // Black quad over the whole screen, none if the `alpha` is not positive
+    fn dim_quad(&self, alpha: f32) -> Option<InstanceUniform> {
+        (0.0 < alpha).then(|| InstanceUniform {
+            transform: Matrix4::from(&Transform {
+                translation: Vector3::new(self.width / 2.0, self.height / 2.0, DIM_DEPTH),
+                scale: Vector3::new(self.width, self.height, 1.0),
+                ..Default::default()
+            })
+            .into(),
+            color: [0.0, 0.0, 0.0, alpha.min(1.0)],
+            disabled: 0,
+            uv_rect: InstanceUniform::NO_SPRITE,
+        })
+    }
+
+    // Draws the `lines` one under another in the middle of the screen
+    pub fn render_menu(&self, renderer: &Renderer, storage: &RenderStorage, lines: &[String]) {
+        self.upload(renderer, storage, self.centered_lines(lines));
+    }
+
+    fn centered_lines<'a>(
+        &'a self,
+        lines: &'a [String],
+    ) -> impl Iterator<Item = InstanceUniform> + 'a {
+        (0..lines.len()).flat_map(move |i| {
+            let rect = self.centered_line_rect(lines, i);
+            self.text(&lines[i], Vector2::new(rect.left(), rect.top()))
+        })
+    }
+
+    // Draws the `lines` one under another with
+    // the last one at the bottom left corner
+    fn corner_lines<'a>(
+        &'a self,
+        lines: &'a [String],
+    ) -> impl Iterator<Item = InstanceUniform> + 'a {
+        let line_height = GLYPH_HEIGHT as f32 * PIXEL_SIZE;
+        let line_step = (GLYPH_HEIGHT + LINE_GAP) as f32 * PIXEL_SIZE;
+        lines.iter().rev().enumerate().flat_map(move |(i, line)| {
+            let top = MARGIN + line_height + line_step * i as f32;
+            self.text(line, Vector2::new(MARGIN, top))
+        })
+    }
+
+    // Index of the menu line under the `position` in screen pixels
+    // with the origin in the bottom left corner of the window
+    pub fn menu_line_at(&self, lines: &[String], position: Vector2<f32>) -> Option<usize> {
+        (0..lines.len()).find(|&i| self.centered_line_rect(lines, i).contains(position))
+    }
+
+    fn centered_line_rect(&self, lines: &[String], index: usize) -> Rectangle {
+        let line_height = GLYPH_HEIGHT as f32 * PIXEL_SIZE;
+        let line_step = (GLYPH_HEIGHT + LINE_GAP) as f32 * PIXEL_SIZE;
+        let total_height = line_step * lines.len() as f32 - line_step + line_height;
+        let top = (self.height + total_height) / 2.0 - line_step * index as f32;
+        let width = text_width(&lines[index]);
+        Rectangle {
+            x: (self.width - width) / 2.0,
+            y: top - line_height,
+            width,
+            height: line_height,
+        }
+    }
+
+    // Uploads the `instances` and hides the rest of the buffer
+    fn upload(
+        &self,
+        renderer: &Renderer,
+        storage: &RenderStorage,
+        instances: impl Iterator<Item = InstanceUniform>,
+    ) {
+        let mut data = instances
+            .take(self.instances.instance_num as usize)
+            .collect::<Vec<_>>();
+        data.resize(
+            self.instances.instance_num as usize,
+            InstanceUniform {
+                disabled: 1,
+                uv_rect: InstanceUniform::NO_SPRITE,
+                ..Default::default()
+            },
+        );
+
+        self.instances
+            .instance_buffer_handle
+            .update(renderer, storage, 0, &data);
+    }
+
+    // Instances for all lit pixels of the `text` starting at the `top_left` corner
+    fn text<'a>(
+        &'a self,
+        text: &'a str,
+        top_left: Vector2<f32>,
+    ) -> impl Iterator<Item = InstanceUniform> + 'a {
+        text.chars().enumerate().flat_map(move |(i, c)| {
+            let glyph = glyph(c);
+            let glyph_left = top_left.x + (i as u32 * (GLYPH_WIDTH + 1)) as f32 * PIXEL_SIZE;
+            (0..GLYPH_HEIGHT).flat_map(move |row| {
+                (0..GLYPH_WIDTH)
+                    .filter(move |col| glyph[row as usize] & (1 << (GLYPH_WIDTH - 1 - col)) != 0)
+                    .map(move |col| InstanceUniform {
+                        transform: Matrix4::from(&Transform {
+                            translation: Vector3::new(
+                                glyph_left + (col as f32 + 0.5) * PIXEL_SIZE,
+                                top_left.y - (row as f32 + 0.5) * PIXEL_SIZE,
+                                // In front of everything in the scene
+                                1.0,
+                            ),
+                            scale: Vector3::new(PIXEL_SIZE, PIXEL_SIZE, 1.0),
+                            ..Default::default()
+                        })
+                        .into(),
+                        color: self.color,
+                        disabled: 0,
+                        uv_rect: InstanceUniform::NO_SPRITE,
+                    })
+            })
+        })
+    }
+
+    pub fn render_command(&self, pipeline_id: ResourceId) -> InstancesRenderCommand {
+        self.instances
+            .render_command(pipeline_id, self.camera_bind_group.0)
+    }
+}
+
+#[inline]
+fn text_width(text: &str) -> f32 {
+    (text.chars().count() as u32 * (GLYPH_WIDTH + 1)).saturating_sub(1) as f32 * PIXEL_SIZE
+}
+
+// Rows of the glyph from top to bottom with
+// the most significant bit being the left pixel
+fn glyph(c: char) -> [u8; GLYPH_HEIGHT as usize] {
+    match c.to_ascii_uppercase() {
+        '0' | 'O' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' | 'S' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b001, 0b001, 0b001],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'B' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'C' => [0b111, 0b100, 0b100, 0b100, 0b111],
+        'D' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'E' => [0b111, 0b100, 0b110, 0b100, 0b111],
+        'F' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'G' => [0b111, 0b100, 0b101, 0b101, 0b111],
+        'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        'I' => [0b111, 0b010, 0b010, 0b010, 0b111],
+        'J' => [0b001, 0b001, 0b001, 0b101, 0b111],
+        'K' => [0b101, 0b101, 0b110, 0b101, 0b101],
+        'L' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'N' => [0b110, 0b101, 0b101, 0b101, 0b101],
+        'P' => [0b111, 0b101, 0b111, 0b100, 0b100],
+        'Q' => [0b111, 0b101, 0b101, 0b111, 0b001],
+        'R' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        'T' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        'U' => [0b101, 0b101, 0b101, 0b101, 0b111],
+        'V' => [0b101, 0b101, 0b101, 0b101, 0b010],
+        'W' => [0b101, 0b101, 0b111, 0b111, 0b101],
+        'X' => [0b101, 0b101, 0b010, 0b101, 0b101],
+        'Y' => [0b101, 0b101, 0b010, 0b010, 0b010],
+        'Z' => [0b111, 0b001, 0b010, 0b100, 0b111],
+        ':' => [0b000, 0b010, 0b000, 0b010, 0b000],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        '/' => [0b001, 0b001, 0b010, 0b100, 0b100],
+        _ => [0b000; GLYPH_HEIGHT as usize],
+    }
+}
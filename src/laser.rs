@@ -0,0 +1,93 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use zero::prelude::*;
+
+use crate::{physics::Rectangle, rendering::InstanceUniform};
+
+// Color of the stripe warning about the targeted column
+const WARNING_COLOR: [f32; 4] = [1.0, 0.1, 0.1, 0.35];
+
+// Timers of the laser, the interval comes from the config
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ColumnLaserState {
+    // Seconds until the next column is targeted
+    pub next: f32,
+    // Targeted column and seconds left until it is destroyed
+    pub target: Option<(i32, f32)>,
+}
+
+// Hazard targeting a random column of crates every `interval` seconds.
+// The column is warned about for `TELEGRAPH` seconds and then destroyed.
+pub struct ColumnLaser {
+    interval: f32,
+    state: ColumnLaserState,
+}
+
+impl ColumnLaser {
+    pub const TELEGRAPH: f32 = 1.5;
+
+    pub fn new(interval: f32) -> Self {
+        Self {
+            interval,
+            state: ColumnLaserState {
+                next: interval,
+                target: None,
+            },
+        }
+    }
+
+    #[inline]
+    pub fn state(&self) -> ColumnLaserState {
+        self.state
+    }
+
+    pub fn set_state(&mut self, state: &ColumnLaserState) {
+        self.state = *state;
+    }
+
+    // Column being warned about
+    #[inline]
+    pub fn target(&self) -> Option<i32> {
+        self.state.target.map(|(column, _)| column)
+    }
+
+    // Targets one of the `columns` once the interval runs out and returns
+    // it once its warning is over. The interval starts over after each
+    // target is picked, nothing is targeted if there are no columns.
+    pub fn update(&mut self, dt: f32, columns: &[i32], rng: &mut impl Rng) -> Option<i32> {
+        if let Some((column, remaining)) = self.state.target {
+            let remaining = remaining - dt;
+            if 0.0 < remaining {
+                self.state.target = Some((column, remaining));
+                return None;
+            }
+            self.state.target = None;
+            return Some(column);
+        }
+
+        self.state.next -= dt;
+        if self.state.next <= 0.0 {
+            self.state.next = self.interval;
+            if !columns.is_empty() {
+                let column = columns[rng.gen_range(0..columns.len())];
+                self.state.target = Some((column, Self::TELEGRAPH));
+            }
+        }
+        None
+    }
+}
+
+// Stripe covering the targeted column
+pub fn warning_instance(column: Rectangle) -> InstanceUniform {
+    InstanceUniform {
+        transform: Matrix4::from(&Transform {
+            translation: column.pos().extend(0.0),
+            scale: Vector3::new(column.width, column.height, 1.0),
+            ..Default::default()
+        })
+        .into(),
+        color: WARNING_COLOR,
+        disabled: 0,
+        uv_rect: InstanceUniform::NO_SPRITE,
+    }
+}
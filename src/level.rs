@@ -0,0 +1,336 @@
+use serde::{Deserialize, Serialize};
+use zero::prelude::Vector2;
+
+use crate::{crates::CrateKind, physics::GravityWell};
+
+#[derive(Debug)]
+pub enum LevelError {
+    Io(std::io::Error),
+    Parse(ron::error::SpannedError),
+    // Directory does not contain any levels
+    Empty(String),
+}
+
+impl From<std::io::Error> for LevelError {
+    fn from(value: std::io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+impl From<ron::error::SpannedError> for LevelError {
+    fn from(value: ron::error::SpannedError) -> Self {
+        Self::Parse(value)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LevelCrate {
+    pub position: [f32; 2],
+    // Width and height of this crate, the level
+    // crate size is used if not set
+    #[serde(default)]
+    pub size: Option<[f32; 2]>,
+    pub color: [f32; 4],
+    pub health: u32,
+    pub points: u32,
+    #[serde(default)]
+    pub kind: CrateKind,
+    // Column and row of the crate counting rows from the bottom.
+    // Only crates with cells can be destroyed by explosions.
+    #[serde(default)]
+    pub cell: Option<[i32; 2]>,
+    // Counterclockwise rotation of the crate in degrees
+    #[serde(default)]
+    pub rotation: f32,
+    // Hit on which the crate drops a power-up, counting from 1.
+    // Crates without it drop them by chance once destroyed.
+    #[serde(default)]
+    pub power_up_hit: Option<u32>,
+}
+
+// Ball speed is multiplied by `factor` for every
+// destroyed crate until it reaches `max_speed`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SpeedCurve {
+    pub factor: f32,
+    pub max_speed: f32,
+}
+
+// Well bending the path of balls passing within `radius` of the `position`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LevelGravityWell {
+    pub position: [f32; 2],
+    pub radius: f32,
+    pub strength: f32,
+}
+
+impl From<LevelGravityWell> for GravityWell {
+    fn from(value: LevelGravityWell) -> Self {
+        Self {
+            pos: Vector2::from(value.position),
+            radius: value.radius,
+            strength: value.strength,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Level {
+    // Size of the playfield inside of the border
+    pub width: f32,
+    pub height: f32,
+    pub ball_speed: f32,
+    #[serde(default)]
+    pub speed_curve: Option<SpeedCurve>,
+    pub paddle_width: f32,
+    pub crate_width: f32,
+    pub crate_height: f32,
+    pub crates: Vec<LevelCrate>,
+    #[serde(default)]
+    pub gravity_wells: Vec<LevelGravityWell>,
+}
+
+impl Level {
+    pub fn load(path: &str) -> Result<Self, LevelError> {
+        let level = std::fs::read_to_string(path)?;
+        Ok(ron::from_str(&level)?)
+    }
+}
+
+// Levels played one after another. Clearing the
+// current one moves on to the next in the set.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LevelSet {
+    paths: Vec<String>,
+    levels: Vec<Level>,
+    current: usize,
+}
+
+impl LevelSet {
+    // The `path` is either a single level or a directory
+    // of levels played in the order of their file names
+    pub fn load(path: &str) -> Result<Self, LevelError> {
+        let paths = if std::path::Path::new(path).is_dir() {
+            let mut paths = std::fs::read_dir(path)?
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<Result<Vec<_>, _>>()?
+                .into_iter()
+                .filter(|path| path.extension().is_some_and(|ext| ext == "ron"))
+                .map(|path| path.to_string_lossy().into_owned())
+                .collect::<Vec<_>>();
+            paths.sort();
+            paths
+        } else {
+            vec![path.to_string()]
+        };
+        if paths.is_empty() {
+            return Err(LevelError::Empty(path.to_string()));
+        }
+        let levels = paths
+            .iter()
+            .map(|path| Level::load(path))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self {
+            paths,
+            levels,
+            current: 0,
+        })
+    }
+
+    #[inline]
+    pub fn current(&self) -> &Level {
+        &self.levels[self.current]
+    }
+
+    #[inline]
+    pub fn current_path(&self) -> &str {
+        &self.paths[self.current]
+    }
+
+    // Index of the current level counting from 0
+    #[inline]
+    pub fn index(&self) -> usize {
+        self.current
+    }
+
+    // Moves to the next level. Returns false
+    // if the current one is the last level.
+    pub fn advance(&mut self) -> bool {
+        if self.current + 1 < self.levels.len() {
+            self.current += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    // Makes the level loaded from the `path` the current one.
+    // Returns false if the set has no such level.
+    pub fn select(&mut self, path: &str) -> bool {
+        match self.paths.iter().position(|p| p == path) {
+            Some(index) => {
+                self.current = index;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn restart(&mut self) {
+        self.current = 0;
+    }
+}
+
+// Levels for the tests of other modules
+#[cfg(test)]
+impl Level {
+    // Level of the default playfield size with the `crates`
+    pub fn test(crate_size: [f32; 2], crates: Vec<LevelCrate>) -> Self {
+        Self {
+            width: 15.0,
+            height: 20.0,
+            ball_speed: 1.0,
+            speed_curve: None,
+            paddle_width: 2.0,
+            crate_width: crate_size[0],
+            crate_height: crate_size[1],
+            crates,
+            gravity_wells: vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+impl LevelCrate {
+    // White normal crate of the level crate size with 1 health
+    pub fn test(position: [f32; 2], points: u32) -> Self {
+        Self {
+            position,
+            size: None,
+            color: [1.0; 4],
+            health: 1,
+            points,
+            kind: CrateKind::Normal,
+            cell: None,
+            rotation: 0.0,
+            power_up_hit: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LEVEL: &str = "Level(
+        width: 15.0,
+        height: 20.0,
+        ball_speed: 1.0,
+        paddle_width: 2.0,
+        crate_width: 1.5,
+        crate_height: 1.0,
+        crates: [
+            (position: (0.0, 4.0), color: (1.0, 0.0, 0.0, 1.0), health: 1, points: 10),
+        ],
+    )";
+
+    #[test]
+    fn gravity_wells_are_parsed() {
+        let level = LEVEL.replace(
+            "crates: [",
+            "gravity_wells: [(position: (1.0, -2.0), radius: 3.0, strength: 4.0)],\n        crates: [",
+        );
+        let level: Level = ron::from_str(&level).unwrap();
+
+        assert_eq!(
+            level.gravity_wells,
+            vec![LevelGravityWell {
+                position: [1.0, -2.0],
+                radius: 3.0,
+                strength: 4.0,
+            }]
+        );
+        assert_eq!(
+            GravityWell::from(level.gravity_wells[0]),
+            GravityWell {
+                pos: Vector2::new(1.0, -2.0),
+                radius: 3.0,
+                strength: 4.0,
+            }
+        );
+    }
+
+    #[test]
+    fn gravity_wells_are_optional() {
+        let level: Level = ron::from_str(LEVEL).unwrap();
+        assert!(level.gravity_wells.is_empty());
+    }
+
+    #[test]
+    fn level_round_trips_through_ron() {
+        let level = Level {
+            width: 12.0,
+            height: 16.0,
+            ball_speed: 1.5,
+            speed_curve: Some(SpeedCurve {
+                factor: 1.02,
+                max_speed: 2.0,
+            }),
+            paddle_width: 3.0,
+            crate_width: 1.5,
+            crate_height: 1.0,
+            crates: vec![
+                LevelCrate {
+                    position: [-1.0, 4.0],
+                    size: Some([2.0, 0.5]),
+                    color: [0.2, 0.4, 0.6, 1.0],
+                    health: 2,
+                    points: 20,
+                    kind: CrateKind::Explosive,
+                    cell: Some([0, 1]),
+                    rotation: 45.0,
+                    power_up_hit: Some(2),
+                },
+                LevelCrate {
+                    position: [1.0, 4.0],
+                    size: None,
+                    color: [0.5, 0.5, 0.5, 1.0],
+                    health: 1,
+                    points: 0,
+                    kind: CrateKind::Steel,
+                    cell: None,
+                    rotation: 0.0,
+                    power_up_hit: None,
+                },
+            ],
+            gravity_wells: vec![LevelGravityWell {
+                position: [0.0, 1.0],
+                radius: 2.0,
+                strength: 3.0,
+            }],
+        };
+
+        let serialized = ron::to_string(&level).unwrap();
+        let deserialized: Level = ron::from_str(&serialized).unwrap();
+
+        assert_eq!(deserialized, level);
+    }
+
+    #[test]
+    fn level_set_advances_through_the_levels_in_order() {
+        let mut levels = LevelSet::load(concat!(env!("CARGO_MANIFEST_DIR"), "/levels")).unwrap();
+        assert_eq!(levels.index(), 0);
+        assert!(levels.current_path().ends_with("level1.ron"));
+
+        assert!(levels.advance());
+        assert_eq!(levels.index(), 1);
+        assert!(levels.current_path().ends_with("level2.ron"));
+
+        // Last level is cleared, the set stays on it
+        assert!(!levels.advance());
+        assert_eq!(levels.index(), 1);
+
+        levels.restart();
+        assert_eq!(levels.index(), 0);
+    }
+}
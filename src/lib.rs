@@ -0,0 +1,49 @@
+// Breakout game made using the zero renderer. The `prelude` has the
+// types needed to embed the game or its simulation in another app.
+
+pub mod aim_guide;
+pub mod audio;
+pub mod background;
+pub mod ball;
+pub mod border;
+pub mod bot;
+pub mod charge;
+pub mod config;
+pub mod crates;
+#[cfg(feature = "debug")]
+pub mod debug;
+pub mod game;
+#[cfg(feature = "gamepad")]
+pub mod gamepad;
+#[cfg(feature = "hot-reload")]
+pub mod hot_reload;
+pub mod hud;
+pub mod laser;
+pub mod level;
+pub mod menu;
+pub mod options;
+pub mod particles;
+pub mod physics;
+pub mod platform;
+pub mod powerup;
+pub mod rendering;
+pub mod scores;
+pub mod settings;
+pub mod simulation;
+pub mod snapshot;
+pub mod texture;
+pub mod theme;
+
+pub mod prelude {
+    pub use crate::{
+        ball::Ball,
+        border::Border,
+        config::GameConfig,
+        crates::CratePack,
+        game::{Game, GameState},
+        physics::{Collider, Collision, Rectangle},
+        platform::Platform,
+        scores::{FinishedGame, ScoreLog, ScoreSink},
+        simulation::GameEvent,
+    };
+}
@@ -1,52 +1,122 @@
 use zero::prelude::winit::{
-    event::{Event, KeyEvent, WindowEvent},
+    event::{ElementState, Event, KeyEvent, WindowEvent},
     event_loop::{ControlFlow, EventLoop},
-    window::WindowBuilder,
+    keyboard::{Key, NamedKey},
+    window::{Fullscreen, WindowBuilder},
 };
 
-mod ball;
-mod border;
-mod crates;
-mod game;
-mod physics;
-mod platform;
-mod rendering;
-
-use game::*;
+#[cfg(feature = "gamepad")]
+use breakout_zero::gamepad;
+use breakout_zero::prelude::*;
 
 struct FpsLogger {
     last_log: std::time::Instant,
+    // Scene buffer writes at the last log
+    last_writes: u64,
 }
 
 impl FpsLogger {
     fn new() -> Self {
         Self {
             last_log: std::time::Instant::now(),
+            last_writes: 0,
         }
     }
 
-    fn log(&mut self, now: std::time::Instant, dt: std::time::Duration) {
+    fn log(&mut self, now: std::time::Instant, dt: std::time::Duration, writes: u64) {
         if 1.0 <= (now - self.last_log).as_secs_f32() {
             println!(
-                "Frame time: {:.2}ms(FPS: {:.2})",
+                "Frame time: {:.2}ms(FPS: {:.2}), scene buffer writes: {}",
                 dt.as_secs_f64() * 1000.0,
-                1.0 / dt.as_secs_f64()
+                1.0 / dt.as_secs_f64(),
+                writes - self.last_writes
             );
             self.last_log = now;
+            self.last_writes = writes;
         }
     }
 }
 
+// Keeps frames at least `frame_time` apart by waiting
+// for the next frame instead of polling when it is enabled
+struct FrameLimiter {
+    frame_time: std::time::Duration,
+    enabled: bool,
+    last_frame: std::time::Instant,
+}
+
+impl FrameLimiter {
+    // Frame rate used when toggled on without a configured limit
+    const DEFAULT_FRAME_LIMIT: u32 = 60;
+
+    fn new(frame_limit: Option<u32>) -> Self {
+        let fps = frame_limit.unwrap_or(Self::DEFAULT_FRAME_LIMIT).max(1);
+        Self {
+            frame_time: std::time::Duration::from_secs_f64(1.0 / fps as f64),
+            enabled: frame_limit.is_some(),
+            last_frame: std::time::Instant::now(),
+        }
+    }
+
+    fn toggle(&mut self) {
+        self.enabled = !self.enabled;
+        if self.enabled {
+            println!(
+                "Frame limit: {:.0} FPS",
+                1.0 / self.frame_time.as_secs_f64()
+            );
+        } else {
+            println!("Frame limit: off");
+        }
+    }
+
+    fn frame_started(&mut self, now: std::time::Instant) {
+        self.last_frame = now;
+    }
+
+    // Time to wait for before drawing the next frame
+    fn next_frame(&self) -> Option<std::time::Instant> {
+        self.enabled.then(|| self.last_frame + self.frame_time)
+    }
+}
+
 fn main() {
     env_logger::init();
 
-    let event_loop = EventLoop::new().unwrap();
-    let window = WindowBuilder::new().build(&event_loop).unwrap();
+    let config = GameConfig {
+        level_path: std::env::args().nth(1),
+        seed: std::env::args().nth(2).and_then(|seed| match seed.parse() {
+            Ok(seed) => Some(seed),
+            Err(e) => {
+                eprintln!("Invalid seed {seed}, using a random one: {e}");
+                None
+            }
+        }),
+        ..Default::default()
+    };
 
-    let mut game = Game::new(&window);
+    let event_loop = EventLoop::new().unwrap();
+    let window = WindowBuilder::new()
+        .with_fullscreen(config.fullscreen.then_some(Fullscreen::Borderless(None)))
+        .build(&event_loop)
+        .unwrap();
+    let mut frame_limiter = FrameLimiter::new(config.frame_limit);
+    let mut fps_logger = config.log_frame_stats.then(FpsLogger::new);
+    let mut game = match Game::try_with_config(&window, config) {
+        Ok(game) => game,
+        Err(e) => {
+            eprintln!("Could not start the game: {e:?}");
+            std::process::exit(1);
+        }
+    };
+    println!("Seed: {}", game.seed());
+    if let Some(best) = game.high_scores().first() {
+        println!("High score: {}", best.score);
+    }
 
     let mut last_render_time = std::time::Instant::now();
-    let mut fps_logger = FpsLogger::new();
+    #[cfg(feature = "gamepad")]
+    let mut gamepad = gamepad::GamepadInput::new();
     _ = event_loop.run(|event, target| {
         target.set_control_flow(ControlFlow::Poll);
         match event {
@@ -54,37 +124,80 @@ fn main() {
                 ref event,
                 window_id,
             } if window_id == window.id() => match event {
-                WindowEvent::CloseRequested => target.exit(),
+                WindowEvent::CloseRequested => {
+                    game.on_exit();
+                    target.exit();
+                }
                 WindowEvent::KeyboardInput {
                     event:
                         KeyEvent {
                             logical_key: key,
                             state,
+                            // Held keys are tracked by their press and release
+                            repeat: false,
                             ..
                         },
                     ..
-                } => game.handle_input(key, state),
+                } => {
+                    match key {
+                        Key::Character(c)
+                            if *state == ElementState::Pressed && c.eq_ignore_ascii_case("l") =>
+                        {
+                            frame_limiter.toggle()
+                        }
+                        // Switching sends a resize event with the new size
+                        Key::Named(NamedKey::F11) if *state == ElementState::Pressed => {
+                            let fullscreen = match window.fullscreen() {
+                                Some(_) => None,
+                                None => Some(Fullscreen::Borderless(window.current_monitor())),
+                            };
+                            window.set_fullscreen(fullscreen);
+                        }
+                        _ => {}
+                    }
+                    game.handle_input(key, state)
+                }
+                WindowEvent::CursorMoved { position, .. } => game.cursor_moved(*position),
+                WindowEvent::MouseInput { button, state, .. } => game.mouse_input(*button, *state),
                 WindowEvent::Resized(physical_size) => {
                     game.resize(*physical_size);
                 }
+                WindowEvent::Focused(focused) => {
+                    game.focus_changed(*focused);
+                    // Do not count the time spent unfocused as a frame
+                    last_render_time = std::time::Instant::now();
+                }
                 WindowEvent::RedrawRequested => {
                     let now = std::time::Instant::now();
                     let dt = now - last_render_time;
                     last_render_time = now;
+                    frame_limiter.frame_started(now);
 
-                    fps_logger.log(now, dt);
+                    if let Some(fps_logger) = fps_logger.as_mut() {
+                        fps_logger.log(now, dt, game.scene_buffer_writes());
+                    }
 
                     let dt = dt.as_secs_f32();
 
+                    #[cfg(feature = "gamepad")]
+                    gamepad.poll(&mut game);
                     game.update(dt);
                     game.render_sync();
-                    if !game.render() {
+                    if game.should_quit() || !game.render() {
+                        game.on_exit();
                         target.exit();
                     }
                 }
                 _ => {}
             },
-            Event::AboutToWait => window.request_redraw(),
+            // Covers exits not started by the game itself
+            Event::LoopExiting => game.on_exit(),
+            Event::AboutToWait => match frame_limiter.next_frame() {
+                Some(next_frame) if std::time::Instant::now() < next_frame => {
+                    target.set_control_flow(ControlFlow::WaitUntil(next_frame));
+                }
+                _ => window.request_redraw(),
+            },
             _ => {}
         }
     });
@@ -0,0 +1,74 @@
+// Entries of the main menu from top to bottom
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuItem {
+    Start,
+    // Continues the saved game, or starts a new one if there is none
+    Continue,
+    // Toggles the rainbow ball
+    Rainbow,
+    // Switches to the next built-in theme
+    Theme,
+    Options,
+    Quit,
+}
+
+impl MenuItem {
+    pub const ALL: [Self; 6] = [
+        Self::Start,
+        Self::Continue,
+        Self::Rainbow,
+        Self::Theme,
+        Self::Options,
+        Self::Quit,
+    ];
+}
+
+#[derive(Debug, Default)]
+pub struct Menu {
+    selected: usize,
+}
+
+impl Menu {
+    #[inline]
+    pub fn selected(&self) -> MenuItem {
+        MenuItem::ALL[self.selected]
+    }
+
+    // Index is clamped to the last item
+    pub fn select(&mut self, index: usize) {
+        self.selected = index.min(MenuItem::ALL.len() - 1);
+    }
+
+    // Moves the selection down, wrapping around to the top
+    pub fn select_next(&mut self) {
+        self.selected = (self.selected + 1) % MenuItem::ALL.len();
+    }
+
+    // Moves the selection up, wrapping around to the bottom
+    pub fn select_prev(&mut self) {
+        self.selected = (self.selected + MenuItem::ALL.len() - 1) % MenuItem::ALL.len();
+    }
+
+    // Text of each item with the selected one marked
+    pub fn lines(&self, rainbow: bool, theme: &str) -> Vec<String> {
+        MenuItem::ALL
+            .iter()
+            .enumerate()
+            .map(|(i, item)| {
+                let label = match item {
+                    MenuItem::Start => "START".to_string(),
+                    MenuItem::Continue => "CONTINUE".to_string(),
+                    MenuItem::Rainbow => format!("RAINBOW {}", if rainbow { "ON" } else { "OFF" }),
+                    MenuItem::Theme => format!("THEME {theme}"),
+                    MenuItem::Options => "OPTIONS".to_string(),
+                    MenuItem::Quit => "QUIT".to_string(),
+                };
+                if i == self.selected {
+                    format!("- {label} -")
+                } else {
+                    label
+                }
+            })
+            .collect()
+    }
+}
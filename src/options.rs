@@ -0,0 +1,94 @@
+use crate::settings::{Action, Settings};
+
+// Entries of the options screen from top to bottom
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OptionsItem {
+    Volume,
+    Difficulty,
+    Binding(Action),
+    Back,
+}
+
+impl OptionsItem {
+    pub const ALL: [Self; 6] = [
+        Self::Volume,
+        Self::Difficulty,
+        Self::Binding(Action::Left),
+        Self::Binding(Action::Right),
+        Self::Binding(Action::SuperShot),
+        Self::Back,
+    ];
+}
+
+#[derive(Debug, Default)]
+pub struct OptionsMenu {
+    selected: usize,
+    // Action waiting for the next key press to be bound to it
+    rebinding: Option<Action>,
+}
+
+impl OptionsMenu {
+    #[inline]
+    pub fn selected(&self) -> OptionsItem {
+        OptionsItem::ALL[self.selected]
+    }
+
+    // Index is clamped to the last item
+    pub fn select(&mut self, index: usize) {
+        self.selected = index.min(OptionsItem::ALL.len() - 1);
+    }
+
+    // Moves the selection down, wrapping around to the top
+    pub fn select_next(&mut self) {
+        self.selected = (self.selected + 1) % OptionsItem::ALL.len();
+    }
+
+    // Moves the selection up, wrapping around to the bottom
+    pub fn select_prev(&mut self) {
+        self.selected = (self.selected + OptionsItem::ALL.len() - 1) % OptionsItem::ALL.len();
+    }
+
+    #[inline]
+    pub fn rebinding(&self) -> Option<Action> {
+        self.rebinding
+    }
+
+    pub fn start_rebinding(&mut self, action: Action) {
+        self.rebinding = Some(action);
+    }
+
+    pub fn stop_rebinding(&mut self) {
+        self.rebinding = None;
+    }
+
+    // Text of each item with the selected one marked.
+    // The key of the action being rebound is replaced by a prompt.
+    pub fn lines(&self, settings: &Settings) -> Vec<String> {
+        OptionsItem::ALL
+            .iter()
+            .enumerate()
+            .map(|(i, item)| {
+                let label = match item {
+                    OptionsItem::Volume => format!("VOLUME {:.1}", settings.volume),
+                    OptionsItem::Difficulty => {
+                        format!("DIFFICULTY {}", settings.difficulty.name())
+                    }
+                    OptionsItem::Binding(action) if self.rebinding == Some(*action) => {
+                        format!("{} PRESS A KEY", action.name())
+                    }
+                    OptionsItem::Binding(action) => format!(
+                        "{} {}",
+                        action.name(),
+                        settings.bindings.key(*action).to_uppercase()
+                    ),
+                    OptionsItem::Back => "BACK".to_string(),
+                };
+                if i == self.selected {
+                    format!("- {label} -")
+                } else {
+                    label
+                }
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,148 @@
+use rand::Rng;
+use zero::prelude::*;
+
+use crate::rendering::{InstanceAllocator, InstanceRange, InstanceUniform, SceneBuffer};
+
+struct Particle {
+    position: Vector2<f32>,
+    velocity: Vector2<f32>,
+    color: [f32; 4],
+    age: f32,
+    lifetime: f32,
+}
+
+pub struct ParticleSystem {
+    particles: Vec<Particle>,
+    // Holds `max_particles` instances, unused ones are disabled
+    instance_range: InstanceRange,
+
+    max_particles: u32,
+    size: f32,
+    speed: f32,
+    lifetime: f32,
+}
+
+impl ParticleSystem {
+    // Trail particles live for this part of the lifetime,
+    // move this much slower and start this transparent
+    const TRAIL_LIFETIME: f32 = 0.25;
+    const TRAIL_SPEED: f32 = 0.1;
+    const TRAIL_ALPHA: f32 = 0.4;
+
+    pub fn new(
+        max_particles: u32,
+        size: f32,
+        speed: f32,
+        lifetime: f32,
+        instance_allocator: &mut InstanceAllocator,
+    ) -> Self {
+        Self {
+            particles: Vec::with_capacity(max_particles as usize),
+            instance_range: instance_allocator.allocate(max_particles),
+            max_particles,
+            size,
+            speed,
+            lifetime,
+        }
+    }
+
+    // Spawns up to `count` particles flying from the `center` in random
+    // directions. Particles over the `max_particles` limit are skipped.
+    pub fn spawn_burst(
+        &mut self,
+        center: Vector2<f32>,
+        color: [f32; 4],
+        count: u32,
+        rng: &mut impl Rng,
+    ) {
+        let free = self.max_particles as usize - self.particles.len();
+        for _ in 0..(count as usize).min(free) {
+            let angle = rng.gen_range(0.0..std::f32::consts::TAU);
+            let speed = self.speed * rng.gen_range(0.5..1.0);
+            self.particles.push(Particle {
+                position: center,
+                velocity: Vector2::new(angle.cos(), angle.sin()) * speed,
+                color,
+                age: 0.0,
+                lifetime: self.lifetime,
+            });
+        }
+    }
+
+    // Spawns a single faint particle slowly drifting from the `position`,
+    // which fades out faster than the ones of bursts. It is skipped
+    // once there are `max_particles`.
+    pub fn spawn_trail(&mut self, position: Vector2<f32>, color: [f32; 4], rng: &mut impl Rng) {
+        if self.max_particles as usize <= self.particles.len() {
+            return;
+        }
+        let angle = rng.gen_range(0.0..std::f32::consts::TAU);
+        self.particles.push(Particle {
+            position,
+            velocity: Vector2::new(angle.cos(), angle.sin()) * self.speed * Self::TRAIL_SPEED,
+            color: [color[0], color[1], color[2], color[3] * Self::TRAIL_ALPHA],
+            age: 0.0,
+            lifetime: self.lifetime * Self::TRAIL_LIFETIME,
+        });
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.particles.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.particles.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.particles.clear();
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        for particle in self.particles.iter_mut() {
+            particle.position += particle.velocity * dt;
+            particle.age += dt;
+        }
+        self.particles
+            .retain(|particle| particle.age < particle.lifetime);
+    }
+
+    // Particles fade out and shrink towards the end of their lifetime
+    pub fn render_sync(&self, scene: &mut SceneBuffer) {
+        let mut data = self
+            .particles
+            .iter()
+            .map(|particle| {
+                let life_left = 1.0 - particle.age / particle.lifetime;
+                let size = self.size * life_left;
+                InstanceUniform {
+                    transform: Matrix4::from(&Transform {
+                        translation: particle.position.extend(0.0),
+                        scale: Vector3::new(size, size, 1.0),
+                        ..Default::default()
+                    })
+                    .into(),
+                    color: [
+                        particle.color[0],
+                        particle.color[1],
+                        particle.color[2],
+                        particle.color[3] * life_left,
+                    ],
+                    disabled: 0,
+                    uv_rect: InstanceUniform::NO_SPRITE,
+                }
+            })
+            .collect::<Vec<_>>();
+        data.resize(
+            self.max_particles as usize,
+            InstanceUniform {
+                disabled: 1,
+                uv_rect: InstanceUniform::NO_SPRITE,
+                ..Default::default()
+            },
+        );
+        scene.write(self.instance_range, 0, &data);
+    }
+}
@@ -27,14 +27,15 @@ impl Rectangle {
         }
     }
 
+    // The world is y-up, so the top edge has the larger y
     #[inline]
     pub fn top(&self) -> f32 {
-        self.y
+        self.y + self.height
     }
 
     #[inline]
     pub fn bot(&self) -> f32 {
-        self.y + self.height
+        self.y
     }
 
     #[inline]
@@ -46,6 +47,382 @@ impl Rectangle {
     pub fn right(&self) -> f32 {
         self.x + self.width
     }
+
+    #[inline]
+    pub fn contains(&self, point: Vector2<f32>) -> bool {
+        self.left() <= point.x
+            && point.x <= self.right()
+            && self.bot() <= point.y
+            && point.y <= self.top()
+    }
+
+    // Moves `self` along the `displacement` and returns the fraction of it
+    // after which `self` touches `other` together with the collision.
+    // The collision normal points from `other` towards `self`.
+    pub fn sweep_collides(
+        &self,
+        other: &Rectangle,
+        displacement: Vector2<f32>,
+    ) -> Option<(f32, Collision)> {
+        let (entry_x, exit_x) = sweep_axis(
+            self.left(),
+            self.right(),
+            other.left(),
+            other.right(),
+            displacement.x,
+        )?;
+        let (entry_y, exit_y) = sweep_axis(
+            self.bot(),
+            self.top(),
+            other.bot(),
+            other.top(),
+            displacement.y,
+        )?;
+
+        let entry = entry_x.max(entry_y);
+        let exit = exit_x.min(exit_y);
+        if exit <= entry || entry < 0.0 || 1.0 < entry {
+            return None;
+        }
+
+        let center = self.pos() + displacement * entry;
+        let collision = if entry_y < entry_x {
+            let sign = -displacement.x.signum();
+            Collision {
+                pos: Vector2 {
+                    x: if 0.0 < sign {
+                        other.right()
+                    } else {
+                        other.left()
+                    },
+                    y: center.y,
+                },
+                normal: Vector2 { x: sign, y: 0.0 },
+                penetration: 0.0,
+            }
+        } else {
+            let sign = -displacement.y.signum();
+            Collision {
+                pos: Vector2 {
+                    x: center.x,
+                    y: if 0.0 < sign { other.top() } else { other.bot() },
+                },
+                normal: Vector2 { x: 0.0, y: sign },
+                penetration: 0.0,
+            }
+        };
+        Some((entry, collision))
+    }
+
+    // Collision with a circle using the closest point of the rectangle to the
+    // circle center. The normal points from the rectangle towards the circle.
+    pub fn collides_circle(&self, center: Vector2<f32>, radius: f32) -> Option<Collision> {
+        let closest = Vector2 {
+            x: center.x.clamp(self.left(), self.right()),
+            y: center.y.clamp(self.bot(), self.top()),
+        };
+        let delta = center - closest;
+        let distance = delta.x.hypot(delta.y);
+        if radius <= distance {
+            return None;
+        }
+
+        if distance != 0.0 {
+            return Some(Collision {
+                pos: closest,
+                normal: delta / distance,
+                penetration: radius - distance,
+            });
+        }
+
+        // The center is inside of the rectangle, so push
+        // the circle out through the closest side
+        [
+            (center.x - self.left(), Vector2 { x: -1.0, y: 0.0 }),
+            (self.right() - center.x, Vector2 { x: 1.0, y: 0.0 }),
+            (center.y - self.bot(), Vector2 { x: 0.0, y: -1.0 }),
+            (self.top() - center.y, Vector2 { x: 0.0, y: 1.0 }),
+        ]
+        .into_iter()
+        .min_by(|a, b| a.0.total_cmp(&b.0))
+        .map(|(distance, normal)| Collision {
+            pos: center + normal * distance,
+            normal,
+            penetration: distance + radius,
+        })
+    }
+
+    // Moves a circle along the `displacement` and returns the fraction of it
+    // after which the circle touches `self` together with the collision.
+    // Hits on the rectangle corners produce diagonal normals.
+    pub fn sweep_circle(
+        &self,
+        center: Vector2<f32>,
+        radius: f32,
+        displacement: Vector2<f32>,
+    ) -> Option<(f32, Collision)> {
+        // Already overlapping circles only collide if they keep moving inside
+        if let Some(collision) = self.collides_circle(center, radius) {
+            let moving_in =
+                displacement.x * collision.normal.x + displacement.y * collision.normal.y < 0.0;
+            return moving_in.then_some((0.0, collision));
+        }
+
+        // Circle center against the rectangle grown by the radius
+        let (entry_x, exit_x) = sweep_axis(
+            center.x,
+            center.x,
+            self.left() - radius,
+            self.right() + radius,
+            displacement.x,
+        )?;
+        let (entry_y, exit_y) = sweep_axis(
+            center.y,
+            center.y,
+            self.bot() - radius,
+            self.top() + radius,
+            displacement.y,
+        )?;
+
+        let entry = entry_x.max(entry_y);
+        let exit = exit_x.min(exit_y);
+        if exit <= entry || entry < 0.0 || 1.0 < entry {
+            return None;
+        }
+
+        let hit = center + displacement * entry;
+        if self.left() <= hit.x && hit.x <= self.right() {
+            let sign = -displacement.y.signum();
+            return Some((
+                entry,
+                Collision {
+                    pos: Vector2 {
+                        x: hit.x,
+                        y: if 0.0 < sign { self.top() } else { self.bot() },
+                    },
+                    normal: Vector2 { x: 0.0, y: sign },
+                    penetration: 0.0,
+                },
+            ));
+        }
+        if self.bot() <= hit.y && hit.y <= self.top() {
+            let sign = -displacement.x.signum();
+            return Some((
+                entry,
+                Collision {
+                    pos: Vector2 {
+                        x: if 0.0 < sign {
+                            self.right()
+                        } else {
+                            self.left()
+                        },
+                        y: hit.y,
+                    },
+                    normal: Vector2 { x: sign, y: 0.0 },
+                    penetration: 0.0,
+                },
+            ));
+        }
+
+        // The grown rectangle was entered near a corner, so the circle
+        // either hits the corner itself or passes by it
+        let corner = Vector2 {
+            x: hit.x.clamp(self.left(), self.right()),
+            y: hit.y.clamp(self.bot(), self.top()),
+        };
+        let to_center = center - corner;
+        let a = displacement.x * displacement.x + displacement.y * displacement.y;
+        let b = 2.0 * (to_center.x * displacement.x + to_center.y * displacement.y);
+        let c = to_center.x * to_center.x + to_center.y * to_center.y - radius * radius;
+        let discriminant = b * b - 4.0 * a * c;
+        if discriminant < 0.0 {
+            return None;
+        }
+        let toi = (-b - discriminant.sqrt()) / (2.0 * a);
+        if !(0.0..=1.0).contains(&toi) {
+            return None;
+        }
+        Some((
+            toi,
+            Collision {
+                pos: corner,
+                normal: (center + displacement * toi - corner) / radius,
+                penetration: 0.0,
+            },
+        ))
+    }
+
+    // Same rectangle rotated around its center by the `rotation`
+    // in radians counterclockwise
+    #[inline]
+    pub fn to_obb(&self, rotation: f32) -> Obb {
+        Obb {
+            center: self.pos(),
+            half_extents: Vector2 {
+                x: self.width / 2.0,
+                y: self.height / 2.0,
+            },
+            rotation,
+        }
+    }
+}
+
+// Oriented box: a rectangle rotated around its center
+// by `rotation` in radians counterclockwise.
+// Circles are tested against it in its own frame, where it is an
+// axis aligned rectangle, and boxes with the separating axis test.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Obb {
+    pub center: Vector2<f32>,
+    pub half_extents: Vector2<f32>,
+    pub rotation: f32,
+}
+
+impl Obb {
+    // Unit vectors along the width and the height of the box
+    #[inline]
+    pub fn axes(&self) -> [Vector2<f32>; 2] {
+        let (sin, cos) = self.rotation.sin_cos();
+        [Vector2 { x: cos, y: sin }, Vector2 { x: -sin, y: cos }]
+    }
+
+    // Axis aligned rectangle containing the box
+    pub fn aabb(&self) -> Rectangle {
+        let [x_axis, y_axis] = self.axes();
+        Rectangle::from_center(
+            self.center,
+            2.0 * (x_axis.x.abs() * self.half_extents.x + y_axis.x.abs() * self.half_extents.y),
+            2.0 * (x_axis.y.abs() * self.half_extents.x + y_axis.y.abs() * self.half_extents.y),
+        )
+    }
+
+    // The box in its own frame
+    #[inline]
+    fn local_rect(&self) -> Rectangle {
+        Rectangle::from_center(
+            Vector2 { x: 0.0, y: 0.0 },
+            self.half_extents.x * 2.0,
+            self.half_extents.y * 2.0,
+        )
+    }
+
+    #[inline]
+    fn rotate_to_local(&self, v: Vector2<f32>) -> Vector2<f32> {
+        let [x_axis, y_axis] = self.axes();
+        Vector2 {
+            x: dot(v, x_axis),
+            y: dot(v, y_axis),
+        }
+    }
+
+    #[inline]
+    fn rotate_to_world(&self, v: Vector2<f32>) -> Vector2<f32> {
+        let [x_axis, y_axis] = self.axes();
+        x_axis * v.x + y_axis * v.y
+    }
+
+    #[inline]
+    fn collision_to_world(&self, collision: Collision) -> Collision {
+        Collision {
+            pos: self.center + self.rotate_to_world(collision.pos),
+            normal: self.rotate_to_world(collision.normal),
+            penetration: collision.penetration,
+        }
+    }
+
+    // Same as `Rectangle::collides_circle`, tested in the frame of the box
+    pub fn collides_circle(&self, center: Vector2<f32>, radius: f32) -> Option<Collision> {
+        self.local_rect()
+            .collides_circle(self.rotate_to_local(center - self.center), radius)
+            .map(|collision| self.collision_to_world(collision))
+    }
+
+    // Same as `Rectangle::sweep_circle`. Rotation keeps lengths,
+    // so the fraction of the move is the same in both frames.
+    pub fn sweep_circle(
+        &self,
+        center: Vector2<f32>,
+        radius: f32,
+        displacement: Vector2<f32>,
+    ) -> Option<(f32, Collision)> {
+        self.local_rect()
+            .sweep_circle(
+                self.rotate_to_local(center - self.center),
+                radius,
+                self.rotate_to_local(displacement),
+            )
+            .map(|(toi, collision)| (toi, self.collision_to_world(collision)))
+    }
+
+    // Half of the length of the box projected onto the `axis`
+    #[inline]
+    fn projected_radius(&self, axis: Vector2<f32>) -> f32 {
+        let [x_axis, y_axis] = self.axes();
+        self.half_extents.x * dot(x_axis, axis).abs()
+            + self.half_extents.y * dot(y_axis, axis).abs()
+    }
+
+    // Separating axis test with the axes of both boxes. The collision
+    // is along the axis of the smallest overlap with the normal pointing
+    // from `self` towards `other`, same as in `Rectangle::collides`.
+    pub fn collides_obb(&self, other: &Obb) -> Option<Collision> {
+        let delta = other.center - self.center;
+        let (mut penetration, mut normal) = (f32::INFINITY, Vector2 { x: 0.0, y: 0.0 });
+        for axis in self.axes().into_iter().chain(other.axes()) {
+            let distance = dot(delta, axis);
+            let overlap =
+                self.projected_radius(axis) + other.projected_radius(axis) - distance.abs();
+            if overlap <= 0.0 {
+                return None;
+            }
+            if overlap < penetration {
+                penetration = overlap;
+                normal = if distance < 0.0 { -axis } else { axis };
+            }
+        }
+
+        // Center of the `other` moved onto the face of `self`
+        let face_distance = dot(delta, normal) - self.projected_radius(normal);
+        Some(Collision {
+            pos: other.center - normal * face_distance,
+            normal,
+            penetration,
+        })
+    }
+}
+
+#[inline]
+fn dot(a: Vector2<f32>, b: Vector2<f32>) -> f32 {
+    a.x * b.x + a.y * b.y
+}
+
+// Returns times of entry and exit of the moving [min, max] segment
+// into the static [other_min, other_max] one
+#[inline]
+fn sweep_axis(
+    min: f32,
+    max: f32,
+    other_min: f32,
+    other_max: f32,
+    displacement: f32,
+) -> Option<(f32, f32)> {
+    if displacement == 0.0 {
+        if max <= other_min || other_max <= min {
+            None
+        } else {
+            Some((f32::NEG_INFINITY, f32::INFINITY))
+        }
+    } else if 0.0 < displacement {
+        Some((
+            (other_min - max) / displacement,
+            (other_max - min) / displacement,
+        ))
+    } else {
+        Some((
+            (other_max - min) / displacement,
+            (other_min - max) / displacement,
+        ))
+    }
 }
 
 // Represents collision between colliders
@@ -53,6 +430,100 @@ impl Rectangle {
 pub struct Collision {
     pub pos: Vector2<f32>,
     pub normal: Vector2<f32>,
+    // How far the colliders overlap along the normal
+    pub penetration: f32,
+}
+
+impl Collision {
+    // Single collision standing in for the simultaneous `collisions`.
+    // Normals are averaged, so hitting two neighbors on their shared side
+    // reflects once and hitting an inner corner reflects off both sides.
+    // Opposite normals cancel out, in which case the first one is kept.
+    pub fn combine(collisions: &[Collision]) -> Option<Collision> {
+        let (first, rest) = collisions.split_first()?;
+        if rest.is_empty() {
+            return Some(*first);
+        }
+
+        let n = collisions.len() as f32;
+        let (pos, normal) = collisions.iter().fold(
+            (Vector2 { x: 0.0, y: 0.0 }, Vector2 { x: 0.0, y: 0.0 }),
+            |(pos, normal), c| (pos + c.pos, normal + c.normal),
+        );
+        let length = normal.x.hypot(normal.y);
+        Some(Collision {
+            pos: pos / n,
+            normal: if length < f32::EPSILON {
+                first.normal
+            } else {
+                normal / length
+            },
+            penetration: collisions.iter().map(|c| c.penetration).fold(0.0, f32::max),
+        })
+    }
+}
+
+// Decides how the ball velocity changes after a collision.
+// The ball is already pushed out of the collider when it is called
+// and the returned velocity is rescaled to the ball velocity magnitude,
+// so only its direction matters.
+//
+// Custom bouncers implement it and are assigned to `Ball::response`
+// or supplied by a collider, like `CratePack::response`:
+//
+// ```
+// struct Sticky;
+//
+// impl CollisionResponse for Sticky {
+//     // Slides along the surface instead of bouncing off
+//     fn respond(&self, velocity: Vector2<f32>, collision: &Collision) -> Vector2<f32> {
+//         let dot = velocity.dot(collision.normal);
+//         velocity - collision.normal * dot.min(0.0)
+//     }
+// }
+// ```
+pub trait CollisionResponse {
+    fn respond(&self, velocity: Vector2<f32>, collision: &Collision) -> Vector2<f32>;
+}
+
+// Reflects the velocity about the collision normal
+// if it is moving against it
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Reflect;
+
+impl CollisionResponse for Reflect {
+    fn respond(&self, velocity: Vector2<f32>, collision: &Collision) -> Vector2<f32> {
+        let dot = velocity.x * collision.normal.x + velocity.y * collision.normal.y;
+        if dot < 0.0 {
+            velocity - collision.normal * 2.0 * dot
+        } else {
+            velocity
+        }
+    }
+}
+
+// Pulls objects within `radius` towards its center
+// with inverse distance strength
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GravityWell {
+    pub pos: Vector2<f32>,
+    pub radius: f32,
+    pub strength: f32,
+}
+
+impl GravityWell {
+    // Closer distances are clamped to this value to avoid
+    // infinite acceleration near the center
+    const MIN_DISTANCE: f32 = 0.5;
+
+    pub fn acceleration(&self, point: Vector2<f32>) -> Vector2<f32> {
+        let to_well = self.pos - point;
+        let distance = to_well.x.hypot(to_well.y);
+        if distance == 0.0 || self.radius < distance {
+            return Vector2 { x: 0.0, y: 0.0 };
+        }
+        to_well / distance * self.strength / distance.max(Self::MIN_DISTANCE)
+    }
 }
 
 // Trait for determining collison
@@ -64,6 +535,10 @@ pub trait Collider {
     fn collides_mut(&mut self, _other: &impl Collider) -> Option<Collision> {
         None
     }
+    // Response used instead of the one of the ball
+    fn response(&self) -> Option<&dyn CollisionResponse> {
+        None
+    }
 }
 
 impl Collider for Rectangle {
@@ -107,6 +582,7 @@ impl Collider for Rectangle {
                     y: other_rect.pos().y,
                 },
                 normal: Vector2 { x: sign, y: 0.0 },
+                penetration: px,
             })
         } else {
             let sign = dy.signum();
@@ -116,7 +592,103 @@ impl Collider for Rectangle {
                     y: this_rect.pos().y + this_rect.height / 2.0 * sign,
                 },
                 normal: Vector2 { x: 0.0, y: sign },
+                penetration: py,
             })
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-5, "{a} != {b}");
+    }
+
+    #[test]
+    fn sweep_stops_at_a_thin_rectangle() {
+        let moving = Rectangle::from_center(Vector2::new(-3.0, 0.0), 1.0, 1.0);
+        let thin = Rectangle::from_center(Vector2::new(0.0, 0.0), 0.2, 2.0);
+
+        let (toi, collision) = moving
+            .sweep_collides(&thin, Vector2::new(6.0, 0.0))
+            .unwrap();
+
+        assert_close(toi, 0.4);
+        assert_eq!(collision.normal, Vector2::new(-1.0, 0.0));
+        assert_close(collision.pos.x, thin.left());
+    }
+
+    #[test]
+    fn sweep_misses_rectangles_off_the_path() {
+        let moving = Rectangle::from_center(Vector2::new(-3.0, 0.0), 1.0, 1.0);
+        let thin = Rectangle::from_center(Vector2::new(0.0, 5.0), 0.2, 2.0);
+
+        assert!(moving
+            .sweep_collides(&thin, Vector2::new(6.0, 0.0))
+            .is_none());
+        // Too short to reach it
+        let near = Rectangle::from_center(Vector2::new(0.0, 0.0), 0.2, 2.0);
+        assert!(moving
+            .sweep_collides(&near, Vector2::new(2.0, 0.0))
+            .is_none());
+    }
+
+    #[test]
+    fn circle_sweep_stops_at_a_thin_rectangle() {
+        let thin = Rectangle::from_center(Vector2::new(0.0, 0.0), 0.2, 2.0);
+
+        let (toi, collision) = thin
+            .sweep_circle(Vector2::new(-3.0, 0.0), 0.5, Vector2::new(6.0, 0.0))
+            .unwrap();
+
+        assert_close(toi, 0.4);
+        assert_eq!(collision.normal, Vector2::new(-1.0, 0.0));
+    }
+
+    #[test]
+    fn top_is_above_bottom_in_the_y_up_world() {
+        let rect = Rectangle::from_center(Vector2::new(1.0, 2.0), 4.0, 2.0);
+
+        assert!(rect.bot() < rect.top());
+        assert_close(rect.top(), 3.0);
+        assert_close(rect.bot(), 1.0);
+        assert!(rect.left() < rect.right());
+        assert_eq!(rect.pos(), Vector2::new(1.0, 2.0));
+        assert!(rect.contains(Vector2::new(1.0, 2.9)));
+        assert!(!rect.contains(Vector2::new(1.0, 3.1)));
+    }
+
+    #[test]
+    fn circle_overlapping_a_face_is_pushed_straight_out() {
+        let rect = Rectangle::from_center(Vector2::new(0.0, 0.0), 2.0, 1.0);
+
+        let collision = rect.collides_circle(Vector2::new(0.3, 0.8), 0.5).unwrap();
+
+        assert_eq!(collision.normal, Vector2::new(0.0, 1.0));
+        assert_close(collision.penetration, 0.2);
+        assert_close(collision.pos.x, 0.3);
+        assert_close(collision.pos.y, rect.top());
+    }
+
+    #[test]
+    fn circle_overlapping_a_corner_is_pushed_away_from_the_vertex() {
+        let rect = Rectangle::from_center(Vector2::new(0.0, 0.0), 2.0, 1.0);
+        let center = Vector2::new(1.3, 0.9);
+
+        let collision = rect.collides_circle(center, 0.6).unwrap();
+
+        // Top right vertex is the closest point, 0.5 away from the center
+        let vertex = Vector2::new(rect.right(), rect.top());
+        assert_eq!(collision.pos, vertex);
+        let to_center = center - vertex;
+        let distance = to_center.x.hypot(to_center.y);
+        assert_close(collision.normal.x, to_center.x / distance);
+        assert_close(collision.normal.y, to_center.y / distance);
+        assert_close(collision.penetration, 0.1);
+
+        // Just out of reach of the corner
+        assert!(rect.collides_circle(center, 0.45).is_none());
+    }
+}
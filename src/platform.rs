@@ -1,110 +1,415 @@
+use serde::{Deserialize, Serialize};
 use zero::{
-    cgmath_imports::{Matrix4, Vector3},
-    prelude::winit::{event::ElementState, keyboard::Key},
-    render::{renderer::Renderer, storage::RenderStorage},
+    cgmath_imports::{Matrix4, Quaternion, Rad, Rotation3, Vector2, Vector3},
     transform::Transform,
 };
 
 use crate::{
     border::Border,
-    physics::{Collider, Collision, Rectangle},
-    rendering::{InstanceUniform, Instances},
+    physics::{Collider, Collision, Obb, Rectangle},
+    rendering::{InstanceAllocator, InstanceRange, InstanceUniform, SceneBuffer},
 };
 
+// Everything that changes while the platform moves
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PlatformState {
+    pub x: f32,
+    pub previous_x: f32,
+    pub velocity_x: f32,
+    // Missing in saves from before the platform had momentum
+    #[serde(default)]
+    pub drive_velocity: f32,
+    pub width: f32,
+    pub target_width: f32,
+    pub width_speed: f32,
+    // Missing in saves from before the platform could be frozen
+    #[serde(default)]
+    pub frozen: f32,
+}
+
 pub struct Platform {
     position: Vector3<f32>,
     width: f32,
+    // Width is tweened towards the target at `width_speed`
+    // units per second, keeping the center in place
+    target_width: f32,
+    width_speed: f32,
     height: f32,
+    // Tilt in radians counterclockwise
+    rotation: f32,
     color: [f32; 4],
+    // Drawn instead of the color while set
+    tint: Option<[f32; 4]>,
     speed: f32,
+    // Input in [-1, 1], the platform speeds up towards `movement * speed`
     movement: f32,
-    instance_buffer_offset: u64,
+    // Units per second squared the platform speeds up with while moved
+    // and slows down with once released. Zero changes speed instantly.
+    accel: f32,
+    friction: f32,
+    // Velocity the platform moves with before walls stop it
+    drive_velocity: f32,
+    // Horizontal velocity during the last update,
+    // zero while the platform is pushing into a wall
+    velocity_x: f32,
+    // Position before the last update
+    previous_x: f32,
+    // Seconds left until the platform follows the input again
+    frozen: f32,
+    instance_range: InstanceRange,
 }
 
 impl Platform {
+    // Angle from the vertical in degrees at which
+    // the ball leaves the edge of the platform
+    const MAX_BOUNCE_ANGLE: f32 = 60.0;
+    // Seconds the width takes to reach a new target
+    const WIDTH_TWEEN_DURATION: f32 = 0.25;
+
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         position: Vector3<f32>,
         width: f32,
         height: f32,
         color: [f32; 4],
         speed: f32,
-        instance_buffer_offset: u64,
+        accel: f32,
+        friction: f32,
+        instance_allocator: &mut InstanceAllocator,
     ) -> Self {
         Self {
             position,
             width,
+            target_width: width,
+            width_speed: 0.0,
             height,
+            rotation: 0.0,
             color,
+            tint: None,
             speed,
             movement: 0.0,
-            instance_buffer_offset,
+            accel,
+            friction,
+            drive_velocity: 0.0,
+            velocity_x: 0.0,
+            previous_x: position.x,
+            frozen: 0.0,
+            instance_range: instance_allocator.allocate(1),
         }
     }
 
-    pub fn handle_input(&mut self, key: &Key, state: &ElementState) {
-        let pressed = if *state == ElementState::Pressed {
-            1.0
-        } else {
-            0.0
-        };
-        if let Key::Character(c) = key {
-            match c.as_str() {
-                "a" | "A" => {
-                    self.movement = pressed;
-                }
-                "d" | "D" => {
-                    self.movement = -pressed;
-                }
-                _ => {}
-            }
+    pub fn state(&self) -> PlatformState {
+        PlatformState {
+            x: self.position.x,
+            previous_x: self.previous_x,
+            velocity_x: self.velocity_x,
+            drive_velocity: self.drive_velocity,
+            width: self.width,
+            target_width: self.target_width,
+            width_speed: self.width_speed,
+            frozen: self.frozen,
         }
     }
 
+    pub fn set_state(&mut self, state: &PlatformState) {
+        self.position.x = state.x;
+        self.previous_x = state.previous_x;
+        self.velocity_x = state.velocity_x;
+        self.drive_velocity = state.drive_velocity;
+        self.width = state.width;
+        self.target_width = state.target_width;
+        self.width_speed = state.width_speed;
+        self.frozen = state.frozen;
+    }
+
+    // Movement in [-1, 1] relative to the platform speed, which the
+    // platform speeds up to. Positive `axis` moves the platform right.
+    pub fn set_analog_movement(&mut self, axis: f32) {
+        self.movement = axis.clamp(-1.0, 1.0);
+    }
+
+    #[inline]
+    pub fn position(&self) -> Vector3<f32> {
+        self.position
+    }
+
+    #[inline]
+    pub fn width(&self) -> f32 {
+        self.width
+    }
+
+    #[inline]
+    pub fn velocity_x(&self) -> f32 {
+        self.velocity_x
+    }
+
+    // Stops the platform at once and ignores the input for the `duration`
+    // in seconds. Freezing a frozen platform keeps the longer freeze.
+    pub fn freeze(&mut self, duration: f32) {
+        self.frozen = self.frozen.max(duration);
+        self.drive_velocity = 0.0;
+    }
+
+    #[inline]
+    pub fn is_frozen(&self) -> bool {
+        0.0 < self.frozen
+    }
+
+    pub fn set_color(&mut self, color: [f32; 4]) {
+        self.color = color;
+    }
+
+    pub fn set_tint(&mut self, tint: Option<[f32; 4]>) {
+        self.tint = tint;
+    }
+
+    // Tilts the platform around its center by the
+    // `rotation` in radians counterclockwise
+    pub fn set_rotation(&mut self, rotation: f32) {
+        self.rotation = rotation;
+    }
+
+    // Unit vector out of the top face of the platform
+    #[inline]
+    pub fn up(&self) -> Vector2<f32> {
+        self.obb().axes()[1]
+    }
+
+    // Middle of the top face of the platform
+    #[inline]
+    pub fn top_center(&self) -> Vector2<f32> {
+        self.position.truncate() + self.up() * (self.height / 2.0)
+    }
+
+    #[inline]
+    pub fn target_width(&self) -> f32 {
+        self.target_width
+    }
+
+    // Width changes to the `width` over `WIDTH_TWEEN_DURATION`
+    pub fn set_target_width(&mut self, width: f32) {
+        self.target_width = width;
+        self.width_speed = (width - self.width).abs() / Self::WIDTH_TWEEN_DURATION;
+    }
+
+    // Platform without the tilt
     #[inline]
     pub fn border(&self) -> Rectangle {
         Rectangle::from_center(self.position.truncate(), self.width, self.height)
     }
 
+    #[inline]
+    pub fn obb(&self) -> Obb {
+        self.border().to_obb(self.rotation)
+    }
+
+    // Velocity of the ball bouncing off the platform. The closer the ball is
+    // to the platform edge, the steeper the angle. Balls hitting the corner
+    // or beyond it bounce with the steepest angle. Angles are measured
+    // from the `up` direction, so tilted platforms tilt the bounce as well.
+    pub fn bounce_velocity(&self, ball_center: Vector2<f32>, incoming_speed: f32) -> Vector2<f32> {
+        let [along, _] = self.obb().axes();
+        let to_ball = ball_center - self.position.truncate();
+        let offset =
+            ((to_ball.x * along.x + to_ball.y * along.y) / (self.width / 2.0)).clamp(-1.0, 1.0);
+        // Clockwise from the vertical, so the tilt is subtracted
+        let angle = offset * Self::MAX_BOUNCE_ANGLE.to_radians() - self.rotation;
+        Vector2 {
+            x: incoming_speed * angle.sin(),
+            y: incoming_speed * angle.cos(),
+        }
+    }
+
+    // Moves the platform from its position before the last update to the
+    // current one and returns the fraction of the move after which it
+    // touches the static circle together with the collision.
+    // The collision normal points from the platform towards the circle.
+    pub fn sweep_circle(&self, center: Vector2<f32>, radius: f32) -> Option<(f32, Collision)> {
+        let displacement = self.displacement();
+        if displacement.x == 0.0 {
+            return None;
+        }
+        let previous = Rectangle::from_center(
+            Vector2::new(self.previous_x, self.position.y),
+            self.width,
+            self.height,
+        )
+        .to_obb(self.rotation);
+        // Same as the circle moving against the platform
+        previous.sweep_circle(center, radius, -displacement)
+    }
+
+    #[inline]
+    pub fn displacement(&self) -> Vector2<f32> {
+        Vector2::new(self.position.x - self.previous_x, 0.0)
+    }
+
     pub fn update(&mut self, border: &Border, dt: f32) {
-        self.position.x -= self.movement * self.speed * dt;
+        let step = self.width_speed * dt;
+        self.width = if self.width < self.target_width {
+            (self.width + step).min(self.target_width)
+        } else {
+            (self.width - step).max(self.target_width)
+        };
 
+        // Frozen platform ignores the input and does not glide either
+        let movement = if self.is_frozen() {
+            self.frozen = (self.frozen - dt).max(0.0);
+            self.drive_velocity = 0.0;
+            0.0
+        } else {
+            self.movement
+        };
+
+        // Speeds up towards the input and glides to a stop once released
+        let target = movement * self.speed;
+        let rate = if movement == 0.0 {
+            self.friction
+        } else {
+            self.accel
+        };
+        let change = target - self.drive_velocity;
+        self.drive_velocity = if 0.0 < rate {
+            self.drive_velocity + change.clamp(-rate * dt, rate * dt)
+        } else {
+            target
+        }
+        .clamp(-self.speed, self.speed);
+
+        let x = self.position.x;
+        self.previous_x = x;
+        self.position.x += self.drive_velocity * dt;
+
+        // Also pushes the platform out of the wall it grew into, which
+        // stops it. Walls are tested against the bounds of the tilted platform.
         if let Some(collision) = border.collides(self) {
+            self.drive_velocity = 0.0;
+            let half_width = self.obb().aabb().width / 2.0;
             if 0.0 <= collision.normal.x {
-                self.position.x = collision.pos.x + self.width / 2.0;
+                self.position.x = collision.pos.x + half_width;
             } else {
-                self.position.x = collision.pos.x - self.width / 2.0;
+                self.position.x = collision.pos.x - half_width;
             }
         }
+        if 0.0 < dt {
+            self.velocity_x = (self.position.x - x) / dt;
+        }
     }
 
-    pub fn render_sync(&self, renderer: &Renderer, storage: &RenderStorage, boxes: &Instances) {
+    pub fn render_sync(&self, scene: &mut SceneBuffer) {
         let data = InstanceUniform {
             transform: Matrix4::from(&Transform {
                 translation: self.position,
+                rotation: Quaternion::from_angle_z(Rad(self.rotation)),
                 scale: Vector3::new(self.width, self.height, 1.0),
-                ..Default::default()
             })
             .into(),
-            color: self.color,
+            color: self.tint.unwrap_or(self.color),
             disabled: 0,
+            uv_rect: InstanceUniform::NO_SPRITE,
         };
-        boxes.instance_buffer_handle.update(
-            renderer,
-            storage,
-            self.instance_buffer_offset,
-            &[data],
-        );
+        scene.write(self.instance_range, 0, &[data]);
     }
 }
 
 impl Collider for Platform {
+    // Bounds of the tilted platform
     #[inline]
     fn rect(&self) -> Option<Rectangle> {
-        Some(self.border())
+        Some(self.obb().aabb())
     }
 
     #[inline]
     fn collides(&self, other: &impl Collider) -> Option<Collision> {
-        self.border().collides(other)
+        self.obb().collides_obb(&other.rect()?.to_obb(0.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::border::Walls;
+
+    fn border(allocator: &mut InstanceAllocator) -> Border {
+        Border::new(
+            15.0,
+            20.0,
+            0.2,
+            [1.0; 4],
+            [0.0; 4],
+            Walls::ALL,
+            false,
+            allocator,
+        )
+    }
+
+    fn platform(x: f32, allocator: &mut InstanceAllocator) -> Platform {
+        Platform::new(
+            Vector3::new(x, -8.0, 0.0),
+            2.0,
+            0.5,
+            [1.0; 4],
+            5.0,
+            40.0,
+            30.0,
+            allocator,
+        )
+    }
+
+    #[test]
+    fn width_converges_to_the_target_around_the_center() {
+        let mut allocator = InstanceAllocator::default();
+        let border = border(&mut allocator);
+        let mut platform = platform(0.0, &mut allocator);
+
+        platform.set_target_width(4.0);
+        platform.update(&border, Platform::WIDTH_TWEEN_DURATION / 2.0);
+        assert!(2.0 < platform.width() && platform.width() < 4.0);
+        for _ in 0..10 {
+            platform.update(&border, 0.1);
+        }
+
+        assert_eq!(platform.width(), 4.0);
+        assert_eq!(platform.position().x, 0.0);
+    }
+
+    #[test]
+    fn growing_next_to_a_wall_keeps_the_platform_inside() {
+        let mut allocator = InstanceAllocator::default();
+        let border = border(&mut allocator);
+        let mut platform = platform(6.0, &mut allocator);
+
+        platform.set_target_width(6.0);
+        for _ in 0..10 {
+            platform.update(&border, 0.1);
+        }
+
+        assert_eq!(platform.width(), 6.0);
+        assert!(platform.border().right() <= border.border().right());
+        assert!(border.border().left() <= platform.border().left());
+    }
+
+    #[test]
+    fn platform_glides_to_a_stop_after_the_input_is_released() {
+        let mut allocator = InstanceAllocator::default();
+        let border = border(&mut allocator);
+        let mut platform = platform(-4.0, &mut allocator);
+
+        platform.set_analog_movement(1.0);
+        for _ in 0..20 {
+            platform.update(&border, 0.05);
+        }
+        assert!((platform.velocity_x() - 5.0).abs() < 1e-3);
+
+        platform.set_analog_movement(0.0);
+        platform.update(&border, 0.05);
+        assert!(0.0 < platform.velocity_x() && platform.velocity_x() < 5.0);
+        for _ in 0..10 {
+            platform.update(&border, 0.05);
+        }
+        assert_eq!(platform.velocity_x(), 0.0);
+        let x = platform.position().x;
+        platform.update(&border, 0.05);
+        assert_eq!(platform.position().x, x);
     }
 }
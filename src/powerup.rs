@@ -0,0 +1,124 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use zero::prelude::*;
+
+use crate::{
+    physics::{Collider, Rectangle},
+    rendering::InstanceUniform,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PowerUpKind {
+    WidePaddle,
+    SlowBall,
+    ExtraLife,
+    MultiBall,
+    // Debuff swapping left and right for a while
+    InvertControls,
+}
+
+impl PowerUpKind {
+    const ALL: [PowerUpKind; 5] = [
+        PowerUpKind::WidePaddle,
+        PowerUpKind::SlowBall,
+        PowerUpKind::ExtraLife,
+        PowerUpKind::MultiBall,
+        PowerUpKind::InvertControls,
+    ];
+
+    pub fn random(rng: &mut impl Rng) -> Self {
+        Self::ALL[rng.gen_range(0..Self::ALL.len())]
+    }
+
+    pub fn color(&self) -> [f32; 4] {
+        match self {
+            PowerUpKind::WidePaddle => [0.2, 0.4, 1.0, 1.0],
+            PowerUpKind::SlowBall => [1.0, 0.8, 0.1, 1.0],
+            PowerUpKind::ExtraLife => [1.0, 0.3, 0.6, 1.0],
+            PowerUpKind::MultiBall => [0.6, 1.0, 1.0, 1.0],
+            PowerUpKind::InvertControls => [0.6, 0.2, 0.9, 1.0],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PowerUpState {
+    pub kind: PowerUpKind,
+    pub position: [f32; 2],
+}
+
+// Falls down after dropping from a crate
+// until it is caught by the platform
+pub struct PowerUp {
+    kind: PowerUpKind,
+    transform: Transform,
+}
+
+impl PowerUp {
+    const WIDTH: f32 = 0.8;
+    const HEIGHT: f32 = 0.4;
+    const FALL_SPEED: f32 = 3.0;
+
+    pub fn new(kind: PowerUpKind, position: Vector2<f32>) -> Self {
+        Self {
+            kind,
+            transform: Transform {
+                translation: position.extend(0.0),
+                scale: Vector3::new(Self::WIDTH, Self::HEIGHT, 1.0),
+                ..Default::default()
+            },
+        }
+    }
+
+    pub fn from_state(state: &PowerUpState) -> Self {
+        Self::new(state.kind, state.position.into())
+    }
+
+    #[inline]
+    pub fn state(&self) -> PowerUpState {
+        PowerUpState {
+            kind: self.kind,
+            position: self.position().into(),
+        }
+    }
+
+    #[inline]
+    pub fn kind(&self) -> PowerUpKind {
+        self.kind
+    }
+
+    #[inline]
+    pub fn position(&self) -> Vector2<f32> {
+        self.transform.translation.truncate()
+    }
+
+    #[inline]
+    pub fn border(&self) -> Rectangle {
+        Rectangle::from_center(self.position(), Self::WIDTH, Self::HEIGHT)
+    }
+
+    // Falls down while drifting towards the `target_x` by up to
+    // `magnet` units per second. 0 makes it fall straight down.
+    pub fn update(&mut self, dt: f32, target_x: f32, magnet: f32) {
+        self.transform.translation.y -= Self::FALL_SPEED * dt;
+        let drift = magnet * dt;
+        let x = &mut self.transform.translation.x;
+        *x += (target_x - *x).clamp(-drift, drift);
+    }
+
+    pub fn instance(&self) -> InstanceUniform {
+        InstanceUniform {
+            transform: Matrix4::from(&self.transform).into(),
+            color: self.kind.color(),
+            disabled: 0,
+            uv_rect: InstanceUniform::NO_SPRITE,
+        }
+    }
+}
+
+impl Collider for PowerUp {
+    #[inline]
+    fn rect(&self) -> Option<Rectangle> {
+        Some(self.border())
+    }
+}
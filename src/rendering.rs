@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use zero::{impl_simple_sized_gpu_buffer, prelude::*};
 
 #[repr(C)]
@@ -9,6 +11,7 @@ pub struct InstanceVertex {
     pub transform_3: [f32; 4],
     pub color: [f32; 4],
     pub disabled: i32,
+    pub uv_rect: [f32; 4],
 }
 
 impl VertexLayout for InstanceVertex {
@@ -47,6 +50,11 @@ impl VertexLayout for InstanceVertex {
                     shader_location: 10,
                     format: VertexFormat::Sint32,
                 },
+                VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 21]>() as BufferAddress,
+                    shader_location: 11,
+                    format: VertexFormat::Float32x4,
+                },
             ],
         }
     }
@@ -58,10 +66,18 @@ pub struct InstanceUniform {
     pub transform: [[f32; 4]; 4],
     pub color: [f32; 4],
     pub disabled: u32,
+    // Sprite in the texture atlas as x, y, width and height in UV
+    // coordinates, multiplied by the color. Zero width draws the
+    // flat color, as does the pipeline without a texture.
+    pub uv_rect: [f32; 4],
+}
+
+impl InstanceUniform {
+    pub const NO_SPRITE: [f32; 4] = [0.0; 4];
 }
 
 impl_simple_sized_gpu_buffer!(InstancesBuffer, InstancesBufferResources, {
-    BufferUsages::VERTEX | BufferUsages::COPY_DST
+    BufferUsages::VERTEX | BufferUsages::COPY_SRC | BufferUsages::COPY_DST
 });
 
 pub struct InstanceBufferHandle {
@@ -90,34 +106,248 @@ impl InstanceBufferHandle {
     }
 }
 
+// Range of instances inside of the `Instances` buffer
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct InstanceRange {
+    pub start: u32,
+    pub num: u32,
+}
+
+impl InstanceRange {
+    // Offset of the range start in bytes
+    #[inline]
+    pub fn offset(&self) -> BufferAddress {
+        self.offset_of(0)
+    }
+
+    // Offset of the `index`th instance of the range in bytes
+    #[inline]
+    pub fn offset_of(&self, index: u32) -> BufferAddress {
+        (self.start + index) as BufferAddress
+            * std::mem::size_of::<InstanceUniform>() as BufferAddress
+    }
+}
+
+// CPU side copy of an instance buffer shared by many objects. Objects
+// write their instances into it and everything changed since the last
+// upload goes to the GPU with a single buffer write.
+#[derive(Debug, Default)]
+pub struct SceneBuffer {
+    data: Vec<InstanceUniform>,
+    // Changed instances as a [start, end) range
+    dirty: Option<(u32, u32)>,
+    // Buffer writes done so far
+    writes: u64,
+}
+
+impl SceneBuffer {
+    pub fn new(num: u32) -> Self {
+        Self {
+            data: vec![InstanceUniform::default(); num as usize],
+            dirty: None,
+            writes: 0,
+        }
+    }
+
+    // Clears the buffer for a new layout of `num` instances.
+    // Everything has to be written again before the next upload.
+    pub fn resize(&mut self, num: u32) {
+        self.data.clear();
+        self.data.resize(num as usize, InstanceUniform::default());
+        self.dirty = None;
+    }
+
+    #[inline]
+    pub fn writes(&self) -> u64 {
+        self.writes
+    }
+
+    // Instances as they will be uploaded
+    #[inline]
+    pub fn instances(&self) -> &[InstanceUniform] {
+        &self.data
+    }
+
+    // Writes the `instances` starting at the `index`th instance of the `range`
+    pub fn write(&mut self, range: InstanceRange, index: u32, instances: &[InstanceUniform]) {
+        let start = range.start + index;
+        let end = start + instances.len() as u32;
+        self.data[start as usize..end as usize].copy_from_slice(instances);
+        self.dirty = Some(match self.dirty {
+            Some((dirty_start, dirty_end)) => (dirty_start.min(start), dirty_end.max(end)),
+            None => (start, end),
+        });
+    }
+
+    // Uploads the changed range into the `instances` buffer
+    pub fn upload(&mut self, renderer: &Renderer, storage: &RenderStorage, instances: &Instances) {
+        let Some((start, end)) = self.dirty.take() else {
+            return;
+        };
+        let range = InstanceRange {
+            start,
+            num: end - start,
+        };
+        instances.instance_buffer_handle.update(
+            renderer,
+            storage,
+            range.offset(),
+            &self.data[start as usize..end as usize],
+        );
+        self.writes += 1;
+    }
+}
+
+// Hands out consecutive ranges of instances. Ranges are
+// never moved, so objects can keep them for their lifetime.
+#[derive(Debug, Default)]
+pub struct InstanceAllocator {
+    allocated: u32,
+}
+
+impl InstanceAllocator {
+    pub fn allocate(&mut self, num: u32) -> InstanceRange {
+        let range = InstanceRange {
+            start: self.allocated,
+            num,
+        };
+        self.allocated += num;
+        range
+    }
+
+    // Total number of allocated instances
+    #[inline]
+    pub fn allocated(&self) -> u32 {
+        self.allocated
+    }
+}
+
+// Shape parameters of a mesh. Floats are kept as bits,
+// so shapes can be used as keys of the `MeshCache`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MeshKey {
+    Quad { width: u32, height: u32 },
+    Circle { radius: u32, segments: u32 },
+}
+
+impl MeshKey {
+    // Quad used by the border, the platform, crates, power-ups,
+    // particles and the HUD, scaled by their transforms
+    pub const UNIT_QUAD: Self = Self::quad(1.0, 1.0);
+
+    pub const fn quad(width: f32, height: f32) -> Self {
+        Self::Quad {
+            width: width.to_bits(),
+            height: height.to_bits(),
+        }
+    }
+
+    pub const fn circle(radius: f32, segments: u32) -> Self {
+        Self::Circle {
+            radius: radius.to_bits(),
+            segments,
+        }
+    }
+
+    fn mesh(&self) -> Mesh {
+        match *self {
+            Self::Quad { width, height } => {
+                Quad::new(f32::from_bits(width), f32::from_bits(height)).into()
+            }
+            Self::Circle { radius, segments } => {
+                Circle::new(f32::from_bits(radius), segments).into()
+            }
+        }
+    }
+}
+
+// Meshes inserted into the storage, so objects with the
+// same shape share a single mesh instead of building their own
+#[derive(Default)]
+pub struct MeshCache {
+    meshes: HashMap<MeshKey, ResourceId>,
+}
+
+impl MeshCache {
+    // Builds and inserts the mesh on the first request of the `key`
+    pub fn get_or_insert(
+        &mut self,
+        renderer: &Renderer,
+        storage: &mut RenderStorage,
+        key: MeshKey,
+    ) -> ResourceId {
+        *self
+            .meshes
+            .entry(key)
+            .or_insert_with(|| storage.insert_mesh(key.mesh().build(renderer)))
+    }
+}
+
 pub struct Instances {
     pub mesh_id: ResourceId,
     pub instance_buffer_handle: InstanceBufferHandle,
     pub instance_num: u32,
+    pub capacity: u32,
 }
 
 impl Instances {
-    pub fn new<M: Into<Mesh>>(
+    // Mesh is taken from the `MeshCache`
+    pub fn new(
         renderer: &Renderer,
         storage: &mut RenderStorage,
-        mesh: M,
+        mesh_id: ResourceId,
         num: u32,
     ) -> Self {
-        let mesh: Mesh = mesh.into();
-        let mesh_id = storage.insert_mesh(mesh.build(renderer));
-
-        let instance_buffer = InstancesBuffer {
-            size: num as u64 * std::mem::size_of::<InstanceUniform>() as u64,
-        };
-        let instance_buffer_resource = instance_buffer.build(renderer);
-        let instance_buffer_handle = InstanceBufferHandle::new(storage, instance_buffer_resource);
+        let instance_buffer_handle = Self::build_buffer(renderer, storage, num);
         Self {
             mesh_id,
             instance_buffer_handle,
             instance_num: num,
+            capacity: num,
         }
     }
 
+    fn build_buffer(
+        renderer: &Renderer,
+        storage: &mut RenderStorage,
+        capacity: u32,
+    ) -> InstanceBufferHandle {
+        let instance_buffer = InstancesBuffer {
+            size: capacity as u64 * std::mem::size_of::<InstanceUniform>() as u64,
+        };
+        let instance_buffer_resource = instance_buffer.build(renderer);
+        InstanceBufferHandle::new(storage, instance_buffer_resource)
+    }
+
+    // Sets the number of drawn instances to `needed`. If the buffer is too small
+    // for it, reallocates it with at least double the capacity and copies
+    // the old instances into the new buffer.
+    pub fn ensure_capacity(
+        &mut self,
+        renderer: &Renderer,
+        storage: &mut RenderStorage,
+        needed: u32,
+    ) {
+        if self.capacity < needed {
+            let capacity = needed.max(self.capacity * 2);
+            let new_handle = Self::build_buffer(renderer, storage, capacity);
+
+            let mut encoder = renderer.create_encoder();
+            encoder.copy_buffer_to_buffer(
+                storage.get_buffer(self.instance_buffer_handle.buffer_id),
+                0,
+                storage.get_buffer(new_handle.buffer_id),
+                0,
+                self.capacity as u64 * std::mem::size_of::<InstanceUniform>() as u64,
+            );
+            renderer.submit(std::iter::once(encoder.finish()));
+
+            self.instance_buffer_handle = new_handle;
+            self.capacity = capacity;
+        }
+        self.instance_num = needed;
+    }
+
     pub fn render_command(
         &self,
         pipeline_id: ResourceId,
@@ -128,9 +358,24 @@ impl Instances {
             mesh_id: self.mesh_id,
             instance_buffer_id: self.instance_buffer_handle.buffer_id,
             camera_bind_group,
+            texture_bind_group: None,
             instance_num: self.instance_num,
         }
     }
+
+    // Same as `render_command` for the textured pipeline,
+    // which samples the texture of the `texture_bind_group`
+    pub fn textured_render_command(
+        &self,
+        pipeline_id: ResourceId,
+        camera_bind_group: ResourceId,
+        texture_bind_group: ResourceId,
+    ) -> InstancesRenderCommand {
+        InstancesRenderCommand {
+            texture_bind_group: Some(texture_bind_group),
+            ..self.render_command(pipeline_id, camera_bind_group)
+        }
+    }
 }
 
 pub struct InstancesRenderCommand {
@@ -138,6 +383,7 @@ pub struct InstancesRenderCommand {
     pub mesh_id: ResourceId,
     pub instance_buffer_id: ResourceId,
     pub camera_bind_group: ResourceId,
+    pub texture_bind_group: Option<ResourceId>,
     pub instance_num: u32,
 }
 
@@ -145,6 +391,9 @@ impl RenderCommand for InstancesRenderCommand {
     fn execute<'a>(&self, render_pass: &mut RenderPass<'a>, storage: &'a CurrentFrameStorage) {
         render_pass.set_pipeline(storage.get_pipeline(self.pipeline_id));
         render_pass.set_bind_group(0, storage.get_bind_group(self.camera_bind_group), &[]);
+        if let Some(texture_bind_group) = self.texture_bind_group {
+            render_pass.set_bind_group(1, storage.get_bind_group(texture_bind_group), &[]);
+        }
 
         let mesh = storage.get_mesh(self.mesh_id);
         render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
@@ -156,3 +405,201 @@ impl RenderCommand for InstancesRenderCommand {
         render_pass.draw_indexed(0..mesh.num_elements, 0, 0..self.instance_num);
     }
 }
+
+// Centered part of the window everything is drawn into, in physical
+// pixels with the origin in the top left corner. A fixed aspect ratio
+// leaves black bars on the sides or at the top and bottom.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Viewport {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Viewport {
+    // Largest rectangle with the width to height `aspect` fitting into
+    // the window, or the whole window without one
+    pub fn fit(window_width: u32, window_height: u32, aspect: Option<f32>) -> Self {
+        let (width, height) = match aspect.filter(|aspect| aspect.is_normal() && 0.0 < *aspect) {
+            Some(aspect) => {
+                let width = (window_height as f32 * aspect).round() as u32;
+                if width <= window_width {
+                    (width, window_height)
+                } else {
+                    (window_width, (window_width as f32 / aspect).round() as u32)
+                }
+            }
+            None => (window_width, window_height),
+        };
+        let width = width.max(1);
+        let height = height.max(1);
+        Self {
+            x: window_width.saturating_sub(width) / 2,
+            y: window_height.saturating_sub(height) / 2,
+            width,
+            height,
+        }
+    }
+
+    #[inline]
+    pub fn aspect(&self) -> f32 {
+        self.width as f32 / self.height as f32
+    }
+
+    // Converts a `position` in window coordinates into one relative
+    // to the top left corner of the viewport
+    #[inline]
+    pub fn to_local(&self, position: Vector2<f32>) -> Vector2<f32> {
+        Vector2::new(position.x - self.x as f32, position.y - self.y as f32)
+    }
+
+    // Limits drawing of the `render_pass` to the viewport
+    pub fn apply(&self, render_pass: &mut RenderPass) {
+        render_pass.set_viewport(
+            self.x as f32,
+            self.y as f32,
+            self.width as f32,
+            self.height as f32,
+            0.0,
+            1.0,
+        );
+        render_pass.set_scissor_rect(self.x, self.y, self.width, self.height);
+    }
+}
+
+// Color and depth textures the scene is drawn into. With more than one
+// sample the scene is drawn into a multisampled color texture which is
+// resolved into the surface texture at the end of the render pass.
+pub struct RenderTargets {
+    sample_count: u32,
+    color: Option<TextureView>,
+    depth: TextureView,
+}
+
+impl RenderTargets {
+    pub const DEPTH_FORMAT: TextureFormat = TextureFormat::Depth32Float;
+
+    // Falls back to a single sample if the adapter
+    // does not support the requested `sample_count`
+    pub fn new(renderer: &Renderer, width: u32, height: u32, sample_count: u32) -> Self {
+        let supported = renderer
+            .adapter()
+            .get_texture_format_features(renderer.surface_format())
+            .flags
+            .sample_count_supported(sample_count);
+        let sample_count = if supported {
+            sample_count
+        } else {
+            eprintln!("{sample_count}x MSAA is not supported, falling back to 1x");
+            1
+        };
+
+        let (color, depth) = Self::build_textures(renderer, width, height, sample_count);
+        Self {
+            sample_count,
+            color,
+            depth,
+        }
+    }
+
+    #[inline]
+    pub fn sample_count(&self) -> u32 {
+        self.sample_count
+    }
+
+    pub fn resize(&mut self, renderer: &Renderer, width: u32, height: u32) {
+        (self.color, self.depth) = Self::build_textures(renderer, width, height, self.sample_count);
+    }
+
+    fn build_textures(
+        renderer: &Renderer,
+        width: u32,
+        height: u32,
+        sample_count: u32,
+    ) -> (Option<TextureView>, TextureView) {
+        let build = |label, format| {
+            renderer
+                .device()
+                .create_texture(&TextureDescriptor {
+                    label: Some(label),
+                    size: Extent3d {
+                        width: width.max(1),
+                        height: height.max(1),
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count,
+                    dimension: TextureDimension::D2,
+                    format,
+                    usage: TextureUsages::RENDER_ATTACHMENT,
+                    view_formats: &[],
+                })
+                .create_view(&TextureViewDescriptor::default())
+        };
+        let color = (1 < sample_count).then(|| build("msaa_color", renderer.surface_format()));
+        let depth = build("depth", Self::DEPTH_FORMAT);
+        (color, depth)
+    }
+
+    // Render pass which clears both targets and ends up in the `surface_view`
+    pub fn render_pass<'a>(
+        &'a self,
+        encoder: &'a mut CommandEncoder,
+        surface_view: &'a TextureView,
+    ) -> RenderPass<'a> {
+        let (view, resolve_target) = match &self.color {
+            Some(color) => (color, Some(surface_view)),
+            None => (surface_view, None),
+        };
+        encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("scene_render_pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view,
+                resolve_target,
+                ops: Operations {
+                    // Also the color of letterboxing bars, see `Viewport`
+                    load: LoadOp::Clear(Color::BLACK),
+                    // Only the resolved surface texture is needed
+                    store: if resolve_target.is_some() {
+                        StoreOp::Discard
+                    } else {
+                        StoreOp::Store
+                    },
+                },
+            })],
+            depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
+                view: &self.depth,
+                depth_ops: Some(Operations {
+                    load: LoadOp::Clear(1.0),
+                    store: StoreOp::Discard,
+                }),
+                stencil_ops: None,
+            }),
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn minimized_window_gives_a_finite_aspect() {
+        for aspect in [None, Some(0.75)] {
+            let viewport = Viewport::fit(0, 0, aspect);
+            assert!(viewport.aspect().is_normal());
+        }
+    }
+
+    #[test]
+    fn viewport_is_centered_in_a_wider_window() {
+        let viewport = Viewport::fit(1600, 900, Some(1.0));
+        assert_eq!(viewport.width, 900);
+        assert_eq!(viewport.height, 900);
+        assert_eq!(viewport.x, 350);
+        assert_eq!(viewport.y, 0);
+    }
+}
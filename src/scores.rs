@@ -0,0 +1,246 @@
+use std::{io::Write, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug)]
+pub enum ScoresError {
+    Io(std::io::Error),
+    Serialize(ron::Error),
+}
+
+impl From<std::io::Error> for ScoresError {
+    fn from(value: std::io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+impl From<ron::Error> for ScoresError {
+    fn from(value: ron::Error) -> Self {
+        Self::Serialize(value)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScoreEntry {
+    pub score: u32,
+    #[serde(default)]
+    pub name: Option<String>,
+}
+
+// Best scores sorted from the highest, stored as RON
+// in the platform config directory
+#[derive(Debug, Default)]
+pub struct HighScores {
+    entries: Vec<ScoreEntry>,
+    // Scores are only kept in memory if there is no place for the file
+    path: Option<PathBuf>,
+}
+
+impl HighScores {
+    pub const MAX_ENTRIES: usize = 10;
+    const FILE_NAME: &'static str = "scores.ron";
+
+    // Missing or corrupt files are not an error,
+    // scores just start from scratch
+    pub fn load() -> Self {
+        let path = directories::ProjectDirs::from("", "", "breakout_zero")
+            .map(|dirs| dirs.config_dir().join(Self::FILE_NAME));
+        let mut entries = path
+            .as_ref()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|scores| match ron::from_str::<Vec<ScoreEntry>>(&scores) {
+                Ok(entries) => Some(entries),
+                Err(e) => {
+                    eprintln!("Ignoring corrupt high scores: {e}");
+                    None
+                }
+            })
+            .unwrap_or_default();
+        entries.sort_by(|a, b| b.score.cmp(&a.score));
+        entries.truncate(Self::MAX_ENTRIES);
+        Self { entries, path }
+    }
+
+    #[inline]
+    pub fn entries(&self) -> &[ScoreEntry] {
+        &self.entries
+    }
+
+    // Zero scores never make it to the list
+    pub fn qualifies(&self, score: u32) -> bool {
+        0 < score
+            && (self.entries.len() < Self::MAX_ENTRIES
+                || self.entries.last().map_or(true, |last| last.score < score))
+    }
+
+    // Inserts the entry if it qualifies, later entries go
+    // after earlier ones with the same score.
+    // Returns true if the entry was inserted.
+    pub fn insert(&mut self, entry: ScoreEntry) -> bool {
+        if !self.qualifies(entry.score) {
+            return false;
+        }
+        let index = self.entries.partition_point(|e| entry.score <= e.score);
+        self.entries.insert(index, entry);
+        self.entries.truncate(Self::MAX_ENTRIES);
+        true
+    }
+
+    pub fn save(&self) -> Result<(), ScoresError> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let scores = ron::ser::to_string_pretty(&self.entries, Default::default())?;
+        std::fs::write(path, scores)?;
+        Ok(())
+    }
+}
+
+// Result of a game that was won or lost
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct FinishedGame {
+    pub score: u32,
+    // Index of the last level played counting from 0,
+    // 0 if the game was not played with a level set
+    pub level: usize,
+    // Seconds of play, without pauses and countdowns
+    pub duration: f32,
+    // Seed replaying the game together with the same config and levels
+    pub seed: u64,
+    pub won: bool,
+}
+
+// Receives every finished game, for example to post
+// its score to a leaderboard server or a local file
+pub trait ScoreSink {
+    fn submit(&mut self, game: &FinishedGame);
+}
+
+// Drops all scores, used unless the game is given another sink
+pub struct NoScoreSink;
+
+impl ScoreSink for NoScoreSink {
+    fn submit(&mut self, _game: &FinishedGame) {}
+}
+
+// Appends every finished game as a line of RON to the file at the `path`.
+// Failures are reported and the game is not logged.
+pub struct ScoreLog {
+    pub path: PathBuf,
+}
+
+impl ScoreLog {
+    fn append(&self, game: &FinishedGame) -> Result<(), ScoresError> {
+        let line = ron::to_string(game)?;
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{line}")?;
+        Ok(())
+    }
+}
+
+impl ScoreSink for ScoreLog {
+    fn submit(&mut self, game: &FinishedGame) {
+        if let Err(e) = self.append(game) {
+            eprintln!("Could not log the score to {}: {e:?}", self.path.display());
+        }
+    }
+}
+
+// Submits the finished game to the sink only once, even if
+// the game is finished again before the next one starts
+pub struct ScoreReporter {
+    sink: Box<dyn ScoreSink>,
+    submitted: bool,
+}
+
+impl Default for ScoreReporter {
+    fn default() -> Self {
+        Self::new(Box::new(NoScoreSink))
+    }
+}
+
+impl ScoreReporter {
+    pub fn new(sink: Box<dyn ScoreSink>) -> Self {
+        Self {
+            sink,
+            submitted: false,
+        }
+    }
+
+    // Called whenever a game starts or continues
+    pub fn start(&mut self) {
+        self.submitted = false;
+    }
+
+    pub fn finish(&mut self, game: &FinishedGame) {
+        if !self.submitted {
+            self.submitted = true;
+            self.sink.submit(game);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use zero::prelude::*;
+
+    use super::*;
+    use crate::{
+        config::GameConfig,
+        rendering::InstanceAllocator,
+        simulation::{Input, Simulation},
+    };
+
+    struct MockSink(Rc<RefCell<Vec<FinishedGame>>>);
+
+    impl ScoreSink for MockSink {
+        fn submit(&mut self, game: &FinishedGame) {
+            self.0.borrow_mut().push(*game);
+        }
+    }
+
+    #[test]
+    fn game_over_submits_the_final_score_once() {
+        let submitted = Rc::new(RefCell::new(vec![]));
+        let mut reporter = ScoreReporter::new(Box::new(MockSink(submitted.clone())));
+        let config = GameConfig {
+            starting_lives: 1,
+            power_up_chance: 0.0,
+            ..Default::default()
+        };
+        let mut simulation = Simulation::new(&config, None, 7, &mut InstanceAllocator::default());
+        simulation.crate_pack.hit(0);
+        simulation.step(1.0 / 120.0, &Input::default());
+        simulation.balls.clear();
+        simulation.spawn_ball(Vector3::new(0.0, -9.5, 0.0), Vector2::new(0.0, -2.5));
+
+        // Finishing is checked after every step, like the game does
+        for _ in 0..10 {
+            simulation.step(1.0 / 120.0, &Input::default());
+            if simulation.is_lost() {
+                reporter.finish(&FinishedGame {
+                    score: simulation.score(),
+                    level: 0,
+                    duration: simulation.play_time(),
+                    seed: simulation.seed(),
+                    won: false,
+                });
+            }
+        }
+
+        assert!(simulation.is_lost());
+        let submitted = submitted.borrow();
+        assert_eq!(submitted.len(), 1);
+        assert_eq!(submitted[0].score, 10);
+        assert_eq!(submitted[0].seed, 7);
+        assert!(0.0 < submitted[0].duration);
+    }
+}
@@ -0,0 +1,238 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::GameConfig;
+
+#[derive(Debug)]
+pub enum SettingsError {
+    Io(std::io::Error),
+    Serialize(ron::Error),
+}
+
+impl From<std::io::Error> for SettingsError {
+    fn from(value: std::io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+impl From<ron::Error> for SettingsError {
+    fn from(value: ron::Error) -> Self {
+        Self::Serialize(value)
+    }
+}
+
+// Preset of the config values which make the game easier or harder
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+}
+
+impl Difficulty {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Easy => "EASY",
+            Self::Normal => "NORMAL",
+            Self::Hard => "HARD",
+        }
+    }
+
+    // Next harder preset, wrapping around to the easiest one
+    pub fn next(&self) -> Self {
+        match self {
+            Self::Easy => Self::Normal,
+            Self::Normal => Self::Hard,
+            Self::Hard => Self::Easy,
+        }
+    }
+
+    // Next easier preset, wrapping around to the hardest one
+    pub fn prev(&self) -> Self {
+        match self {
+            Self::Easy => Self::Hard,
+            Self::Normal => Self::Easy,
+            Self::Hard => Self::Normal,
+        }
+    }
+
+    // Normal matches the default config. Levels
+    // keep their own ball speed and platform width.
+    pub fn apply(&self, config: &mut GameConfig) {
+        let (starting_lives, ball_speed, platform_width, power_up_chance) = match self {
+            Self::Easy => (5, 0.8, 2.5, 0.25),
+            Self::Normal => (3, 1.0, 2.0, 0.15),
+            Self::Hard => (2, 1.3, 1.5, 0.1),
+        };
+        config.starting_lives = starting_lives;
+        config.ball.speed = ball_speed;
+        config.platform.width = platform_width;
+        config.power_up_chance = power_up_chance;
+    }
+}
+
+// Actions of the keys which can be remapped
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Left,
+    Right,
+    SuperShot,
+}
+
+impl Action {
+    pub const ALL: [Self; 3] = [Self::Left, Self::Right, Self::SuperShot];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Left => "LEFT",
+            Self::Right => "RIGHT",
+            Self::SuperShot => "SUPER SHOT",
+        }
+    }
+}
+
+// Character keys of the remappable actions, stored in lower case.
+// Arrow keys always move the platform as well.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyBindings {
+    pub left: String,
+    pub right: String,
+    pub super_shot: String,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            left: "a".to_string(),
+            right: "d".to_string(),
+            super_shot: "w".to_string(),
+        }
+    }
+}
+
+impl KeyBindings {
+    #[inline]
+    pub fn key(&self, action: Action) -> &str {
+        match action {
+            Action::Left => &self.left,
+            Action::Right => &self.right,
+            Action::SuperShot => &self.super_shot,
+        }
+    }
+
+    // Action bound to the character `key`, if any
+    pub fn action(&self, key: &str) -> Option<Action> {
+        Action::ALL
+            .into_iter()
+            .find(|&action| self.key(action).eq_ignore_ascii_case(key))
+    }
+
+    // Only single characters can be bound. An action which already
+    // uses the `key` gets the previous key of the `action` instead,
+    // so no key is ever bound twice. Returns false if the `key` is ignored.
+    pub fn bind(&mut self, action: Action, key: &str) -> bool {
+        if key.chars().count() != 1 {
+            return false;
+        }
+        let key = key.to_lowercase();
+        let previous = self.key(action).to_string();
+        if let Some(other) = self.action(&key) {
+            *self.key_mut(other) = previous;
+        }
+        *self.key_mut(action) = key;
+        true
+    }
+
+    #[inline]
+    fn key_mut(&mut self, action: Action) -> &mut String {
+        match action {
+            Action::Left => &mut self.left,
+            Action::Right => &mut self.right,
+            Action::SuperShot => &mut self.super_shot,
+        }
+    }
+
+    // Bindings which are not single characters or bind
+    // the same key twice are replaced by the defaults
+    fn is_valid(&self) -> bool {
+        Action::ALL.iter().all(|&action| {
+            self.key(action).chars().count() == 1 && self.action(self.key(action)) == Some(action)
+        })
+    }
+}
+
+// Choices of the options screen, stored as RON in the platform
+// config directory. Saved when the game exits.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    // Master volume in [0, 1]
+    pub volume: f32,
+    pub difficulty: Difficulty,
+    pub bindings: KeyBindings,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            volume: 1.0,
+            difficulty: Difficulty::default(),
+            bindings: KeyBindings::default(),
+        }
+    }
+}
+
+impl Settings {
+    const FILE_NAME: &'static str = "settings.ron";
+    // Volume changes by this much with each step in the options
+    pub const VOLUME_STEP: f32 = 0.1;
+
+    fn path() -> Option<PathBuf> {
+        directories::ProjectDirs::from("", "", "breakout_zero")
+            .map(|dirs| dirs.config_dir().join(Self::FILE_NAME))
+    }
+
+    // Missing or corrupt files are not an error, the defaults are used
+    // instead. Values out of their range are fixed up.
+    pub fn load() -> Self {
+        let mut settings = Self::path()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|settings| match ron::from_str::<Settings>(&settings) {
+                Ok(settings) => Some(settings),
+                Err(e) => {
+                    eprintln!("Ignoring corrupt settings: {e}");
+                    None
+                }
+            })
+            .unwrap_or_default();
+        settings.set_volume(settings.volume);
+        if !settings.bindings.is_valid() {
+            eprintln!("Ignoring invalid key bindings: {:?}", settings.bindings);
+            settings.bindings = KeyBindings::default();
+        }
+        settings
+    }
+
+    pub fn save(&self) -> Result<(), SettingsError> {
+        let Some(path) = Self::path() else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let settings = ron::ser::to_string_pretty(self, Default::default())?;
+        std::fs::write(path, settings)?;
+        Ok(())
+    }
+
+    // Volume is clamped to [0, 1], anything not a number mutes the game
+    pub fn set_volume(&mut self, volume: f32) {
+        self.volume = if volume.is_nan() {
+            0.0
+        } else {
+            volume.clamp(0.0, 1.0)
+        };
+    }
+}
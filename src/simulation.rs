@@ -0,0 +1,1507 @@
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use zero::prelude::*;
+
+use crate::{
+    ball::{resolve_ball_collisions, Ball, BallState, Hit},
+    border::{Border, Wall},
+    charge::ChargeMeter,
+    config::{BallConfig, GameConfig},
+    crates::{CrateKind, CratePack, CratePackState},
+    laser::{ColumnLaser, ColumnLaserState},
+    level::{Level, SpeedCurve},
+    physics::{Collider, GravityWell, Rectangle},
+    platform::{Platform, PlatformState},
+    powerup::{PowerUp, PowerUpKind, PowerUpState},
+    rendering::{InstanceAllocator, InstanceUniform},
+    theme::Theme,
+};
+
+// Platform width is multiplied by this value when
+// the power-up is caught, up to the maximum width
+const WIDE_PADDLE_FACTOR: f32 = 1.5;
+const MAX_PADDLE_WIDTH: f32 = 6.0;
+// Ball speed is multiplied by this value when
+// the power-up is caught, down to the minimum speed
+const SLOW_BALL_FACTOR: f32 = 0.75;
+const MIN_BALL_SPEED: f32 = 0.5;
+
+// Crates this close to each other count as one cluster for homing balls
+const HOMING_CLUSTER_RADIUS: f32 = 3.0;
+
+// Angle in degrees between the balls split by the multi ball power-up
+const MULTI_BALL_SPREAD: f32 = 30.0;
+// Degrees stalled balls are turned by either way
+const STALL_NUDGE_MIN: f32 = 5.0;
+const STALL_NUDGE_MAX: f32 = 15.0;
+// Platform color while the controls are inverted
+const INVERTED_CONTROLS_TINT: [f32; 4] = [0.6, 0.2, 0.9, 1.0];
+// Platform color while it is frozen, over the inverted controls one
+const FROZEN_PADDLE_TINT: [f32; 4] = [0.6, 0.9, 1.0, 1.0];
+
+// Player input for a single simulation step. It can come from
+// the keyboard, a gamepad, a bot or a recorded replay.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Input {
+    // Platform movement in [-1, 1], positive moves right
+    pub movement: f32,
+    pub launch: bool,
+    pub super_shot: bool,
+}
+
+// Something that happened during a simulation step
+// which the game may want to react to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GameEvent {
+    CrateDestroyed {
+        points: u32,
+        pos: Vector2<f32>,
+        color: [f32; 4],
+    },
+    // Crate was hit, but still has health left
+    CrateHit {
+        pos: Vector2<f32>,
+    },
+    // Ball fell out of play
+    BallLost,
+    WallHit {
+        wall: Wall,
+    },
+    PaddleHit,
+    // Last crate was destroyed
+    LevelCleared,
+    // Life was granted by the score or a power-up
+    ExtraLife,
+}
+
+// Everything that changes during a game. Combined with the config,
+// the level and the seed it continues the game where it was saved.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SimulationState {
+    pub balls: Vec<BallState>,
+    pub platform: PlatformState,
+    pub crate_pack: CratePackState,
+    pub power_ups: Vec<PowerUpState>,
+    pub charge: u32,
+    pub lives: u32,
+    pub next_extra_life: u32,
+    pub score: u32,
+    pub combo: u32,
+    // Missing in saves from before the play time was kept
+    #[serde(default)]
+    pub play_time: f32,
+    // Missing in saves from before the inverted controls debuff
+    #[serde(default)]
+    pub inverted_controls: f32,
+    // Missing in saves from before the stall watchdog
+    #[serde(default)]
+    pub since_progress: f32,
+    // Missing in saves from before the column laser
+    #[serde(default)]
+    pub column_laser: ColumnLaserState,
+    // Random number generator continues from this seed
+    pub rng_seed: u64,
+}
+
+// Game objects and rules without any rendering,
+// so it can be stepped without a window or a GPU
+pub struct Simulation {
+    pub border: Border,
+    pub balls: Vec<Ball>,
+    pub platform: Platform,
+    pub crate_pack: CratePack,
+    pub power_ups: Vec<PowerUp>,
+    pub gravity_wells: Vec<GravityWell>,
+    pub charge_meter: ChargeMeter,
+    pub column_laser: Option<ColumnLaser>,
+
+    // Events of the last step
+    pub events: Vec<GameEvent>,
+
+    // Ball speed is already overridden by the level
+    ball: BallConfig,
+    ball_color: [f32; 4],
+    // Sprite of new balls in the texture atlas
+    ball_sprite: [f32; 4],
+    // Crates of levels and images keep their own colors
+    // instead of taking them from the theme
+    themed_crates: bool,
+    // Speeds balls up as crates are destroyed
+    speed_curve: Option<SpeedCurve>,
+    rainbow: bool,
+    // Chance of a destroyed crate to drop a power-up
+    power_up_chance: f32,
+    // Units per second power-ups drift towards the platform with
+    power_up_magnet: f32,
+    // Width of the platform at the start, restored after a lost life
+    // if the platform shrinks with every returned ball
+    paddle_width: f32,
+    paddle_shrink_per_hit: f32,
+    min_paddle_width: f32,
+
+    lives: u32,
+    max_lives: u32,
+    extra_life_every: Option<u32>,
+    // Score at which the next bonus life is granted
+    next_extra_life: u32,
+    score: u32,
+    // Crates destroyed since a ball last touched the platform.
+    // Each destroyed crate gives its points times the combo.
+    combo: u32,
+    // Seconds stepped so far, kept over the levels of a set
+    play_time: f32,
+    // Seconds left until the controls stop being inverted
+    inverted_controls: f32,
+    inverted_controls_duration: f32,
+    paddle_freeze_duration: f32,
+    stall_timeout: Option<f32>,
+    // Seconds since the last destroyed crate or platform hit
+    since_progress: f32,
+
+    // All random decisions are drawn from it, so simulations
+    // with the same seed and inputs play out the same
+    rng: SmallRng,
+    seed: u64,
+}
+
+impl Simulation {
+    // Maximum number of power-ups falling at the same time
+    pub const MAX_POWER_UPS: u32 = 16;
+
+    // Builds the `level` or the crate grid from the `config` if there is none.
+    // Crate images which can not be used are reported and replaced by the
+    // crate grid. Instances for the border, the platform and the crates
+    // are taken from the `instance_allocator`.
+    pub fn new(
+        config: &GameConfig,
+        level: Option<&Level>,
+        seed: u64,
+        instance_allocator: &mut InstanceAllocator,
+    ) -> Self {
+        let (width, height) = level.map_or((config.border.width, config.border.height), |level| {
+            (level.width, level.height)
+        });
+        let border = Border::new(
+            width,
+            height,
+            config.border.thickness,
+            config.theme.border,
+            config.theme.inner,
+            config.border.walls,
+            config.border.deadly_bottom,
+            instance_allocator,
+        );
+
+        let mut platform = Platform::new(
+            config.platform.position.into(),
+            level.map_or(config.platform.width, |level| level.paddle_width),
+            config.platform.height,
+            config.theme.platform,
+            config.platform.speed,
+            config.platform.accel,
+            config.platform.friction,
+            instance_allocator,
+        );
+        platform.set_rotation(config.platform.rotation.to_radians());
+
+        let crates = &config.crates;
+        let row_points = |row| crates.points_per_row * (row + 1);
+        let color = |_, row| config.theme.crate_color(row);
+        let image_pack = match (level, &crates.image) {
+            (None, Some(path)) => match CratePack::from_image(
+                path,
+                crates.center.into(),
+                crates.width,
+                crates.height,
+                crates.gap_x,
+                crates.gap_y,
+                row_points,
+                crates.rows * crates.cols,
+                instance_allocator,
+            ) {
+                Ok(crate_pack) => Some(crate_pack),
+                Err(e) => {
+                    eprintln!("Could not build crates from {path}, using the crate grid: {e:?}");
+                    None
+                }
+            },
+            _ => None,
+        };
+        // Crates of levels and images keep their own colors
+        let themed_crates = level.is_none() && image_pack.is_none();
+        let mut crate_pack = match (level, image_pack, &crates.mask) {
+            (Some(level), _, _) => CratePack::from_level(level, instance_allocator),
+            (None, Some(crate_pack), _) => crate_pack,
+            (None, None, Some(mask)) => CratePack::from_mask(
+                crates.center.into(),
+                crates.width,
+                crates.height,
+                crates.gap_x,
+                crates.gap_y,
+                mask,
+                color,
+                row_points,
+                instance_allocator,
+            ),
+            (None, None, None) => CratePack::new(
+                crates.center.into(),
+                crates.rows,
+                crates.cols,
+                crates.width,
+                crates.height,
+                crates.gap_x,
+                crates.gap_y,
+                color,
+                row_points,
+                |_, row| crates.row_health.get(row as usize).copied().unwrap_or(1),
+                |_, row| {
+                    crates
+                        .row_kinds
+                        .get(row as usize)
+                        .copied()
+                        .unwrap_or_default()
+                },
+                instance_allocator,
+            ),
+        };
+
+        let mut ball = config.ball.clone();
+        if let Some(level) = level {
+            ball.speed = level.ball_speed;
+        }
+
+        let textures = config.textures.as_ref();
+        if let Some(textures) = textures {
+            crate_pack.set_sprite(textures.crate_sprite);
+        }
+
+        let paddle_width = platform.width();
+        let mut simulation = Self {
+            border,
+            balls: vec![],
+            platform,
+            crate_pack,
+            power_ups: vec![],
+            gravity_wells: level.map_or(config.gravity_wells.clone(), |level| {
+                level
+                    .gravity_wells
+                    .iter()
+                    .copied()
+                    .map(Into::into)
+                    .collect()
+            }),
+            charge_meter: ChargeMeter::new(config.charge_capacity, config.plow_through_duration),
+            column_laser: config
+                .column_laser_interval
+                .filter(|interval| 0.0 < *interval)
+                .map(ColumnLaser::new),
+            events: vec![],
+            ball,
+            ball_color: config.theme.ball,
+            ball_sprite: textures
+                .map_or(InstanceUniform::NO_SPRITE, |textures| textures.ball_sprite),
+            themed_crates,
+            speed_curve: level.and_then(|level| level.speed_curve),
+            rainbow: config.ball.rainbow,
+            power_up_chance: config.power_up_chance,
+            power_up_magnet: config.power_up_magnet.max(0.0),
+            paddle_width,
+            paddle_shrink_per_hit: config.paddle_shrink_per_hit,
+            min_paddle_width: config.min_paddle_width,
+            lives: config.starting_lives.min(config.max_lives),
+            max_lives: config.max_lives,
+            extra_life_every: config.extra_life_every.filter(|every| 0 < *every),
+            next_extra_life: config.extra_life_every.unwrap_or(0),
+            score: 0,
+            combo: 0,
+            play_time: 0.0,
+            inverted_controls: 0.0,
+            inverted_controls_duration: config.inverted_controls_duration,
+            paddle_freeze_duration: config.paddle_freeze_duration,
+            stall_timeout: config.stall_timeout.filter(|timeout| 0.0 < *timeout),
+            since_progress: 0.0,
+            rng: SmallRng::seed_from_u64(seed),
+            seed,
+        };
+        simulation.balls.push(simulation.main_ball());
+        simulation
+    }
+
+    // The random number generator can not be saved, so it is reseeded
+    // from itself. The saved and the running simulation then draw the same
+    // numbers, but ones different from a simulation which was not saved.
+    pub fn save_state(&mut self) -> SimulationState {
+        let rng_seed = self.rng.gen();
+        self.rng = SmallRng::seed_from_u64(rng_seed);
+        SimulationState {
+            balls: self.balls.iter().map(Ball::state).collect(),
+            platform: self.platform.state(),
+            crate_pack: self.crate_pack.state(),
+            power_ups: self.power_ups.iter().map(PowerUp::state).collect(),
+            charge: self.charge_meter.charge(),
+            lives: self.lives,
+            next_extra_life: self.next_extra_life,
+            score: self.score,
+            combo: self.combo,
+            play_time: self.play_time,
+            inverted_controls: self.inverted_controls,
+            since_progress: self.since_progress,
+            column_laser: self
+                .column_laser
+                .as_ref()
+                .map_or_else(Default::default, ColumnLaser::state),
+            rng_seed,
+        }
+    }
+
+    // Simulation has to be built from the same config and level as
+    // the one the `state` was saved from, otherwise crates are mismatched
+    pub fn load_state(&mut self, state: &SimulationState) {
+        self.balls = state
+            .balls
+            .iter()
+            .map(|ball_state| {
+                let mut ball = self.new_ball(
+                    ball_state.position.into(),
+                    ball_state.spawn_velocity.into(),
+                    ball_state.speed,
+                );
+                ball.set_state(ball_state);
+                ball
+            })
+            .collect();
+        self.platform.set_state(&state.platform);
+        self.crate_pack.set_state(&state.crate_pack);
+        self.power_ups = state.power_ups.iter().map(PowerUp::from_state).collect();
+        self.charge_meter.set_charge(state.charge);
+        self.lives = state.lives.min(self.max_lives);
+        self.next_extra_life = state.next_extra_life;
+        self.score = state.score;
+        self.combo = state.combo;
+        self.play_time = state.play_time;
+        self.inverted_controls = state.inverted_controls;
+        self.since_progress = state.since_progress;
+        if let Some(laser) = self.column_laser.as_mut() {
+            laser.set_state(&state.column_laser);
+        }
+        self.rng = SmallRng::seed_from_u64(state.rng_seed);
+        self.events.clear();
+    }
+
+    // Keeps the score, the lives and the charge of the `previous`
+    // simulation, used when the next level of a set starts
+    pub fn carry_over(&mut self, previous: &Simulation) {
+        self.charge_meter.set_charge(previous.charge_meter.charge());
+        self.lives = previous.lives.min(self.max_lives);
+        self.next_extra_life = previous.next_extra_life;
+        self.score = previous.score;
+        self.play_time = previous.play_time;
+    }
+
+    // Seed of the random number generator the simulation was created with
+    #[inline]
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    #[inline]
+    pub fn lives(&self) -> u32 {
+        self.lives
+    }
+
+    #[inline]
+    pub fn score(&self) -> u32 {
+        self.score
+    }
+
+    #[inline]
+    pub fn combo(&self) -> u32 {
+        self.combo
+    }
+
+    // Seconds stepped until the game was won or lost
+    #[inline]
+    pub fn play_time(&self) -> f32 {
+        self.play_time
+    }
+
+    // Left and right input move the platform the other way while set
+    #[inline]
+    pub fn controls_inverted(&self) -> bool {
+        0.0 < self.inverted_controls
+    }
+
+    #[inline]
+    pub fn is_lost(&self) -> bool {
+        self.lives == 0
+    }
+
+    #[inline]
+    pub fn is_won(&self) -> bool {
+        self.crate_pack.all_disabled()
+    }
+
+    // Chance in [0, 1] of a destroyed crate to drop a power-up
+    pub fn set_power_up_chance(&mut self, chance: f32) {
+        self.power_up_chance = chance.clamp(0.0, 1.0);
+    }
+
+    // Bounds of the crates the column laser is about to destroy
+    pub fn laser_warning(&self) -> Option<Rectangle> {
+        let column = self.column_laser.as_ref()?.target()?;
+        self.crate_pack.column_rect(column)
+    }
+
+    // Recolors all objects, they still have to be synced
+    pub fn set_theme(&mut self, theme: &Theme) {
+        self.border.set_colors(theme.border, theme.inner);
+        self.platform.set_color(theme.platform);
+        self.ball_color = theme.ball;
+        for ball in self.balls.iter_mut() {
+            ball.set_color(theme.ball);
+        }
+        if self.themed_crates {
+            self.crate_pack.set_row_colors(|row| theme.crate_color(row));
+        }
+    }
+
+    pub fn toggle_rainbow(&mut self) {
+        self.rainbow = !self.rainbow;
+        for ball in self.balls.iter_mut() {
+            ball.set_rainbow(self.rainbow);
+        }
+    }
+
+    // Adds a ball which is already moving with the `velocity`.
+    // It is removed once it falls out of play.
+    pub fn spawn_ball(&mut self, position: Vector3<f32>, velocity: Vector2<f32>) {
+        let speed = self.balls.first().map_or(self.ball.speed, Ball::speed);
+        self.balls.push(self.new_ball(position, velocity, speed));
+    }
+
+    fn new_ball(&self, position: Vector3<f32>, velocity: Vector2<f32>, speed: f32) -> Ball {
+        let mut ball = Ball::new(
+            position,
+            self.combo_radius(),
+            self.ball_color,
+            velocity,
+            speed,
+        );
+        ball.set_sprite(self.ball_sprite);
+        ball.set_rainbow_speed(self.ball.rainbow_speed);
+        ball.set_rainbow(self.rainbow);
+        ball.set_trail_length(self.ball.trail_length);
+        ball.set_spin_enabled(self.ball.spin);
+        ball.set_min_vertical_ratio(self.ball.min_vertical_ratio);
+        ball.set_bounce_assist(self.ball.bounce_assist);
+        ball.set_behavior(self.ball.behavior);
+        ball.set_launch_angle(self.ball.launch_angle.to_radians());
+        ball
+    }
+
+    // Ball waiting on the platform to be launched
+    fn main_ball(&self) -> Ball {
+        let mut ball = self.new_ball(
+            self.ball.position.into(),
+            self.ball.velocity.into(),
+            self.ball.speed,
+        );
+        ball.set_launch_from_platform(true);
+        ball
+    }
+
+    // Advances the simulation by `dt` seconds. `events` are cleared
+    // at the start, so they only hold events of this step.
+    // Does nothing once the simulation is won or lost.
+    pub fn step(&mut self, dt: f32, input: &Input) {
+        self.events.clear();
+
+        if self.is_won() || self.is_lost() {
+            return;
+        }
+        self.play_time += dt;
+
+        // Inverting the input covers the keyboard, gamepads and bots alike
+        if self.controls_inverted() {
+            self.platform.set_analog_movement(-input.movement);
+            self.inverted_controls = (self.inverted_controls - dt).max(0.0);
+        } else {
+            self.platform.set_analog_movement(input.movement);
+        }
+        let tint = if self.platform.is_frozen() {
+            Some(FROZEN_PADDLE_TINT)
+        } else {
+            self.controls_inverted().then_some(INVERTED_CONTROLS_TINT)
+        };
+        self.platform.set_tint(tint);
+        if input.launch {
+            let jitter = self.ball.launch_jitter.abs().to_radians();
+            // Only balls waiting on the platform draw a random number,
+            // so holding launch does not change the rest of the game
+            for ball in self.balls.iter_mut().filter(|ball| !ball.is_launched()) {
+                ball.launch(self.rng.gen_range(-jitter..=jitter));
+            }
+        }
+        if input.super_shot {
+            if let Some(duration) = self.charge_meter.activate() {
+                for ball in self.balls.iter_mut() {
+                    ball.set_plow_through(duration);
+                }
+            }
+        }
+
+        self.platform.update(&self.border, dt);
+
+        let destroyed = self.crate_pack.destroyed;
+        let homing_target = if self.balls.iter().any(Ball::is_homing) {
+            self.crate_pack.densest_cluster(HOMING_CLUSTER_RADIUS)
+        } else {
+            None
+        };
+        for ball in self.balls.iter_mut() {
+            if let Some(target) = homing_target {
+                ball.steer(target, dt);
+            }
+            ball.update(
+                &self.border,
+                &self.platform,
+                &mut self.crate_pack,
+                &self.gravity_wells,
+                dt,
+            );
+            for hit in ball.hits.drain(..) {
+                let event = match hit {
+                    Hit::Border(wall) => GameEvent::WallHit { wall },
+                    Hit::Platform => GameEvent::PaddleHit,
+                    // Destroyed crates get their own event below
+                    Hit::Crate(i) => {
+                        let c = &self.crate_pack.crates[i];
+                        if c.is_disabled() {
+                            continue;
+                        }
+                        GameEvent::CrateHit { pos: c.position() }
+                    }
+                };
+                self.events.push(event);
+            }
+        }
+        resolve_ball_collisions(&mut self.balls);
+        self.charge_meter
+            .fill(self.crate_pack.destroyed - destroyed);
+
+        let destroyed_crates = std::mem::take(&mut self.crate_pack.just_destroyed);
+        for &i in destroyed_crates.iter() {
+            let c = &self.crate_pack.crates[i];
+            self.combo += 1;
+            self.score += c.points() * self.combo;
+            self.events.push(GameEvent::CrateDestroyed {
+                points: c.points(),
+                pos: c.position(),
+                color: c.color(),
+            });
+            if c.kind() == CrateKind::Freeze {
+                self.platform.freeze(self.paddle_freeze_duration);
+            }
+        }
+        // Order of hits within a step is not kept, so crates destroyed in
+        // the same step as a platform hit still count towards the combo
+        // and the platform hit resets it only after them
+        if self.events.contains(&GameEvent::PaddleHit) {
+            self.combo = 0;
+            self.shrink_paddle();
+        }
+        self.scale_balls_by_combo();
+        self.award_extra_lives();
+        if let Some(curve) = self.speed_curve {
+            // Speed never goes down because of the curve,
+            // even if the ball is already over the cap
+            let factor = curve.factor.powi(destroyed_crates.len() as i32);
+            for ball in self.balls.iter_mut() {
+                let speed = ball.speed();
+                ball.set_speed((speed * factor).min(curve.max_speed.max(speed)));
+            }
+        }
+        // Crates with a power-up hit drop exactly one on that hit
+        // and do not take a chance on being destroyed
+        let power_up_drops = std::mem::take(&mut self.crate_pack.power_up_drops);
+        for &i in destroyed_crates.iter() {
+            if self.crate_pack.crates[i].power_up_hit().is_none()
+                && self.power_ups.len() < Self::MAX_POWER_UPS as usize
+                && self.rng.gen::<f32>() < self.power_up_chance
+            {
+                self.drop_power_up(i);
+            }
+        }
+        for &i in power_up_drops.iter() {
+            self.drop_power_up(i);
+        }
+        self.fire_column_laser(dt);
+        self.update_power_ups(dt);
+        self.watch_progress(dt);
+
+        if self.is_won() {
+            self.events.push(GameEvent::LevelCleared);
+            return;
+        }
+
+        // A life is lost only when the last ball falls out of play
+        let platform_y = self.platform.position().y;
+        let balls = self.balls.len();
+        let border = &self.border;
+        self.balls
+            .retain(|ball| !border.is_out_of_play(&ball.border(), platform_y));
+        for _ in self.balls.len()..balls {
+            self.events.push(GameEvent::BallLost);
+        }
+        if self.balls.is_empty() {
+            self.lives = self.lives.saturating_sub(1);
+            self.combo = 0;
+            if 0.0 < self.paddle_shrink_per_hit {
+                self.platform.set_target_width(self.paddle_width);
+            }
+            self.balls.push(self.main_ball());
+        }
+    }
+
+    // Radius of balls with the current combo
+    fn combo_radius(&self) -> f32 {
+        let growth = (self.combo as f32 * self.ball.combo_growth).min(self.ball.max_combo_growth);
+        self.ball.radius * (1.0 + growth.max(0.0))
+    }
+
+    // Balls grown into a wall or the platform are pushed out of it
+    // on their next update
+    fn scale_balls_by_combo(&mut self) {
+        if self.ball.combo_growth <= 0.0 {
+            return;
+        }
+        let radius = self.combo_radius();
+        for ball in self.balls.iter_mut() {
+            ball.set_radius(radius);
+        }
+    }
+
+    // Narrows the platform around its center, the border pushes it back
+    // in on the next update if needed. Platforms already narrower
+    // than `min_paddle_width` keep their width.
+    fn shrink_paddle(&mut self) {
+        if self.paddle_shrink_per_hit <= 0.0 {
+            return;
+        }
+        let width = self.platform.target_width();
+        let min_width = self.min_paddle_width.min(width);
+        self.platform
+            .set_target_width((width - self.paddle_shrink_per_hit).max(min_width));
+    }
+
+    // Turns launched balls by a random angle once nothing was destroyed
+    // and the platform was not hit for the `stall_timeout`. Balls waiting
+    // on the platform are not stalled, so the timer starts over.
+    fn watch_progress(&mut self, dt: f32) {
+        let Some(timeout) = self.stall_timeout else {
+            return;
+        };
+        let progress = self.events.iter().any(|event| {
+            matches!(
+                event,
+                GameEvent::CrateDestroyed { .. } | GameEvent::PaddleHit
+            )
+        });
+        if progress || !self.balls.iter().any(Ball::is_launched) {
+            self.since_progress = 0.0;
+            return;
+        }
+        self.since_progress += dt;
+        if self.since_progress < timeout {
+            return;
+        }
+        self.since_progress = 0.0;
+        for ball in self.balls.iter_mut().filter(|ball| ball.is_launched()) {
+            let angle = self.rng.gen_range(STALL_NUDGE_MIN..=STALL_NUDGE_MAX);
+            let sign = if self.rng.gen() { 1.0 } else { -1.0 };
+            ball.nudge(sign * angle.to_radians());
+        }
+    }
+
+    // Destroys the targeted column once its warning is over. Burnt crates
+    // give no points and neither count towards the combo nor drop power-ups.
+    fn fire_column_laser(&mut self, dt: f32) {
+        let Some(laser) = self.column_laser.as_mut() else {
+            return;
+        };
+        let Some(column) = laser.update(dt, &self.crate_pack.columns(), &mut self.rng) else {
+            return;
+        };
+        self.crate_pack.destroy_column(column);
+        for i in std::mem::take(&mut self.crate_pack.just_destroyed) {
+            let c = &self.crate_pack.crates[i];
+            self.events.push(GameEvent::CrateDestroyed {
+                points: 0,
+                pos: c.position(),
+                color: c.color(),
+            });
+        }
+    }
+
+    // Drops a random power-up from the crate unless too many are falling
+    fn drop_power_up(&mut self, crate_index: usize) {
+        if Self::MAX_POWER_UPS as usize <= self.power_ups.len() {
+            return;
+        }
+        let kind = PowerUpKind::random(&mut self.rng);
+        let position = self.crate_pack.crates[crate_index].position();
+        self.power_ups.push(PowerUp::new(kind, position));
+    }
+
+    // Moves power-ups down, drifting them towards the platform with the magnet,
+    // and applies the ones caught by the platform.
+    // Power-ups that fall below the platform are removed.
+    fn update_power_ups(&mut self, dt: f32) {
+        let mut caught = vec![];
+        let platform = &self.platform;
+        let platform_x = platform.position().x;
+        let magnet = self.power_up_magnet;
+        self.power_ups.retain_mut(|power_up| {
+            power_up.update(dt, platform_x, magnet);
+            if platform.collides(power_up).is_some() {
+                caught.push(power_up.kind());
+                return false;
+            }
+            platform.border().bot() <= power_up.border().top()
+        });
+        for kind in caught {
+            self.apply_power_up(kind);
+        }
+    }
+
+    // Grants a life for each score threshold crossed since the last check
+    fn award_extra_lives(&mut self) {
+        let Some(every) = self.extra_life_every else {
+            return;
+        };
+        while self.next_extra_life <= self.score {
+            self.next_extra_life += every;
+            self.add_life();
+        }
+    }
+
+    // Lives at the maximum are not increased and emit no event
+    fn add_life(&mut self) {
+        if self.lives < self.max_lives {
+            self.lives += 1;
+            self.events.push(GameEvent::ExtraLife);
+        }
+    }
+
+    fn apply_power_up(&mut self, kind: PowerUpKind) {
+        match kind {
+            PowerUpKind::WidePaddle => {
+                let width =
+                    (self.platform.target_width() * WIDE_PADDLE_FACTOR).min(MAX_PADDLE_WIDTH);
+                self.platform.set_target_width(width);
+            }
+            PowerUpKind::SlowBall => {
+                for ball in self.balls.iter_mut() {
+                    let speed = (ball.speed() * SLOW_BALL_FACTOR).max(MIN_BALL_SPEED);
+                    ball.set_speed(speed);
+                }
+            }
+            PowerUpKind::ExtraLife => self.add_life(),
+            // Splits the first launched ball into three
+            PowerUpKind::MultiBall => {
+                let Some(ball) = self.balls.iter().find(|ball| ball.is_launched()) else {
+                    return;
+                };
+                let position = ball.position();
+                let velocity = ball.velocity();
+                for angle in [-MULTI_BALL_SPREAD, MULTI_BALL_SPREAD] {
+                    let (sin, cos) = angle.to_radians().sin_cos();
+                    let velocity = Vector2 {
+                        x: velocity.x * cos - velocity.y * sin,
+                        y: velocity.x * sin + velocity.y * cos,
+                    };
+                    self.spawn_ball(position, velocity);
+                }
+            }
+            PowerUpKind::InvertControls => {
+                self.inverted_controls = self.inverted_controls_duration;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        level::{LevelCrate, LevelGravityWell},
+        rendering::SceneBuffer,
+    };
+
+    const SEED: u64 = 7;
+    const DT: f32 = 1.0 / 120.0;
+    const CRATE_SIZE: [f32; 2] = [1.5, 1.0];
+
+    fn simulation(config: &GameConfig) -> Simulation {
+        Simulation::new(config, None, SEED, &mut InstanceAllocator::default())
+    }
+
+    #[test]
+    fn missing_crate_image_falls_back_to_the_grid() {
+        let mut config = GameConfig::default();
+        config.crates.image = Some("missing_crates.png".to_string());
+
+        let simulation = simulation(&config);
+
+        let crates = &config.crates;
+        assert_eq!(
+            simulation.crate_pack.crates.len() as u32,
+            crates.rows * crates.cols
+        );
+        assert!(simulation.themed_crates);
+    }
+
+    #[test]
+    fn gravity_wells_come_from_the_level() {
+        let config = GameConfig {
+            gravity_wells: vec![GravityWell {
+                pos: Vector2::new(0.0, 0.0),
+                radius: 1.0,
+                strength: 1.0,
+            }],
+            ..Default::default()
+        };
+        let mut level = Level::test(CRATE_SIZE, vec![LevelCrate::test([0.0, 4.0], 10)]);
+        level.gravity_wells = vec![LevelGravityWell {
+            position: [2.0, 3.0],
+            radius: 4.0,
+            strength: 5.0,
+        }];
+
+        let without_level = simulation(&config);
+        let with_level = Simulation::new(
+            &config,
+            Some(&level),
+            SEED,
+            &mut InstanceAllocator::default(),
+        );
+
+        assert_eq!(without_level.gravity_wells, config.gravity_wells);
+        assert_eq!(
+            with_level.gravity_wells,
+            vec![GravityWell {
+                pos: Vector2::new(2.0, 3.0),
+                radius: 4.0,
+                strength: 5.0,
+            }]
+        );
+    }
+
+    #[test]
+    fn inverted_controls_swap_left_and_right_until_they_run_out() {
+        let config = GameConfig {
+            inverted_controls_duration: 0.5,
+            ..Default::default()
+        };
+        let mut simulation = simulation(&config);
+        let left = Input {
+            movement: -1.0,
+            ..Default::default()
+        };
+
+        simulation.apply_power_up(PowerUpKind::InvertControls);
+        for _ in 0..10 {
+            simulation.step(DT, &left);
+        }
+        assert!(simulation.controls_inverted());
+        assert!(0.0 < simulation.platform.velocity_x());
+
+        for _ in 0..60 {
+            simulation.step(DT, &left);
+        }
+        assert!(!simulation.controls_inverted());
+        for _ in 0..60 {
+            simulation.step(DT, &left);
+        }
+        assert!(simulation.platform.velocity_x() < 0.0);
+    }
+
+    #[test]
+    fn movement_input_moves_the_platform() {
+        let config = GameConfig::default();
+        let mut moved_left = simulation(&config);
+        let mut moved_right = simulation(&config);
+        let start = moved_left.platform.position().x;
+
+        for _ in 0..30 {
+            moved_left.step(
+                DT,
+                &Input {
+                    movement: -1.0,
+                    ..Default::default()
+                },
+            );
+            moved_right.step(
+                DT,
+                &Input {
+                    movement: 1.0,
+                    ..Default::default()
+                },
+            );
+        }
+
+        assert!(moved_left.platform.position().x < start);
+        assert!(start < moved_right.platform.position().x);
+    }
+
+    // Order of the crates destroyed by the bot playing for `steps`
+    fn destroyed_crates(seed: u64, steps: u32) -> Vec<Vector2<f32>> {
+        let config = GameConfig::default();
+        let mut simulation =
+            Simulation::new(&config, None, seed, &mut InstanceAllocator::default());
+        let mut destroyed = vec![];
+        for _ in 0..steps {
+            let input = crate::bot::follow_ball(&simulation);
+            simulation.step(DT, &input);
+            destroyed.extend(simulation.events.iter().filter_map(|event| match event {
+                GameEvent::CrateDestroyed { pos, .. } => Some(*pos),
+                _ => None,
+            }));
+        }
+        destroyed
+    }
+
+    #[test]
+    fn same_seed_and_input_destroy_crates_in_the_same_order() {
+        let first = destroyed_crates(SEED, 2400);
+        let second = destroyed_crates(SEED, 2400);
+
+        assert!(!first.is_empty());
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn ball_rests_on_the_platform_until_launched() {
+        let mut simulation = simulation(&GameConfig::default());
+
+        for _ in 0..60 {
+            simulation.step(DT, &Input::default());
+        }
+        let ball = &simulation.balls[0];
+        assert!(!ball.is_launched());
+        assert!(simulation.platform.position().y < ball.position().y);
+        let resting_y = ball.position().y;
+
+        simulation.step(
+            DT,
+            &Input {
+                launch: true,
+                ..Default::default()
+            },
+        );
+        for _ in 0..60 {
+            simulation.step(DT, &Input::default());
+        }
+        let ball = &simulation.balls[0];
+        assert!(ball.is_launched());
+        assert!(resting_y < ball.position().y);
+    }
+
+    #[test]
+    fn launched_ball_hits_a_wall() {
+        let mut simulation = simulation(&GameConfig::default());
+        simulation.step(
+            DT,
+            &Input {
+                launch: true,
+                ..Default::default()
+            },
+        );
+
+        let mut wall_hit = false;
+        for _ in 0..2400 {
+            simulation.step(DT, &Input::default());
+            wall_hit |= simulation
+                .events
+                .iter()
+                .any(|event| matches!(event, GameEvent::WallHit { .. }));
+        }
+
+        assert!(wall_hit);
+    }
+
+    #[test]
+    fn lost_ball_costs_a_life() {
+        let config = GameConfig::default();
+        let mut simulation = simulation(&config);
+        simulation.balls.clear();
+        simulation.spawn_ball(Vector3::new(0.0, -9.5, 0.0), Vector2::new(0.0, -2.5));
+
+        simulation.step(DT, &Input::default());
+
+        assert!(simulation.events.contains(&GameEvent::BallLost));
+        assert_eq!(simulation.lives(), config.starting_lives - 1);
+        // A new ball waits on the platform
+        assert_eq!(simulation.balls.len(), 1);
+        assert!(!simulation.balls[0].is_launched());
+    }
+
+    #[test]
+    fn combo_multiplies_points_of_crates_destroyed_in_a_row() {
+        let config = GameConfig {
+            power_up_chance: 0.0,
+            ..Default::default()
+        };
+        let level = Level::test(
+            CRATE_SIZE,
+            vec![
+                LevelCrate::test([0.0, 2.0], 10),
+                LevelCrate::test([0.0, 3.2], 10),
+                LevelCrate::test([0.0, 4.4], 10),
+            ],
+        );
+        let mut simulation = Simulation::new(
+            &config,
+            Some(&level),
+            SEED,
+            &mut InstanceAllocator::default(),
+        );
+        simulation.balls.clear();
+        simulation.spawn_ball(Vector3::new(0.0, 0.0, 0.0), Vector2::new(0.0, 2.5));
+        simulation.balls[0].set_plow_through(10.0);
+
+        for _ in 0..600 {
+            simulation.step(DT, &Input::default());
+        }
+
+        assert!(simulation.is_won());
+        assert_eq!(simulation.combo(), 3);
+        assert_eq!(simulation.score(), 10 + 10 * 2 + 10 * 3);
+    }
+
+    #[test]
+    fn platform_hit_resets_the_combo_after_crates_of_the_same_step() {
+        let config = GameConfig {
+            power_up_chance: 0.0,
+            ..Default::default()
+        };
+        // Crate right above the platform with just enough room for the ball
+        let level = Level::test(CRATE_SIZE, vec![LevelCrate::test([0.0, -6.2], 10)]);
+        let mut simulation = Simulation::new(
+            &config,
+            Some(&level),
+            SEED,
+            &mut InstanceAllocator::default(),
+        );
+        simulation.balls.clear();
+        simulation.spawn_ball(Vector3::new(0.0, -7.22, 0.0), Vector2::new(0.0, -2.5));
+        simulation.combo = 2;
+
+        // Long enough for the ball to bounce off the platform into the crate
+        simulation.step(0.05, &Input::default());
+
+        assert!(simulation.events.contains(&GameEvent::PaddleHit));
+        assert!(simulation
+            .events
+            .iter()
+            .any(|event| matches!(event, GameEvent::CrateDestroyed { .. })));
+        assert_eq!(simulation.score(), 10 * 3);
+        assert_eq!(simulation.combo(), 0);
+    }
+
+    #[test]
+    fn super_shot_plows_through_crates_for_the_configured_duration() {
+        let config = GameConfig {
+            power_up_chance: 0.0,
+            charge_capacity: 2,
+            plow_through_duration: 0.5,
+            ..Default::default()
+        };
+        // The crate in the corner keeps the level going
+        let level = Level::test(
+            CRATE_SIZE,
+            vec![
+                LevelCrate::test([0.0, 2.0], 10),
+                LevelCrate::test([0.0, 3.2], 10),
+                LevelCrate::test([-6.0, 8.0], 10),
+            ],
+        );
+        let mut simulation = Simulation::new(
+            &config,
+            Some(&level),
+            SEED,
+            &mut InstanceAllocator::default(),
+        );
+        simulation.balls.clear();
+        simulation.spawn_ball(Vector3::new(0.0, 0.0, 0.0), Vector2::new(0.0, 2.5));
+        simulation.balls[0].set_plow_through(1.0);
+
+        for _ in 0..150 {
+            simulation.step(DT, &Input::default());
+        }
+        assert_eq!(simulation.crate_pack.destroyed, 2);
+        assert!(simulation.charge_meter.is_full());
+        assert!(!simulation.balls[0].is_plowing_through());
+
+        simulation.step(
+            DT,
+            &Input {
+                super_shot: true,
+                ..Default::default()
+            },
+        );
+        assert_eq!(simulation.charge_meter.charge(), 0);
+        for _ in 0..50 {
+            simulation.step(DT, &Input::default());
+        }
+        assert!(simulation.balls[0].is_plowing_through());
+        for _ in 0..20 {
+            simulation.step(DT, &Input::default());
+        }
+        assert!(!simulation.balls[0].is_plowing_through());
+    }
+
+    #[test]
+    fn crossing_the_extra_life_threshold_adds_one_life() {
+        let config = GameConfig {
+            extra_life_every: Some(1000),
+            ..Default::default()
+        };
+        let mut simulation = simulation(&config);
+        let lives = simulation.lives();
+
+        simulation.score = 950;
+        simulation.award_extra_lives();
+        assert_eq!(simulation.lives(), lives);
+
+        simulation.score = 1050;
+        simulation.award_extra_lives();
+        assert_eq!(simulation.lives(), lives + 1);
+        assert_eq!(
+            simulation
+                .events
+                .iter()
+                .filter(|event| matches!(event, GameEvent::ExtraLife))
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn loaded_state_plays_the_same_as_the_saved_one() {
+        let config = GameConfig::default();
+        let mut saved = simulation(&config);
+        for _ in 0..600 {
+            let input = crate::bot::follow_ball(&saved);
+            saved.step(DT, &input);
+        }
+
+        let state = ron::to_string(&saved.save_state()).unwrap();
+        // Starts from the beginning of the game until the state is loaded
+        let mut loaded = simulation(&config);
+        loaded.load_state(&ron::from_str(&state).unwrap());
+
+        for _ in 0..100 {
+            let input = crate::bot::follow_ball(&saved);
+            saved.step(DT, &input);
+            let input = crate::bot::follow_ball(&loaded);
+            loaded.step(DT, &input);
+        }
+        assert_eq!(saved.save_state(), loaded.save_state());
+    }
+
+    #[test]
+    fn switching_the_theme_updates_the_synced_platform_color() {
+        let config = GameConfig {
+            theme: Theme::classic(),
+            ..Default::default()
+        };
+        let mut allocator = InstanceAllocator::default();
+        let mut simulation = Simulation::new(&config, None, SEED, &mut allocator);
+        let mut scene = SceneBuffer::new(allocator.allocated());
+        let theme = Theme::color_blind();
+        let has_color = |scene: &SceneBuffer| {
+            scene
+                .instances()
+                .iter()
+                .any(|instance| instance.color == theme.platform)
+        };
+
+        simulation.platform.render_sync(&mut scene);
+        assert!(!has_color(&scene));
+
+        simulation.set_theme(&theme);
+        simulation.platform.render_sync(&mut scene);
+        assert!(has_color(&scene));
+    }
+
+    #[test]
+    fn returned_balls_shrink_the_paddle_down_to_the_minimum() {
+        let config = GameConfig {
+            paddle_shrink_per_hit: 0.25,
+            min_paddle_width: 1.0,
+            ..Default::default()
+        };
+        let mut simulation = simulation(&config);
+        let width = simulation.platform.width();
+
+        for _ in 0..3 {
+            simulation.shrink_paddle();
+        }
+        assert_eq!(simulation.platform.target_width(), width - 0.75);
+
+        for _ in 0..10 {
+            simulation.shrink_paddle();
+        }
+        assert_eq!(simulation.platform.target_width(), 1.0);
+        for _ in 0..60 {
+            simulation.step(DT, &Input::default());
+        }
+        assert_eq!(simulation.platform.width(), 1.0);
+    }
+
+    #[test]
+    fn launches_leave_upwards_within_the_jitter() {
+        let config = GameConfig::default();
+        let mut simulation = simulation(&config);
+        let min_angle = config.ball.launch_angle - config.ball.launch_jitter;
+        let max_angle = config.ball.launch_angle + config.ball.launch_jitter;
+        let launch = Input {
+            launch: true,
+            ..Default::default()
+        };
+
+        for _ in 0..1000 {
+            simulation.balls[0].set_launch_from_platform(true);
+            simulation.step(DT, &Input::default());
+            simulation.step(DT, &launch);
+
+            let velocity = simulation.balls[0].velocity();
+            assert!(0.0 < velocity.y);
+            let angle = velocity.x.atan2(velocity.y).to_degrees();
+            assert!(min_angle - 1e-3 <= angle && angle <= max_angle + 1e-3);
+        }
+    }
+
+    #[test]
+    fn crate_dropping_on_destruction_drops_only_on_the_last_hit() {
+        let config = GameConfig {
+            power_up_chance: 0.0,
+            ..Default::default()
+        };
+        // The second crate keeps the level going
+        let mut level = Level::test(
+            CRATE_SIZE,
+            vec![
+                LevelCrate::test([0.0, 4.0], 10),
+                LevelCrate::test([-6.0, 8.0], 10),
+            ],
+        );
+        level.crates[0].health = 3;
+        level.crates[0].power_up_hit = Some(3);
+        let mut simulation = Simulation::new(
+            &config,
+            Some(&level),
+            SEED,
+            &mut InstanceAllocator::default(),
+        );
+
+        for _ in 0..2 {
+            simulation.crate_pack.hit(0);
+            simulation.step(DT, &Input::default());
+            assert!(simulation.power_ups.is_empty());
+        }
+        simulation.crate_pack.hit(0);
+        simulation.step(DT, &Input::default());
+        assert!(simulation.crate_pack.crates[0].is_disabled());
+        assert_eq!(simulation.power_ups.len(), 1);
+
+        // Hits on the destroyed crate do not drop another one
+        simulation.crate_pack.hit(0);
+        simulation.step(DT, &Input::default());
+        assert_eq!(simulation.power_ups.len(), 1);
+    }
+
+    #[test]
+    fn magnet_pulls_falling_power_ups_towards_the_platform() {
+        let pulled = |magnet| {
+            let config = GameConfig {
+                power_up_chance: 0.0,
+                power_up_magnet: magnet,
+                ..Default::default()
+            };
+            let mut simulation = simulation(&config);
+            simulation
+                .power_ups
+                .push(PowerUp::new(PowerUpKind::SlowBall, Vector2::new(4.0, 4.0)));
+            let mut xs = vec![];
+            for _ in 0..30 {
+                simulation.step(DT, &Input::default());
+                xs.push(simulation.power_ups[0].position().x);
+            }
+            xs
+        };
+
+        let platform_x = GameConfig::default().platform.position[0];
+        let with_magnet = pulled(2.0);
+        let mut previous = 4.0;
+        for x in with_magnet {
+            assert!(platform_x < x && x < previous);
+            previous = x;
+        }
+
+        let without_magnet = pulled(0.0);
+        assert!(without_magnet.iter().all(|&x| x == 4.0));
+    }
+
+    #[test]
+    fn stalled_ball_is_nudged_after_the_timeout_unless_progress_is_made() {
+        let config = GameConfig {
+            power_up_chance: 0.0,
+            stall_timeout: Some(0.5),
+            ..Default::default()
+        };
+        // Crates in the corners stay out of the way of the ball
+        let level = Level::test(
+            CRATE_SIZE,
+            vec![
+                LevelCrate::test([-6.0, 8.0], 10),
+                LevelCrate::test([6.0, 8.0], 10),
+            ],
+        );
+        let mut simulation = Simulation::new(
+            &config,
+            Some(&level),
+            SEED,
+            &mut InstanceAllocator::default(),
+        );
+        simulation.balls.clear();
+        simulation.spawn_ball(Vector3::new(0.0, -4.0, 0.0), Vector2::new(0.0, 2.5));
+        let straight_up = |simulation: &Simulation| simulation.balls[0].velocity().x == 0.0;
+
+        for _ in 0..55 {
+            simulation.step(DT, &Input::default());
+        }
+        assert!(straight_up(&simulation));
+        // Destroying a crate starts the timer over
+        simulation.crate_pack.hit(0);
+        simulation.step(DT, &Input::default());
+        for _ in 0..10 {
+            simulation.step(DT, &Input::default());
+        }
+        assert!(straight_up(&simulation));
+
+        for _ in 0..55 {
+            simulation.step(DT, &Input::default());
+        }
+        assert!(!straight_up(&simulation));
+    }
+
+    #[test]
+    fn destroyed_freeze_crate_stops_the_platform_for_the_duration() {
+        let config = GameConfig {
+            power_up_chance: 0.0,
+            paddle_freeze_duration: 0.5,
+            ..Default::default()
+        };
+        // The second crate keeps the level going
+        let mut level = Level::test(
+            CRATE_SIZE,
+            vec![
+                LevelCrate::test([0.0, 4.0], 10),
+                LevelCrate::test([-6.0, 8.0], 10),
+            ],
+        );
+        level.crates[0].kind = CrateKind::Freeze;
+        let mut simulation = Simulation::new(
+            &config,
+            Some(&level),
+            SEED,
+            &mut InstanceAllocator::default(),
+        );
+        let right = Input {
+            movement: 1.0,
+            ..Default::default()
+        };
+
+        simulation.crate_pack.hit(0);
+        simulation.step(DT, &right);
+        assert!(simulation.platform.is_frozen());
+        let frozen_x = simulation.platform.position().x;
+        for _ in 0..55 {
+            simulation.step(DT, &right);
+            assert_eq!(simulation.platform.position().x, frozen_x);
+        }
+
+        for _ in 0..30 {
+            simulation.step(DT, &right);
+        }
+        assert!(!simulation.platform.is_frozen());
+        assert!(frozen_x < simulation.platform.position().x);
+    }
+
+    #[test]
+    fn column_laser_destroys_only_the_targeted_column_after_the_warning() {
+        let config = GameConfig {
+            power_up_chance: 0.0,
+            column_laser_interval: Some(1.0),
+            ..Default::default()
+        };
+        let mut simulation = simulation(&config);
+        let target = |simulation: &Simulation| simulation.column_laser.as_ref().unwrap().target();
+
+        for _ in 0..200 {
+            if target(&simulation).is_some() {
+                break;
+            }
+            simulation.step(DT, &Input::default());
+        }
+        let column = target(&simulation).unwrap();
+        assert!(simulation.laser_warning().is_some());
+        assert!(simulation
+            .crate_pack
+            .crates
+            .iter()
+            .all(|c| !c.is_disabled()));
+
+        for _ in 0..200 {
+            simulation.step(DT, &Input::default());
+        }
+        assert_eq!(target(&simulation), None);
+        for (i, c) in simulation.crate_pack.crates.iter().enumerate() {
+            // Crates of the grid go column by column
+            let crate_column = (i as u32 / config.crates.rows) as i32;
+            assert_eq!(c.is_disabled(), crate_column == column, "crate {i}");
+        }
+        assert_eq!(simulation.score(), 0);
+    }
+
+    #[test]
+    fn ball_grows_with_the_combo_up_to_the_cap() {
+        let mut config = GameConfig::default();
+        config.ball.radius = 0.5;
+        config.ball.combo_growth = 0.1;
+        config.ball.max_combo_growth = 0.3;
+        let mut simulation = simulation(&config);
+        let size = |simulation: &Simulation| simulation.balls[0].border().width;
+        assert_eq!(size(&simulation), 1.0);
+
+        simulation.combo = 2;
+        simulation.scale_balls_by_combo();
+        assert!((size(&simulation) - 1.2).abs() < 1e-5);
+
+        simulation.combo = 10;
+        simulation.scale_balls_by_combo();
+        assert!((size(&simulation) - 1.3).abs() < 1e-5);
+        assert_eq!(simulation.balls[0].radius(), size(&simulation) / 2.0);
+
+        // Combo resets once the ball returns to the platform
+        simulation.balls.clear();
+        simulation.spawn_ball(Vector3::new(0.0, -7.0, 0.0), Vector2::new(0.0, -2.5));
+        for _ in 0..30 {
+            simulation.step(DT, &Input::default());
+        }
+        assert_eq!(simulation.combo(), 0);
+        assert_eq!(size(&simulation), 1.0);
+    }
+}
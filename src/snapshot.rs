@@ -0,0 +1,89 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::simulation::SimulationState;
+
+#[derive(Debug)]
+pub enum SnapshotError {
+    Io(std::io::Error),
+    Serialize(ron::Error),
+    Parse(ron::error::SpannedError),
+    // Snapshot was saved while playing a different level
+    LevelMismatch {
+        expected: Option<String>,
+        found: Option<String>,
+    },
+    // Crate layout of the config changed since the snapshot was saved
+    CrateMismatch {
+        expected: usize,
+        found: usize,
+    },
+}
+
+impl From<std::io::Error> for SnapshotError {
+    fn from(value: std::io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+impl From<ron::Error> for SnapshotError {
+    fn from(value: ron::Error) -> Self {
+        Self::Serialize(value)
+    }
+}
+
+impl From<ron::error::SpannedError> for SnapshotError {
+    fn from(value: ron::error::SpannedError) -> Self {
+        Self::Parse(value)
+    }
+}
+
+// Game in progress without any GPU resources, stored as RON
+// in the platform data directory so it can be continued later
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GameSnapshot {
+    // Path of the level, none for the crate grid from the config
+    pub level: Option<String>,
+    // Seed the simulation was created with, used for resets
+    pub seed: u64,
+    pub simulation: SimulationState,
+}
+
+impl GameSnapshot {
+    const FILE_NAME: &'static str = "save.ron";
+
+    fn path() -> Option<PathBuf> {
+        directories::ProjectDirs::from("", "", "breakout_zero")
+            .map(|dirs| dirs.data_dir().join(Self::FILE_NAME))
+    }
+
+    // Returns none if there is no saved game
+    pub fn load() -> Result<Option<Self>, SnapshotError> {
+        let Some(path) = Self::path().filter(|path| path.exists()) else {
+            return Ok(None);
+        };
+        let snapshot = std::fs::read_to_string(path)?;
+        Ok(Some(ron::from_str(&snapshot)?))
+    }
+
+    pub fn save(&self) -> Result<(), SnapshotError> {
+        let Some(path) = Self::path() else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let snapshot = ron::ser::to_string_pretty(self, Default::default())?;
+        std::fs::write(path, snapshot)?;
+        Ok(())
+    }
+
+    // Finished games can not be continued
+    pub fn delete() -> Result<(), SnapshotError> {
+        match Self::path().map(std::fs::remove_file) {
+            Some(Err(e)) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use zero::prelude::*;
+
+#[derive(Debug)]
+pub enum TextureError {
+    Image(image::ImageError),
+}
+
+impl From<image::ImageError> for TextureError {
+    fn from(value: image::ImageError) -> Self {
+        Self::Image(value)
+    }
+}
+
+// Layout of the bind group sampled by the textured instance pipeline:
+// a 2D texture and its sampler in group 1, after the camera
+pub struct TextureMaterial {
+    layout: BindGroupLayout,
+}
+
+impl TextureMaterial {
+    pub fn new(renderer: &Renderer) -> Self {
+        let layout = renderer
+            .device()
+            .create_bind_group_layout(&BindGroupLayoutDescriptor {
+                label: Some("texture_bind_group_layout"),
+                entries: &[
+                    BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: BindingType::Texture {
+                            multisampled: false,
+                            view_dimension: TextureViewDimension::D2,
+                            sample_type: TextureSampleType::Float { filterable: true },
+                        },
+                        count: None,
+                    },
+                    BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                ],
+            });
+        Self { layout }
+    }
+
+    #[inline]
+    pub fn layout(&self) -> &BindGroupLayout {
+        &self.layout
+    }
+
+    // Uploads the image at the `path` and inserts its bind group into the
+    // `storage`. The texture is kept alive by the bind group.
+    pub fn load(
+        &self,
+        renderer: &Renderer,
+        storage: &mut RenderStorage,
+        path: &str,
+    ) -> Result<ResourceId, TextureError> {
+        let image = image::open(path)?.into_rgba8();
+        Ok(self.upload(renderer, storage, path, &image))
+    }
+
+    // Same as `load` for an image already in memory
+    pub fn upload(
+        &self,
+        renderer: &Renderer,
+        storage: &mut RenderStorage,
+        label: &str,
+        image: &image::RgbaImage,
+    ) -> ResourceId {
+        let (width, height) = image.dimensions();
+        let size = Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+        let texture = renderer.device().create_texture(&TextureDescriptor {
+            label: Some(label),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::Rgba8UnormSrgb,
+            usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        renderer.queue().write_texture(
+            ImageCopyTexture {
+                texture: &texture,
+                mip_level: 0,
+                origin: Origin3d::ZERO,
+                aspect: TextureAspect::All,
+            },
+            image,
+            ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * width),
+                rows_per_image: Some(height),
+            },
+            size,
+        );
+
+        let view = texture.create_view(&TextureViewDescriptor::default());
+        // Small sprites are magnified a lot, so they are kept sharp
+        let sampler = renderer.device().create_sampler(&SamplerDescriptor {
+            label: Some("texture_sampler"),
+            mag_filter: FilterMode::Nearest,
+            min_filter: FilterMode::Linear,
+            ..Default::default()
+        });
+        let bind_group = renderer.device().create_bind_group(&BindGroupDescriptor {
+            label: Some("texture_bind_group"),
+            layout: &self.layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(&view),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::Sampler(&sampler),
+                },
+            ],
+        });
+        storage.insert_bind_group(bind_group)
+    }
+}
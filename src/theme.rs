@@ -0,0 +1,77 @@
+// Colors of everything drawn from the config. Crates
+// of levels and images keep their own colors.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Theme {
+    pub name: &'static str,
+    pub border: [f32; 4],
+    pub inner: [f32; 4],
+    pub ball: [f32; 4],
+    pub platform: [f32; 4],
+    // Colors of crates in each row counting from the bottom.
+    // Rows past the end of the list use the last color.
+    pub crate_tiers: Vec<[f32; 4]>,
+    pub hud: [f32; 4],
+}
+
+impl Theme {
+    pub fn classic() -> Self {
+        Self {
+            name: "CLASSIC",
+            border: [0.7, 0.7, 0.7, 1.0],
+            inner: [0.0, 0.0, 0.0, 1.0],
+            ball: [0.0, 0.9, 0.18, 1.0],
+            platform: [0.9, 0.16, 0.21, 1.0],
+            crate_tiers: vec![[0.5, 0.5, 0.5, 1.0]],
+            hud: [1.0, 1.0, 1.0, 1.0],
+        }
+    }
+
+    // Okabe-Ito colors, which stay distinct
+    // for all common kinds of color blindness
+    pub fn color_blind() -> Self {
+        Self {
+            name: "COLOR BLIND",
+            border: [0.6, 0.6, 0.6, 1.0],
+            inner: [0.0, 0.0, 0.0, 1.0],
+            ball: [0.94, 0.89, 0.26, 1.0],
+            platform: [0.34, 0.71, 0.91, 1.0],
+            crate_tiers: vec![
+                [0.0, 0.45, 0.7, 1.0],
+                [0.0, 0.62, 0.45, 1.0],
+                [0.9, 0.62, 0.0, 1.0],
+                [0.84, 0.37, 0.0, 1.0],
+                [0.8, 0.47, 0.65, 1.0],
+            ],
+            hud: [1.0, 1.0, 1.0, 1.0],
+        }
+    }
+
+    pub fn builtins() -> [Self; 2] {
+        [Self::classic(), Self::color_blind()]
+    }
+
+    // Built-in theme after this one, wrapping around.
+    // Custom themes are followed by the first built-in one.
+    pub fn next(&self) -> Self {
+        let builtins = Self::builtins();
+        let next = builtins
+            .iter()
+            .position(|theme| theme.name == self.name)
+            .map_or(0, |i| (i + 1) % builtins.len());
+        builtins[next].clone()
+    }
+
+    pub fn crate_color(&self, row: u32) -> [f32; 4] {
+        self.crate_tiers
+            .get(row as usize)
+            .or(self.crate_tiers.last())
+            .copied()
+            .unwrap_or([1.0, 1.0, 1.0, 1.0])
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::classic()
+    }
+}